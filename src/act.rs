@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::thread::sleep;
@@ -14,7 +15,7 @@ use chrono::DateTime;
 use messages::{Message, MessageType, Parses};
 use messages::parser::MessageParser;
 use messages::parts::NamePart;
-use messages::entries::Entry;
+use messages::entries::{Entry, Provenance};
 
 pub struct ActReader {
   path: PathBuf,
@@ -42,8 +43,10 @@ impl ActReader {
     let (tx, rx) = channel();
     let mut reader = BufReader::new(f);
     let stop = self.stop;
+    let path = self.path.clone();
     thread::spawn(move || {
       let mut con = String::new();
+      let mut line = 0;
       while let Ok(size) = reader.read_line(&mut con) {
         let mut content = String::new();
         mem::swap(&mut content, &mut con);
@@ -55,6 +58,7 @@ impl ActReader {
             continue;
           }
         }
+        line += 1;
 
         let mut parts = content.split('|');
 
@@ -87,7 +91,13 @@ impl ActReader {
           message_type,
           timestamp,
           sender,
-          message
+          message,
+          tags: BTreeMap::new(),
+          provenance: Some(Provenance::Packet {
+            path: path.clone(),
+            line,
+            captured: datetime.timestamp() * 1000 + datetime.timestamp_subsec_millis() as i64
+          })
         }).unwrap();
       }
     });
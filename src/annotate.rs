@@ -0,0 +1,235 @@
+//! Annotating entries with tags
+//!
+//! Analysis passes can attach [tags](../messages/entries/struct.Entry.html#structfield.tags) to
+//! entries instead of wrapping them in new types. Tags are kept when entries are serialized, so
+//! later passes and other programs can see what earlier ones found.
+//!
+//! # Examples
+//! Closures can be used as annotators.
+//!
+//! ```rust,no_run
+//! let mut annotators = Annotators::new();
+//! annotators.add(LanguageAnnotator);
+//! annotators.add(|entry: &mut Entry| if entry.message.plain_text().contains("WTS") {
+//!   entry.tag("spam", "");
+//! });
+//! for entry in annotators.apply(reader.iter()) {
+//!   println!("{:?}", entry.tags);
+//! }
+//! ```
+
+use config::HighlightRule;
+use events::FromEntry;
+use events::duty::DutyEvent;
+use localization;
+use messages::MessageType;
+use messages::entries::Entry;
+use pipeline::Sink;
+use similar::SimilarityIndex;
+
+/// Something that attaches tags to entries.
+///
+/// Annotators see entries in order, so they can keep state between them.
+pub trait Annotator: Send {
+  /// Attaches any tags that apply to the entry.
+  fn annotate(&mut self, entry: &mut Entry);
+}
+
+impl<F> Annotator for F
+  where F: FnMut(&mut Entry) + Send
+{
+  fn annotate(&mut self, entry: &mut Entry) {
+    self(entry)
+  }
+}
+
+/// A list of annotators run one after another.
+#[derive(Default)]
+pub struct Annotators {
+  annotators: Vec<Box<dyn Annotator>>
+}
+
+impl Annotators {
+  pub fn new() -> Self {
+    Annotators::default()
+  }
+
+  /// Adds an annotator. Annotators run in the order they were added.
+  pub fn add<A>(&mut self, annotator: A)
+    where A: Annotator + 'static
+  {
+    self.annotators.push(Box::new(annotator));
+  }
+
+  /// Runs every annotator on the entry.
+  pub fn annotate(&mut self, entry: &mut Entry) {
+    for annotator in &mut self.annotators {
+      annotator.annotate(entry);
+    }
+  }
+
+  /// Annotates every entry from an iterator.
+  pub fn apply<I>(self, entries: I) -> Annotated<I::IntoIter>
+    where I: IntoIterator<Item = Entry>
+  {
+    Annotated {
+      annotators: self,
+      entries: entries.into_iter()
+    }
+  }
+}
+
+/// The iterator returned by [`Annotators::apply`](struct.Annotators.html#method.apply).
+pub struct Annotated<I> {
+  annotators: Annotators,
+  entries: I
+}
+
+impl<I> Iterator for Annotated<I>
+  where I: Iterator<Item = Entry>
+{
+  type Item = Entry;
+
+  fn next(&mut self) -> Option<Entry> {
+    let mut entry = opt!(self.entries.next());
+    self.annotators.annotate(&mut entry);
+    Some(entry)
+  }
+}
+
+/// A sink that annotates entries before passing them on to another sink.
+pub struct AnnotatingSink<S> {
+  annotators: Annotators,
+  sink: S
+}
+
+impl<S: Sink> AnnotatingSink<S> {
+  pub fn new(annotators: Annotators, sink: S) -> Self {
+    AnnotatingSink {
+      annotators,
+      sink
+    }
+  }
+}
+
+impl<S: Sink> Sink for AnnotatingSink<S> {
+  fn handle(&mut self, entry: &Entry) {
+    let mut entry = entry.clone();
+    self.annotators.annotate(&mut entry);
+    self.sink.handle(&entry);
+  }
+
+  fn flush(&mut self) {
+    self.sink.flush();
+  }
+
+  fn sync(&mut self) {
+    self.sink.sync();
+  }
+}
+
+/// Tags entries matching highlight rules with `mention`, set to the names of the rules that
+/// matched, separated by commas.
+#[derive(Debug, Clone)]
+pub struct HighlightAnnotator {
+  pub rules: Vec<HighlightRule>
+}
+
+impl Annotator for HighlightAnnotator {
+  fn annotate(&mut self, entry: &mut Entry) {
+    let names: Vec<&str> = self.rules.iter()
+      .filter(|x| x.matches(entry))
+      .map(|x| x.name.as_str())
+      .collect();
+    if !names.is_empty() {
+      let names = names.join(",");
+      entry.tag("mention", names);
+    }
+  }
+}
+
+/// Tags system messages matching a known template with `language`, set to the client language's
+/// code.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LanguageAnnotator;
+
+impl Annotator for LanguageAnnotator {
+  fn annotate(&mut self, entry: &mut Entry) {
+    if let Some(m) = localization::match_entry(entry) {
+      entry.tag("language", m.language.code());
+    }
+  }
+}
+
+/// Tags entries seen during a duty with `duty`, set to the name of the duty.
+#[derive(Debug, Clone, Default)]
+pub struct DutyAnnotator {
+  current: Option<String>
+}
+
+impl Annotator for DutyAnnotator {
+  fn annotate(&mut self, entry: &mut Entry) {
+    match DutyEvent::from_entry(entry) {
+      Some(DutyEvent::Commenced { duty }) => self.current = Some(duty),
+      Some(DutyEvent::Ended { duty }) => {
+        self.current = None;
+        entry.tag("duty", duty);
+        return;
+      },
+      _ => {}
+    }
+    if let Some(ref duty) = self.current {
+      entry.tag("duty", duty.as_str());
+    }
+  }
+}
+
+/// How long, in seconds, the spam annotator remembers a message after it was last repeated.
+pub const DEFAULT_SPAM_WINDOW: u32 = 60 * 60;
+
+/// Tags repeated messages with `spam`, set to the number of times the message has been seen.
+///
+/// Messages are grouped with a [`SimilarityIndex`](../similar/struct.SimilarityIndex.html), so small
+/// changes between repeats don't hide them. Messages not repeated for
+/// [`DEFAULT_SPAM_WINDOW`](constant.DEFAULT_SPAM_WINDOW.html) are forgotten, so the index doesn't
+/// grow for as long as the annotator runs.
+#[derive(Debug, Clone)]
+pub struct SpamAnnotator {
+  /// The number of times a message must be seen before it is tagged.
+  pub min_repeats: usize,
+  /// The channels to look for spam in.
+  pub channels: Vec<MessageType>,
+  pub index: SimilarityIndex
+}
+
+impl SpamAnnotator {
+  /// Create an annotator tagging messages repeated three times in say, shout, or yell.
+  pub fn new() -> Self {
+    let mut index = SimilarityIndex::new();
+    index.window = Some(DEFAULT_SPAM_WINDOW);
+    SpamAnnotator {
+      min_repeats: 3,
+      channels: vec![MessageType::Say, MessageType::Shout, MessageType::Yell],
+      index
+    }
+  }
+}
+
+impl Default for SpamAnnotator {
+  fn default() -> Self {
+    SpamAnnotator::new()
+  }
+}
+
+impl Annotator for SpamAnnotator {
+  fn annotate(&mut self, entry: &mut Entry) {
+    if !self.channels.contains(&entry.message_type) {
+      return;
+    }
+    let cluster = opt_or!(self.index.add(entry), return);
+    let count = self.index.cluster(cluster).map(|x| x.count()).unwrap_or(0);
+    if count >= self.min_repeats {
+      entry.tag("spam", count.to_string());
+    }
+  }
+}
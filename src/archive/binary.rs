@@ -0,0 +1,559 @@
+//! The crate's own binary archive format
+//!
+//! Binary files (`.ffxb`) are meant for keeping logs for a long time. They hold the same entries as
+//! JSON lines files in far less space, and can still be read a piece at a time.
+//!
+//! A file starts with the magic bytes `FFXB`, a version byte, and a length-prefixed
+//! [metadata header](../../schema/index.html) in JSON. Entries follow in frames of up to
+//! [`FRAME_ENTRIES`](constant.FRAME_ENTRIES.html) entries, each compressed with zstd on its own.
+//! Inside a frame, timestamps are stored as differences from the one before, and each sender is
+//! stored once and referred to by number. The file ends with a seek table giving the offset and time
+//! range of every frame, then the offset of the seek table, the number of frames, and `FFXB` again.
+//!
+//! Because of the seek table, [`BinaryReader::query`](struct.BinaryReader.html#method.query) only
+//! decompresses the frames that overlap the time range asked for.
+
+use messages::Message;
+use messages::entries::{Entry, Provenance};
+use messages::parts::Part;
+use schema::Metadata;
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// The bytes binary files start and end with.
+pub const MAGIC: &'static [u8; 4] = b"FFXB";
+
+/// The version of the binary format written.
+pub const VERSION: u8 = 1;
+
+/// The most entries put in one frame.
+pub const FRAME_ENTRIES: usize = 4096;
+
+/// The zstd compression level used for frames.
+pub const COMPRESSION_LEVEL: i32 = 19;
+
+/// The fewest bytes an entry can take up in a frame: the timestamp, type, sender, message length,
+/// and extras length.
+const MIN_ENTRY_BYTES: u64 = 5;
+
+/// The bytes each frame takes up in the seek table.
+const SEEK_ENTRY_BYTES: u64 = 8 + 4 * 4;
+
+/// The bytes at the end of the file after the seek table.
+const FOOTER_BYTES: u64 = 8 + 4 + 4;
+
+/// Where a frame is in a binary file and what it holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameInfo {
+  /// The offset of the compressed frame in the file.
+  pub offset: u64,
+  /// The length of the compressed frame.
+  pub length: u32,
+  /// The earliest timestamp in the frame.
+  pub first_timestamp: u32,
+  /// The latest timestamp in the frame.
+  pub last_timestamp: u32,
+  /// The number of entries in the frame.
+  pub entries: u32
+}
+
+impl FrameInfo {
+  /// Checks if the frame has any entries in the time range.
+  pub fn overlaps(&self, range: &Range<u32>) -> bool {
+    self.first_timestamp < range.end && self.last_timestamp >= range.start
+  }
+}
+
+/// The parts of an entry that are rare enough to be stored as JSON.
+#[derive(Serialize, Deserialize)]
+struct Extras {
+  #[serde(default)]
+  tags: BTreeMap<String, String>,
+  #[serde(default)]
+  provenance: Option<Provenance>
+}
+
+/// Writes entries to a binary file.
+///
+/// Entries are buffered until a frame is full. `finish` must be called to write the last frame and
+/// the seek table, or the file can't be read.
+pub struct BinaryWriter<W: Write> {
+  writer: W,
+  offset: u64,
+  pending: Vec<Entry>,
+  frames: Vec<FrameInfo>
+}
+
+impl<W: Write> BinaryWriter<W> {
+  /// Starts a binary file, writing its header.
+  pub fn new(mut writer: W, metadata: &Metadata) -> io::Result<Self> {
+    let metadata = ::serde_json::to_vec(metadata).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    writer.write_all(MAGIC)?;
+    writer.write_u8(VERSION)?;
+    writer.write_u32::<LittleEndian>(metadata.len() as u32)?;
+    writer.write_all(&metadata)?;
+    Ok(BinaryWriter {
+      writer,
+      offset: (MAGIC.len() + 1 + 4 + metadata.len()) as u64,
+      pending: Vec::with_capacity(FRAME_ENTRIES),
+      frames: Vec::new()
+    })
+  }
+
+  /// Writes an entry.
+  ///
+  /// Entries don't have to be in timestamp order, but files of sorted entries are smaller and
+  /// faster to query.
+  pub fn write(&mut self, entry: &Entry) -> io::Result<()> {
+    self.pending.push(entry.clone());
+    if self.pending.len() >= FRAME_ENTRIES {
+      self.write_frame()?;
+    }
+    Ok(())
+  }
+
+  /// Writes the last frame and the seek table, returning the writer.
+  pub fn finish(mut self) -> io::Result<W> {
+    self.write_frame()?;
+    let table_offset = self.offset;
+    for frame in &self.frames {
+      self.writer.write_u64::<LittleEndian>(frame.offset)?;
+      self.writer.write_u32::<LittleEndian>(frame.length)?;
+      self.writer.write_u32::<LittleEndian>(frame.first_timestamp)?;
+      self.writer.write_u32::<LittleEndian>(frame.last_timestamp)?;
+      self.writer.write_u32::<LittleEndian>(frame.entries)?;
+    }
+    self.writer.write_u64::<LittleEndian>(table_offset)?;
+    self.writer.write_u32::<LittleEndian>(self.frames.len() as u32)?;
+    self.writer.write_all(MAGIC)?;
+    self.writer.flush()?;
+    Ok(self.writer)
+  }
+
+  fn write_frame(&mut self) -> io::Result<()> {
+    if self.pending.is_empty() {
+      return Ok(());
+    }
+    let data = encode_frame(&self.pending)?;
+    let compressed = ::zstd::encode_all(Cursor::new(data), COMPRESSION_LEVEL)?;
+    self.writer.write_all(&compressed)?;
+    self.frames.push(FrameInfo {
+      offset: self.offset,
+      length: compressed.len() as u32,
+      first_timestamp: self.pending.iter().map(|x| x.timestamp).min().unwrap_or(0),
+      last_timestamp: self.pending.iter().map(|x| x.timestamp).max().unwrap_or(0),
+      entries: self.pending.len() as u32
+    });
+    self.offset += compressed.len() as u64;
+    self.pending.clear();
+    Ok(())
+  }
+}
+
+fn encode_frame(entries: &[Entry]) -> io::Result<Vec<u8>> {
+  let to_json = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+  // Intern the senders first, so the frame can start with them.
+  let mut senders: Vec<String> = Vec::new();
+  let mut sender_ids: HashMap<String, u64> = HashMap::new();
+  let mut ids = Vec::with_capacity(entries.len());
+  for entry in entries {
+    let id = match entry.sender {
+      Some(ref sender) => {
+        let json = ::serde_json::to_string(sender).map_err(&to_json)?;
+        let next = senders.len() as u64 + 1;
+        *sender_ids.entry(json.clone()).or_insert_with(|| {
+          senders.push(json);
+          next
+        })
+      },
+      None => 0
+    };
+    ids.push(id);
+  }
+
+  let mut data = Vec::new();
+  write_varint(&mut data, senders.len() as u64);
+  for sender in &senders {
+    write_bytes(&mut data, sender.as_bytes());
+  }
+  let mut last_timestamp = 0i64;
+  for (entry, id) in entries.iter().zip(ids) {
+    let delta = entry.timestamp as i64 - last_timestamp;
+    last_timestamp = entry.timestamp as i64;
+    // Zigzag encoding keeps small negative deltas small.
+    write_varint(&mut data, ((delta << 1) ^ (delta >> 63)) as u64);
+    data.push(entry.message_type.id());
+    write_varint(&mut data, id);
+    write_bytes(&mut data, &::serde_json::to_vec(&entry.message).map_err(&to_json)?);
+    if entry.tags.is_empty() && entry.provenance.is_none() {
+      write_varint(&mut data, 0);
+    } else {
+      let extras = Extras {
+        tags: entry.tags.clone(),
+        provenance: entry.provenance.clone()
+      };
+      write_bytes(&mut data, &::serde_json::to_vec(&extras).map_err(&to_json)?);
+    }
+  }
+  Ok(data)
+}
+
+fn decode_frame(data: &[u8], count: u32) -> io::Result<Vec<Entry>> {
+  let invalid = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+  let mut cursor = Cursor::new(data);
+  let sender_count = read_varint(&mut cursor)?;
+  // Every sender takes at least a byte and every entry a few, so counts that can't fit are corrupt
+  // and shouldn't be allocated for.
+  if sender_count > remaining(&cursor) || count as u64 * MIN_ENTRY_BYTES > remaining(&cursor) {
+    return Err(corrupt("frame is shorter than its counts"));
+  }
+  let mut senders: Vec<Part> = Vec::with_capacity(sender_count as usize);
+  for _ in 0..sender_count {
+    let bytes = read_bytes(&mut cursor)?;
+    senders.push(::serde_json::from_slice(&bytes).map_err(&invalid)?);
+  }
+  let mut entries = Vec::with_capacity(count as usize);
+  let mut timestamp = 0i64;
+  for _ in 0..count {
+    let zigzag = read_varint(&mut cursor)?;
+    timestamp = timestamp.wrapping_add(((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64));
+    if timestamp < 0 || timestamp > ::std::u32::MAX as i64 {
+      return Err(corrupt("timestamp out of range"));
+    }
+    let message_type = cursor.read_u8()?;
+    let sender = match read_varint(&mut cursor)? {
+      0 => None,
+      id => match senders.get(id as usize - 1) {
+        Some(s) => Some(s.clone()),
+        None => return Err(corrupt("sender out of range"))
+      }
+    };
+    let message: Message = ::serde_json::from_slice(&read_bytes(&mut cursor)?).map_err(&invalid)?;
+    let extras = read_bytes(&mut cursor)?;
+    let extras = if extras.is_empty() {
+      Extras {
+        tags: BTreeMap::new(),
+        provenance: None
+      }
+    } else {
+      ::serde_json::from_slice(&extras).map_err(&invalid)?
+    };
+    entries.push(Entry {
+      message_type: message_type.into(),
+      timestamp: timestamp as u32,
+      sender,
+      message,
+      tags: extras.tags,
+      provenance: extras.provenance
+    });
+  }
+  Ok(entries)
+}
+
+fn write_varint(data: &mut Vec<u8>, mut value: u64) {
+  loop {
+    let byte = (value & 0x7f) as u8;
+    value >>= 7;
+    if value == 0 {
+      data.push(byte);
+      return;
+    }
+    data.push(byte | 0x80);
+  }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+  let mut value = 0;
+  for shift in 0..10 {
+    let byte = reader.read_u8()?;
+    value |= ((byte & 0x7f) as u64) << (shift * 7);
+    if byte & 0x80 == 0 {
+      return Ok(value);
+    }
+  }
+  Err(io::Error::new(io::ErrorKind::InvalidData, "varint too long"))
+}
+
+fn write_bytes(data: &mut Vec<u8>, bytes: &[u8]) {
+  write_varint(data, bytes.len() as u64);
+  data.extend_from_slice(bytes);
+}
+
+fn read_bytes(cursor: &mut Cursor<&[u8]>) -> io::Result<Vec<u8>> {
+  let length = read_varint(cursor)?;
+  if length > remaining(cursor) {
+    return Err(corrupt("length runs past the end of the frame"));
+  }
+  let mut bytes = vec![0; length as usize];
+  cursor.read_exact(&mut bytes)?;
+  Ok(bytes)
+}
+
+/// The bytes left after the cursor.
+fn remaining(cursor: &Cursor<&[u8]>) -> u64 {
+  (cursor.get_ref().len() as u64).saturating_sub(cursor.position())
+}
+
+fn corrupt(message: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("corrupt binary archive file: {}", message))
+}
+
+/// Reads a binary file.
+pub struct BinaryReader {
+  path: PathBuf,
+  file: File,
+  metadata: Metadata,
+  frames: Vec<FrameInfo>
+}
+
+impl BinaryReader {
+  /// Opens a binary file, reading its header and seek table.
+  ///
+  /// Fails if the file is from a newer schema version than this crate can read, or if any length or
+  /// offset in it points past where it can.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<BinaryReader> {
+    let invalid = |e: &str| io::Error::new(io::ErrorKind::InvalidData, e.to_owned());
+    let path = path.as_ref().to_path_buf();
+    let mut file = File::open(&path)?;
+    let file_length = file.metadata()?.len();
+    let header_length = MAGIC.len() as u64 + 1 + 4;
+    if file_length < header_length + FOOTER_BYTES {
+      return Err(invalid("binary archive file is incomplete"));
+    }
+
+    let mut magic = [0; 4];
+    file.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+      return Err(invalid("not a binary archive file"));
+    }
+    let version = file.read_u8()?;
+    if version > VERSION {
+      return Err(invalid("binary archive file is from a newer version"));
+    }
+    let length = file.read_u32::<LittleEndian>()? as u64;
+    let body_start = header_length + length;
+    if body_start + FOOTER_BYTES > file_length {
+      return Err(corrupt("metadata runs past the end of the file"));
+    }
+    let mut metadata = vec![0; length as usize];
+    file.read_exact(&mut metadata)?;
+    let metadata: Metadata = ::serde_json::from_slice(&metadata).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    metadata.check().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let mut footer = [0; FOOTER_BYTES as usize];
+    file.seek(SeekFrom::End(-(FOOTER_BYTES as i64)))?;
+    file.read_exact(&mut footer)?;
+    if &footer[12..] != MAGIC {
+      return Err(invalid("binary archive file is incomplete"));
+    }
+    let table_offset = LittleEndian::read_u64(&footer[..8]);
+    let count = LittleEndian::read_u32(&footer[8..12]);
+    let table_end = table_offset.checked_add(count as u64 * SEEK_ENTRY_BYTES);
+    if table_offset < body_start || table_end != Some(file_length - FOOTER_BYTES) {
+      return Err(corrupt("seek table doesn't fit in the file"));
+    }
+    file.seek(SeekFrom::Start(table_offset))?;
+    let mut frames = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+      let frame = FrameInfo {
+        offset: file.read_u64::<LittleEndian>()?,
+        length: file.read_u32::<LittleEndian>()?,
+        first_timestamp: file.read_u32::<LittleEndian>()?,
+        last_timestamp: file.read_u32::<LittleEndian>()?,
+        entries: file.read_u32::<LittleEndian>()?
+      };
+      let end = frame.offset.checked_add(frame.length as u64);
+      if frame.offset < body_start || end.map(|x| x > table_offset).unwrap_or(true) {
+        return Err(corrupt("frame doesn't fit in the file"));
+      }
+      frames.push(frame);
+    }
+    Ok(BinaryReader {
+      path,
+      file,
+      metadata,
+      frames
+    })
+  }
+
+  /// The metadata header of the file.
+  pub fn metadata(&self) -> &Metadata {
+    &self.metadata
+  }
+
+  /// The frames in the file, in the order they were written.
+  pub fn frames(&self) -> &[FrameInfo] {
+    &self.frames
+  }
+
+  /// The number of entries in the file.
+  pub fn len(&self) -> usize {
+    self.frames.iter().map(|x| x.entries as usize).sum()
+  }
+
+  /// Checks if the file has no entries.
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  /// Reads the entries in a frame.
+  ///
+  /// Each entry without a provenance gets the file and the offset of its frame.
+  pub fn read_frame(&mut self, index: usize) -> io::Result<Vec<Entry>> {
+    let frame = match self.frames.get(index) {
+      Some(f) => *f,
+      None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "no such frame"))
+    };
+    self.file.seek(SeekFrom::Start(frame.offset))?;
+    let mut compressed = vec![0; frame.length as usize];
+    self.file.read_exact(&mut compressed)?;
+    let data = ::zstd::decode_all(Cursor::new(compressed))?;
+    let mut entries = decode_frame(&data, frame.entries)?;
+    for entry in &mut entries {
+      if entry.provenance.is_none() {
+        entry.provenance = Some(Provenance::File {
+          path: self.path.clone(),
+          offset: frame.offset
+        });
+      }
+    }
+    Ok(entries)
+  }
+
+  /// Reads the entries in the time range, only decompressing the frames that overlap it.
+  pub fn query(&mut self, range: Range<u32>) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for i in 0..self.frames.len() {
+      if !self.frames[i].overlaps(&range) {
+        continue;
+      }
+      entries.extend(self.read_frame(i)?.into_iter().filter(|x| x.timestamp >= range.start && x.timestamp < range.end));
+    }
+    Ok(entries)
+  }
+
+  /// Reads every entry in the file.
+  pub fn entries(&mut self) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::with_capacity(self.len());
+    for i in 0..self.frames.len() {
+      entries.extend(self.read_frame(i)?);
+    }
+    Ok(entries)
+  }
+}
+
+/// Reads every entry in a binary file.
+pub fn read_entries<P: AsRef<Path>>(path: P) -> io::Result<Vec<Entry>> {
+  BinaryReader::open(path)?.entries()
+}
+
+/// Writes entries to a new binary file.
+pub fn write_file<'a, P, I>(path: P, metadata: &Metadata, entries: I) -> io::Result<()>
+  where P: AsRef<Path>,
+        I: IntoIterator<Item = &'a Entry>
+{
+  let mut writer = BinaryWriter::new(BufWriter::new(File::create(path)?), metadata)?;
+  for entry in entries {
+    writer.write(entry)?;
+  }
+  writer.finish().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use messages::MessageType;
+
+  use std::env;
+  use std::fs;
+
+  fn entry(timestamp: u32, sender: Option<&str>, text: &str) -> Entry {
+    Entry {
+      message_type: MessageType::Say,
+      timestamp,
+      sender: sender.map(|x| Part::PlainText(x.to_owned())),
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("ffxiv_reader_binary_{}_{}.ffxb", name, ::std::process::id()))
+  }
+
+  fn sample() -> Vec<Entry> {
+    let mut entries = vec![
+      entry(1_500_000_000, Some("Some Name"), "hello"),
+      entry(1_500_000_010, None, "You obtain 10 gil."),
+      // Out of order, so the deltas go negative.
+      entry(1_499_999_990, Some("Some Name"), "earlier")
+    ];
+    entries[1].tag("spam", "");
+    entries
+  }
+
+  #[test]
+  fn round_trip() {
+    let path = temp_path("round_trip");
+    let entries = sample();
+    write_file(&path, &Metadata::new(Some("test")), &entries).unwrap();
+    let mut reader = BinaryReader::open(&path).unwrap();
+    assert_eq!(reader.len(), entries.len());
+    let read = reader.entries().unwrap();
+    assert_eq!(read.len(), entries.len());
+    for (a, b) in entries.iter().zip(read.iter()) {
+      assert_eq!(a.dedup_key(), b.dedup_key());
+      assert_eq!(a.tags, b.tags);
+      assert!(b.provenance.is_some());
+    }
+    let queried = reader.query(1_500_000_000..1_500_000_005).unwrap();
+    assert_eq!(queried.len(), 1);
+    assert_eq!(queried[0].timestamp, 1_500_000_000);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn truncated_files_are_errors() {
+    let path = temp_path("truncated");
+    write_file(&path, &Metadata::new(Some("test")), &sample()).unwrap();
+    let data = fs::read(&path).unwrap();
+    for length in 0..data.len() {
+      fs::write(&path, &data[..length]).unwrap();
+      assert!(BinaryReader::open(&path).and_then(|mut x| x.entries()).is_err(), "length {}", length);
+    }
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn corrupt_footer_is_an_error() {
+    let path = temp_path("footer");
+    write_file(&path, &Metadata::new(Some("test")), &sample()).unwrap();
+    let mut data = fs::read(&path).unwrap();
+    let count = data.len() - 8;
+    LittleEndian::write_u32(&mut data[count..count + 4], ::std::u32::MAX);
+    fs::write(&path, &data).unwrap();
+    assert!(BinaryReader::open(&path).is_err());
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn corrupt_frames_are_errors() {
+    // A sender count far larger than the frame.
+    assert!(decode_frame(&[0xff, 0xff, 0xff, 0xff, 0x0f], 0).is_err());
+    // An entry count far larger than the frame.
+    assert!(decode_frame(&[0x00], ::std::u32::MAX).is_err());
+    // A message length far larger than the frame.
+    assert!(decode_frame(&[0x00, 0x00, 0x0a, 0x00, 0xff, 0xff, 0xff, 0xff, 0x0f, 0x00], 1).is_err());
+    // A varint that never ends.
+    assert!(decode_frame(&[0xff; 16], 1).is_err());
+    // A timestamp before the epoch.
+    assert!(decode_frame(&[0x00, 0x01, 0x0a, 0x00, 0x00, 0x00], 1).is_err());
+  }
+}
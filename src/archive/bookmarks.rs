@@ -0,0 +1,174 @@
+//! Bookmarks and notes on entries
+//!
+//! An archive can keep a sidecar file of [`Bookmarks`](struct.Bookmarks.html) next to its index.
+//! Each bookmark is keyed by an entry's
+//! [`dedup_key`](../../messages/entries/struct.Entry.html#method.dedup_key), so it still points at
+//! the same entry after the archive is compacted, converted, or reindexed. The
+//! [renderers](../../render/index.html) can mark bookmarked entries and show their notes.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let archive = Archive::open("logs").unwrap();
+//! let mut bookmarks = archive.bookmarks().unwrap();
+//! bookmarks.add(&entry, Some("first clear!"));
+//! bookmarks.save().unwrap();
+//! ```
+
+use archive::Archive;
+use filter::EntryFilter;
+use messages::entries::Entry;
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The name of the bookmarks file kept in the root of an archive.
+pub const BOOKMARKS_FILE: &'static str = ".ffxiv_reader_bookmarks.json";
+
+/// A bookmarked entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+  /// The `dedup_key` of the entry.
+  pub key: u64,
+  /// The timestamp of the entry, so bookmarked entries can be found without reading everything.
+  pub timestamp: u32,
+  /// A note about the entry.
+  #[serde(default)]
+  pub note: Option<String>,
+  /// When the bookmark was made, in seconds since the epoch.
+  pub created: u64
+}
+
+/// The bookmarks in a sidecar file.
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+  path: PathBuf,
+  bookmarks: BTreeMap<u64, Bookmark>
+}
+
+impl Bookmarks {
+  /// Loads bookmarks from a file, or returns no bookmarks if the file doesn't exist.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Bookmarks> {
+    let path = path.as_ref().to_path_buf();
+    let mut data = String::new();
+    match File::open(&path) {
+      Ok(mut f) => f.read_to_string(&mut data)?,
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Bookmarks {
+        path,
+        bookmarks: BTreeMap::new()
+      }),
+      Err(e) => return Err(e)
+    };
+    let list: Vec<Bookmark> = ::serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(Bookmarks {
+      path,
+      bookmarks: list.into_iter().map(|x| (x.key, x)).collect()
+    })
+  }
+
+  /// Finds the bookmarks of the archive a file is in, looking for a bookmarks file in each of its
+  /// parent directories.
+  ///
+  /// Returns `None` if there is no bookmarks file.
+  pub fn find<P: AsRef<Path>>(path: P) -> Option<io::Result<Bookmarks>> {
+    path.as_ref().ancestors()
+      .map(|x| x.join(BOOKMARKS_FILE))
+      .find(|x| x.is_file())
+      .map(Bookmarks::open)
+  }
+
+  /// The path the bookmarks are saved to.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Saves the bookmarks, replacing the file atomically where the platform allows.
+  pub fn save(&self) -> io::Result<()> {
+    let list: Vec<&Bookmark> = self.iter().collect();
+    let data = ::serde_json::to_string_pretty(&list).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let temp = self.path.with_extension("tmp");
+    File::create(&temp)?.write_all(data.as_bytes())?;
+    fs::rename(&temp, &self.path)
+  }
+
+  /// Bookmarks an entry, replacing the note if it's already bookmarked.
+  pub fn add<S: Into<String>>(&mut self, entry: &Entry, note: Option<S>) -> &mut Bookmark {
+    let key = entry.dedup_key();
+    let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+    let bookmark = self.bookmarks.entry(key).or_insert_with(|| Bookmark {
+      key,
+      timestamp: entry.timestamp,
+      note: None,
+      created
+    });
+    bookmark.note = note.map(Into::into);
+    bookmark
+  }
+
+  /// Removes the bookmark on an entry, returning it if there was one.
+  pub fn remove(&mut self, entry: &Entry) -> Option<Bookmark> {
+    self.bookmarks.remove(&entry.dedup_key())
+  }
+
+  /// Gets the bookmark on an entry.
+  pub fn get(&self, entry: &Entry) -> Option<&Bookmark> {
+    self.bookmarks.get(&entry.dedup_key())
+  }
+
+  /// Gets a bookmark by the `dedup_key` of its entry.
+  pub fn get_key(&self, key: u64) -> Option<&Bookmark> {
+    self.bookmarks.get(&key)
+  }
+
+  /// Gets a bookmark to change its note.
+  pub fn get_mut(&mut self, entry: &Entry) -> Option<&mut Bookmark> {
+    self.bookmarks.get_mut(&entry.dedup_key())
+  }
+
+  /// Checks if an entry is bookmarked.
+  pub fn contains(&self, entry: &Entry) -> bool {
+    self.bookmarks.contains_key(&entry.dedup_key())
+  }
+
+  /// The number of bookmarks.
+  pub fn len(&self) -> usize {
+    self.bookmarks.len()
+  }
+
+  /// Checks if there are no bookmarks.
+  pub fn is_empty(&self) -> bool {
+    self.bookmarks.is_empty()
+  }
+
+  /// Every bookmark, in timestamp order.
+  pub fn iter(&self) -> ::std::vec::IntoIter<&Bookmark> {
+    let mut list: Vec<&Bookmark> = self.bookmarks.values().collect();
+    list.sort_by_key(|x| (x.timestamp, x.key));
+    list.into_iter()
+  }
+
+  /// Reads the bookmarked entries from an archive, in timestamp order.
+  ///
+  /// Only files covering a bookmark's timestamp are read. Bookmarks whose entries are no longer in
+  /// the archive are skipped.
+  pub fn entries(&self, archive: &Archive) -> Vec<(Entry, Bookmark)> {
+    let mut found = Vec::new();
+    let mut seen = HashSet::new();
+    let mut timestamps: Vec<u32> = self.bookmarks.values().map(|x| x.timestamp).collect();
+    timestamps.sort();
+    timestamps.dedup();
+    for timestamp in timestamps {
+      for entry in archive.query(timestamp..timestamp.saturating_add(1), EntryFilter::All) {
+        if let Some(bookmark) = self.get(&entry) {
+          if seen.insert(bookmark.key) {
+            found.push((entry, bookmark.clone()));
+          }
+        }
+      }
+    }
+    found
+  }
+}
@@ -0,0 +1,161 @@
+//! Compaction and retention for archives
+//!
+//! Compacting an archive reads every entry in it, drops duplicates and anything the retention policy
+//! no longer wants, and writes what is left to a new archive with one JSON lines file per month.
+
+use archive::{Archive, binary, jsonl};
+use filter::EntryFilter;
+use messages::MessageType;
+use messages::entries::Entry;
+use schema::Metadata;
+
+use chrono::{TimeZone, Utc};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How long to keep entries of certain types.
+#[derive(Debug, Clone)]
+pub struct RetentionRule {
+  /// The types this rule applies to. If this is empty, the rule applies to every type.
+  pub message_types: Vec<MessageType>,
+  /// How many days to keep entries for, or `None` to keep them forever.
+  pub max_age_days: Option<u32>
+}
+
+impl RetentionRule {
+  /// Checks if this rule applies to the entry.
+  pub fn applies_to(&self, entry: &Entry) -> bool {
+    self.message_types.is_empty() || self.message_types.contains(&entry.message_type)
+  }
+}
+
+/// A list of retention rules.
+///
+/// The first rule that applies to an entry decides whether it is kept. Entries that no rule applies
+/// to are always kept.
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+  pub rules: Vec<RetentionRule>
+}
+
+impl RetentionPolicy {
+  /// Checks if the entry should be kept at the time `now`, in seconds since the epoch.
+  pub fn keeps(&self, entry: &Entry, now: u32) -> bool {
+    let rule = opt_or!(self.rules.iter().find(|x| x.applies_to(entry)), return true);
+    match rule.max_age_days {
+      Some(days) => now.saturating_sub(entry.timestamp) as u64 <= days as u64 * 86400,
+      None => true
+    }
+  }
+}
+
+/// Options for [`compact`](fn.compact.html).
+#[derive(Debug, Clone)]
+pub struct CompactOptions {
+  /// Whether to drop entries with the same [`dedup_key`](../../messages/entries/struct.Entry.html#method.dedup_key).
+  pub deduplicate: bool,
+  /// Whether to compress the files written with xz.
+  pub compress: bool,
+  /// Whether to write files in the [binary format](../binary/index.html) instead of JSON lines.
+  ///
+  /// Binary files are already compressed, so `compress` is ignored.
+  pub binary: bool,
+  /// The retention policy to apply.
+  pub retention: RetentionPolicy,
+  /// Entries that don't match this filter are dropped, such as those from blocked names.
+  pub filter: EntryFilter,
+  /// The time to measure entry ages from, in seconds since the epoch.
+  pub now: u32,
+  /// The metadata header written at the start of each file.
+  pub metadata: Metadata
+}
+
+impl Default for CompactOptions {
+  fn default() -> Self {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs() as u32).unwrap_or(0);
+    CompactOptions {
+      deduplicate: true,
+      compress: false,
+      binary: false,
+      retention: RetentionPolicy::default(),
+      filter: EntryFilter::All,
+      now,
+      metadata: Metadata::new(None)
+    }
+  }
+}
+
+/// What happened during a compaction.
+#[derive(Debug, Clone, Default)]
+pub struct CompactStats {
+  /// The number of entries read from the archive.
+  pub read: usize,
+  /// The number of duplicate entries dropped.
+  pub duplicates: usize,
+  /// The number of entries dropped by the retention policy.
+  pub expired: usize,
+  /// The number of entries dropped by the filter.
+  pub filtered: usize,
+  /// The number of entries written.
+  pub written: usize,
+  /// The number of files written.
+  pub files: usize
+}
+
+/// Compacts an archive into the directory `output`.
+///
+/// Entries are sorted by timestamp and written to one file per month, named like `2017-06.jsonl`
+/// (or `2017-06.jsonl.xz` when compressing, or `2017-06.ffxb` in binary). The output directory is
+/// created if it doesn't exist, and the result can be opened with [`Archive::open`](../struct.Archive.html#method.open).
+///
+/// Every entry in the archive is held in memory while compacting. Returns an error without writing
+/// anything if `output` overlaps the archive, since the archive would then hold every entry twice.
+/// See [`Archive::check_output`](../struct.Archive.html#method.check_output).
+pub fn compact<P: AsRef<Path>>(archive: &Archive, output: P, options: &CompactOptions) -> io::Result<CompactStats> {
+  let output = output.as_ref();
+  archive.check_output(output)?;
+  fs::create_dir_all(output)?;
+
+  let mut stats = CompactStats::default();
+  let mut seen = HashSet::new();
+  let mut months: BTreeMap<String, Vec<Entry>> = BTreeMap::new();
+  for entry in archive.entries() {
+    stats.read += 1;
+    if options.deduplicate && !seen.insert(entry.dedup_key()) {
+      stats.duplicates += 1;
+      continue;
+    }
+    if !options.filter.matches(&entry) {
+      stats.filtered += 1;
+      continue;
+    }
+    if !options.retention.keeps(&entry, options.now) {
+      stats.expired += 1;
+      continue;
+    }
+    let month = Utc.timestamp(entry.timestamp as i64, 0).format("%Y-%m").to_string();
+    months.entry(month).or_insert_with(Vec::new).push(entry);
+  }
+
+  let extension = match (options.binary, options.compress) {
+    (true, _) => "ffxb",
+    (false, true) => "jsonl.xz",
+    (false, false) => "jsonl"
+  };
+  for (month, mut entries) in months {
+    entries.sort_by_key(|x| x.timestamp);
+    let path = output.join(format!("{}.{}", month, extension));
+    if options.binary {
+      binary::write_file(path, &options.metadata, &entries)?;
+    } else {
+      jsonl::write_file(path, &options.metadata, &entries, options.compress)?;
+    }
+    stats.written += entries.len();
+    stats.files += 1;
+  }
+  Ok(stats)
+}
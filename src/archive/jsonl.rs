@@ -0,0 +1,140 @@
+//! JSON lines files
+//!
+//! These are the files written by `output_json`, with one serialized entry per line. They may also
+//! be compressed with xz, in which case they end in `.jsonl.xz`.
+//!
+//! Files start with a [metadata header](../../schema/index.html). Reading a file with a header from a
+//! newer schema version fails instead of silently skipping entries it can't understand.
+
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+
+use messages::entries::{Entry, Provenance};
+use schema::Metadata;
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Reads every entry in a JSON lines file.
+///
+/// Lines that aren't entries are skipped. Entries without a provenance get the file and the offset
+/// of their line.
+pub fn read_entries<P: AsRef<Path>>(path: P) -> io::Result<Vec<Entry>> {
+  let path = path.as_ref();
+  read_traced(BufReader::new(File::open(path)?), Some(path))
+}
+
+/// Reads every entry in an xz-compressed JSON lines file.
+///
+/// Lines that aren't entries are skipped. Entries without a provenance get the file and the offset
+/// of their line in the decompressed data.
+pub fn read_compressed_entries<P: AsRef<Path>>(path: P) -> io::Result<Vec<Entry>> {
+  let path = path.as_ref();
+  read_traced(BufReader::new(XzDecoder::new(File::open(path)?)), Some(path))
+}
+
+/// Reads the metadata header of a JSON lines file, compressed or not.
+///
+/// Returns `None` for files written before headers existed.
+pub fn read_metadata<P: AsRef<Path>>(path: P) -> io::Result<Option<Metadata>> {
+  let path = path.as_ref();
+  let file = File::open(path)?;
+  let mut line = String::new();
+  if path.to_string_lossy().ends_with(".xz") {
+    BufReader::new(XzDecoder::new(file)).read_line(&mut line)?;
+  } else {
+    BufReader::new(file).read_line(&mut line)?;
+  }
+  Ok(Metadata::from_line(&line))
+}
+
+/// Checks the metadata header of JSON lines, if `line` is one.
+///
+/// Returns an error if the header is from a schema version that can't be read.
+pub fn check_header(line: &str) -> io::Result<Option<Metadata>> {
+  let metadata = opt_or!(Metadata::from_line(line), return Ok(None));
+  metadata.check().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+  Ok(Some(metadata))
+}
+
+/// Reads every entry from JSON lines.
+///
+/// Lines that aren't entries are skipped.
+pub fn read_from<R: BufRead>(reader: R) -> io::Result<Vec<Entry>> {
+  read_traced(reader, None)
+}
+
+fn read_traced<R: BufRead>(mut reader: R, path: Option<&Path>) -> io::Result<Vec<Entry>> {
+  let mut entries = Vec::new();
+  let mut line = String::new();
+  let mut offset = 0;
+  let mut first = true;
+  loop {
+    line.clear();
+    let size = reader.read_line(&mut line)?;
+    if size == 0 {
+      break;
+    }
+    let start = offset;
+    offset += size as u64;
+    let is_first = first;
+    first = false;
+    let line = line.trim_end();
+    if !line.starts_with('{') {
+      continue;
+    }
+    if is_first && check_header(line)?.is_some() {
+      continue;
+    }
+    if let Ok(mut entry) = ::serde_json::from_str::<Entry>(line) {
+      if entry.provenance.is_none() {
+        entry.provenance = path.map(|p| Provenance::File {
+          path: p.to_path_buf(),
+          offset: start
+        });
+      }
+      entries.push(entry);
+    }
+  }
+  Ok(entries)
+}
+
+/// Writes a metadata header line.
+pub fn write_header<W: Write>(writer: &mut W, metadata: &Metadata) -> io::Result<()> {
+  writer.write_all(metadata.to_line().as_bytes())?;
+  writer.write_all(b"\n")
+}
+
+/// Writes entries as JSON lines, one per line.
+pub fn write_entries<'a, W, I>(writer: &mut W, entries: I) -> io::Result<()>
+  where W: Write,
+        I: IntoIterator<Item = &'a Entry>
+{
+  for entry in entries {
+    ::serde_json::to_writer(writer, entry).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    writer.write_all(b"\n")?;
+  }
+  Ok(())
+}
+
+/// Writes entries to a new JSON lines file after a metadata header, compressing it with xz if
+/// `compress` is `true`.
+pub fn write_file<'a, P, I>(path: P, metadata: &Metadata, entries: I, compress: bool) -> io::Result<()>
+  where P: AsRef<Path>,
+        I: IntoIterator<Item = &'a Entry>
+{
+  let file = File::create(path)?;
+  if compress {
+    let mut encoder = XzEncoder::new(file, 6);
+    write_header(&mut encoder, metadata)?;
+    write_entries(&mut encoder, entries)?;
+    encoder.finish()?;
+  } else {
+    let mut writer = io::BufWriter::new(file);
+    write_header(&mut writer, metadata)?;
+    write_entries(&mut writer, entries)?;
+    writer.flush()?;
+  }
+  Ok(())
+}
@@ -0,0 +1,323 @@
+//! Archives of chat logs
+//!
+//! An archive is a directory, possibly with subdirectories, full of log files. The game's own log
+//! files, JSON lines files, and the crate's [binary format](binary/index.html) are supported. The
+//! archive keeps an index of the time range covered by each file, so queries only read the files
+//! they need. It can also keep [bookmarks](bookmarks/index.html) on entries.
+
+pub mod native;
+pub mod jsonl;
+pub mod compact;
+pub mod bookmarks;
+pub mod binary;
+
+use self::bookmarks::{Bookmarks, BOOKMARKS_FILE};
+
+use filter::EntryFilter;
+use messages::entries::Entry;
+
+use std::collections::VecDeque;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use std::vec;
+
+/// The name of the index file kept in the root of an archive.
+pub const INDEX_FILE: &'static str = ".ffxiv_reader_index.json";
+
+/// The format of a file in an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+  /// The game's own `.log` files.
+  Native,
+  /// `.jsonl` files with one entry per line.
+  JsonLines,
+  /// `.jsonl.xz` files, which are JSON lines files compressed with xz.
+  CompressedJsonLines,
+  /// `.ffxb` files in the crate's [binary format](binary/index.html).
+  Binary
+}
+
+impl Format {
+  /// Determines the format of a file from its extension.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+    let path = path.as_ref();
+    if path.to_string_lossy().ends_with(".jsonl.xz") {
+      return Some(Format::CompressedJsonLines);
+    }
+    match path.extension().and_then(|x| x.to_str()) {
+      Some("log") => Some(Format::Native),
+      Some("jsonl") => Some(Format::JsonLines),
+      Some("ffxb") => Some(Format::Binary),
+      _ => None
+    }
+  }
+
+  /// Reads every entry in a file of this format.
+  pub fn read_entries<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<Entry>> {
+    match *self {
+      Format::Native => native::read_entries(path),
+      Format::JsonLines => jsonl::read_entries(path),
+      Format::CompressedJsonLines => jsonl::read_compressed_entries(path),
+      Format::Binary => binary::read_entries(path)
+    }
+  }
+}
+
+/// What the index knows about a file in an archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMetadata {
+  /// The path of the file, relative to the root of the archive.
+  pub path: PathBuf,
+  /// The format of the file.
+  pub format: Format,
+  /// The size of the file when it was indexed.
+  pub size: u64,
+  /// The modification time of the file when it was indexed, in seconds since the epoch.
+  pub modified: u64,
+  /// The earliest timestamp in the file.
+  pub first_timestamp: u32,
+  /// The latest timestamp in the file.
+  pub last_timestamp: u32,
+  /// The number of entries in the file.
+  pub entries: usize
+}
+
+impl FileMetadata {
+  /// Checks if any of the entries in this file could be in the range.
+  pub fn overlaps(&self, range: &Range<u32>) -> bool {
+    self.entries > 0 && self.first_timestamp < range.end && self.last_timestamp >= range.start
+  }
+}
+
+/// An archive of log files.
+///
+/// # Examples
+/// This prints every tell received in 2017.
+///
+/// ```rust,no_run
+/// let archive = Archive::open("logs").unwrap();
+/// let filter = EntryFilter::MessageTypes(vec![MessageType::TellReceive]);
+/// for entry in archive.query(1483228800..1514764800, filter) {
+///   println!("{:?}", entry);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Archive {
+  root: PathBuf,
+  files: Vec<FileMetadata>
+}
+
+impl Archive {
+  /// Opens the archive rooted at `root`.
+  ///
+  /// The index is loaded and then refreshed, so any files that were added or changed since the
+  /// archive was last opened are indexed.
+  pub fn open<P: AsRef<Path>>(root: P) -> io::Result<Archive> {
+    let root = root.as_ref().to_path_buf();
+    let files = Archive::load_index(&root).unwrap_or_default();
+    let mut archive = Archive {
+      root,
+      files
+    };
+    archive.refresh()?;
+    Ok(archive)
+  }
+
+  /// The root directory of the archive.
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  /// The indexed files in the archive, in order of their earliest timestamp.
+  pub fn files(&self) -> &[FileMetadata] {
+    &self.files
+  }
+
+  /// Indexes any files that are new or have changed, and forgets files that no longer exist.
+  ///
+  /// Files that can't be read are left out of the index, so they are tried again on the next
+  /// refresh. Only failing to list the archive's directories is an error.
+  ///
+  /// The index is saved if anything changed. Failing to save the index is not an error, since it
+  /// will just be rebuilt next time. Returns whether anything changed.
+  pub fn refresh(&mut self) -> io::Result<bool> {
+    let mut paths = Vec::new();
+    walk(&self.root, &mut paths)?;
+    let mut changed = paths.len() != self.files.len();
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+      let format = match Format::from_path(&path) {
+        Some(f) => f,
+        None => continue
+      };
+      let metadata = try_or!(fs::metadata(&path), continue);
+      let size = metadata.len();
+      let modified = metadata.modified().ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+      let relative = path.strip_prefix(&self.root).unwrap_or(&path).to_path_buf();
+      let existing = self.files.iter().position(|x| x.path == relative && x.size == size && x.modified == modified);
+      if let Some(i) = existing {
+        files.push(self.files.swap_remove(i));
+        continue;
+      }
+      changed = true;
+      let entries = try_or!(format.read_entries(&path), continue);
+      files.push(FileMetadata {
+        path: relative,
+        format,
+        size,
+        modified,
+        first_timestamp: entries.iter().map(|x| x.timestamp).min().unwrap_or(0),
+        last_timestamp: entries.iter().map(|x| x.timestamp).max().unwrap_or(0),
+        entries: entries.len()
+      });
+    }
+    files.sort_by_key(|x| (x.first_timestamp, x.path.clone()));
+    self.files = files;
+    if changed {
+      let _ = self.save_index();
+    }
+    Ok(changed)
+  }
+
+  /// Queries the archive for entries in the time range that match the filter.
+  ///
+  /// Entries are returned file by file, in order of each file's earliest timestamp. Files that can't
+  /// be read are skipped.
+  pub fn query(&self, range: Range<u32>, filter: EntryFilter) -> ArchiveQuery {
+    let files = self.files.iter()
+      .filter(|x| x.overlaps(&range))
+      .map(|x| (self.root.join(&x.path), x.format))
+      .collect();
+    ArchiveQuery {
+      files,
+      current: Vec::new().into_iter(),
+      range,
+      filter
+    }
+  }
+
+  /// Iterates over every entry in the archive.
+  pub fn entries(&self) -> ArchiveQuery {
+    self.query(0..::std::u32::MAX, EntryFilter::All)
+  }
+
+  /// Checks that writing a copy of the archive to `output` won't touch the archive itself.
+  ///
+  /// Returns an error if `output` is the root of the archive, is inside it, or holds it, since
+  /// writing there would overwrite the files being read or mix the copy in with them.
+  pub fn check_output<P: AsRef<Path>>(&self, output: P) -> io::Result<()> {
+    let root = self.root.canonicalize()?;
+    let output = canonicalize_new(output.as_ref())?;
+    if output.starts_with(&root) || root.starts_with(&output) {
+      let message = format!("output {} overlaps the archive at {}", output.display(), root.display());
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+    Ok(())
+  }
+
+  /// Loads the bookmarks kept in the root of the archive.
+  pub fn bookmarks(&self) -> io::Result<Bookmarks> {
+    Bookmarks::open(self.root.join(BOOKMARKS_FILE))
+  }
+
+  fn load_index(root: &Path) -> Option<Vec<FileMetadata>> {
+    let mut data = String::new();
+    try_or!(File::open(root.join(INDEX_FILE)).and_then(|mut f| f.read_to_string(&mut data)), return None);
+    ::serde_json::from_str(&data).ok()
+  }
+
+  fn save_index(&self) -> io::Result<()> {
+    let data = ::serde_json::to_string(&self.files).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    File::create(self.root.join(INDEX_FILE))?.write_all(data.as_bytes())
+  }
+}
+
+/// An iterator over the results of [`Archive::query`](struct.Archive.html#method.query).
+pub struct ArchiveQuery {
+  files: VecDeque<(PathBuf, Format)>,
+  current: vec::IntoIter<Entry>,
+  range: Range<u32>,
+  filter: EntryFilter
+}
+
+impl Iterator for ArchiveQuery {
+  type Item = Entry;
+
+  fn next(&mut self) -> Option<Entry> {
+    loop {
+      if let Some(entry) = self.current.next() {
+        if entry.timestamp >= self.range.start && entry.timestamp < self.range.end && self.filter.matches(&entry) {
+          return Some(entry);
+        }
+        continue;
+      }
+      let (path, format) = opt!(self.files.pop_front());
+      self.current = format.read_entries(&path).unwrap_or_default().into_iter();
+    }
+  }
+}
+
+/// Canonicalizes a path that may not exist yet, by canonicalizing the part of it that does.
+fn canonicalize_new(path: &Path) -> io::Result<PathBuf> {
+  let mut existing = if path.is_absolute() { path.to_path_buf() } else { env::current_dir()?.join(path) };
+  let mut missing = Vec::new();
+  loop {
+    match existing.canonicalize() {
+      Ok(mut p) => {
+        for name in missing.into_iter().rev() {
+          p.push(name);
+        }
+        return Ok(p);
+      },
+      Err(e) => {
+        let name = opt_or!(existing.file_name(), return Err(e)).to_owned();
+        missing.push(name);
+        if !existing.pop() {
+          return Err(e);
+        }
+      }
+    }
+  }
+}
+
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+    if path.is_dir() {
+      walk(&path, paths)?;
+    } else if Format::from_path(&path).is_some() {
+      paths.push(path);
+    }
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn output_must_not_overlap_the_archive() {
+    let root = env::temp_dir().join(format!("ffxiv_reader_archive_{}", ::std::process::id()));
+    fs::create_dir_all(root.join("logs")).unwrap();
+    let archive = Archive {
+      root: root.join("logs"),
+      files: Vec::new()
+    };
+    assert!(archive.check_output(root.join("logs")).is_err());
+    assert!(archive.check_output(root.join("logs").join("2017").join("shared")).is_err());
+    assert!(archive.check_output(root.join("logs").join("..").join("logs")).is_err());
+    assert!(archive.check_output(&root).is_err());
+    assert!(archive.check_output(root.join("shared")).is_ok());
+    assert!(archive.check_output(root.join("logs2")).is_ok());
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
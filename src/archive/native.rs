@@ -0,0 +1,65 @@
+//! The game's own chat log files
+//!
+//! The game keeps chat logs in the `log` folder of each `FFXIV_CHR` folder. Each file starts with
+//! two `u32`s, the end and the start of the range of entries it holds, followed by one `u32` per
+//! entry giving the offset where that entry ends. The entries follow, laid out the same way as they
+//! are in memory.
+
+use messages::entries::{Entry, Provenance, RawEntry};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Reads every entry in a log file.
+///
+/// Entries that can't be parsed are skipped. Each entry's provenance is the file and its offset.
+pub fn read_entries<P: AsRef<Path>>(path: P) -> io::Result<Vec<Entry>> {
+  let path = path.as_ref();
+  let mut data = Vec::new();
+  File::open(path)?.read_to_end(&mut data)?;
+  Ok(parse(&data, Some(path)).unwrap_or_default())
+}
+
+/// Parses the contents of a log file.
+///
+/// If the header of the file is invalid, this will return `None`.
+pub fn parse_entries(data: &[u8]) -> Option<Vec<Entry>> {
+  parse(data, None)
+}
+
+fn parse(data: &[u8], path: Option<&Path>) -> Option<Vec<Entry>> {
+  if data.len() < 8 {
+    return None;
+  }
+  let end = LittleEndian::read_u32(&data[..4]) as usize;
+  let start = LittleEndian::read_u32(&data[4..8]) as usize;
+  if end < start {
+    return None;
+  }
+  let count = end - start;
+  let body = 8 + 4 * count;
+  if data.len() < body {
+    return None;
+  }
+  let mut entries = Vec::with_capacity(count);
+  let mut last_offset = 0;
+  for i in 0..count {
+    let offset = LittleEndian::read_u32(&data[8 + 4 * i..]) as usize;
+    if offset < last_offset || body + offset > data.len() {
+      break;
+    }
+    let bytes = data[body + last_offset..body + offset].to_vec();
+    if let Some(mut entry) = RawEntry::new(bytes).as_entry() {
+      entry.provenance = path.map(|p| Provenance::File {
+        path: p.to_path_buf(),
+        offset: (body + last_offset) as u64
+      });
+      entries.push(entry);
+    }
+    last_offset = offset;
+  }
+  Some(entries)
+}
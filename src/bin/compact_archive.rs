@@ -0,0 +1,118 @@
+extern crate ffxiv_reader;
+
+use ffxiv_reader::archive::Archive;
+use ffxiv_reader::archive::compact::{self, CompactOptions, RetentionRule};
+use ffxiv_reader::filter::Blocklist;
+
+use std::env::args;
+
+// Compacts an archive into a new directory, dropping duplicates and applying retention rules.
+//
+// Usage: compact_archive <archive> <output> [--compress] [--binary] [--keep-duplicates] [--retain <rule>]... [--blocklist <file>] [--game-patch <patch>]
+//
+// A retention rule is a comma-separated list of channels (or "all"), then "=", then the number of
+// days to keep them for (or "forever"). Rules are checked in order. For example:
+//
+//   compact_archive logs compacted --retain tell,tell_receive=forever --retain damage,actions=30
+//
+// A blocklist is a file with one character name per line. Entries from those names are dropped.
+//
+// The game patch is recorded in the header of every file written, like 4.0.
+//
+// --binary writes .ffxb files, which are much smaller than JSON lines files, even compressed ones.
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  // Ensure there is an archive and an output directory.
+  if args.len() < 2 {
+    println!("Please supply an archive directory and an output directory.");
+    return;
+  }
+  let mut options = CompactOptions::default();
+  // Parse the options after the paths.
+  let mut rest = args[2..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--compress" => options.compress = true,
+      "--binary" => options.binary = true,
+      "--keep-duplicates" => options.deduplicate = false,
+      "--retain" => {
+        let rule = match rest.next().and_then(|x| parse_rule(x)) {
+          Some(r) => r,
+          None => {
+            println!("Invalid retention rule. Please specify channels=days, like damage,actions=30.");
+            return;
+          }
+        };
+        options.retention.rules.push(rule);
+      },
+      "--blocklist" => {
+        let path = match rest.next() {
+          Some(p) => p,
+          None => {
+            println!("Please supply a blocklist file.");
+            return;
+          }
+        };
+        options.filter = match Blocklist::load(path) {
+          Ok(b) => b.to_filter(),
+          Err(e) => {
+            println!("Could not load blocklist {}: {}", path, e);
+            return;
+          }
+        };
+      },
+      "--game-patch" => {
+        options.metadata.game_patch = match rest.next() {
+          Some(p) => Some(p.clone()),
+          None => {
+            println!("Please supply a game patch.");
+            return;
+          }
+        };
+      },
+      _ => {
+        println!("Unknown option: {}.", arg);
+        return;
+      }
+    }
+  }
+  // Open the archive, indexing it if needed.
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  let stats = match compact::compact(&archive, &args[1], &options) {
+    Ok(s) => s,
+    Err(e) => {
+      println!("Could not compact archive: {}", e);
+      return;
+    }
+  };
+  println!("Read {} entries, dropped {} duplicates, {} blocked, and {} expired entries.", stats.read, stats.duplicates, stats.filtered, stats.expired);
+  println!("Wrote {} entries to {} files.", stats.written, stats.files);
+}
+
+fn parse_rule(rule: &str) -> Option<RetentionRule> {
+  let mut split = rule.splitn(2, '=');
+  let channels = split.next()?;
+  let days = split.next()?;
+  let message_types = if channels == "all" {
+    Vec::new()
+  } else {
+    channels.split(',').map(|x| x.parse().ok()).collect::<Option<Vec<_>>>()?
+  };
+  let max_age_days = if days == "forever" {
+    None
+  } else {
+    Some(days.parse().ok()?)
+  };
+  Some(RetentionRule {
+    message_types,
+    max_age_days
+  })
+}
@@ -0,0 +1,92 @@
+extern crate ffxiv_reader;
+extern crate ctrlc;
+
+use ffxiv_reader::{MemoryEntryReader, ActReader};
+use ffxiv_reader::config::Config;
+use ffxiv_reader::daemon::{Daemon, DaemonOptions, Source};
+use ffxiv_reader::pipeline::{Pipeline, Backpressure};
+use ffxiv_reader::routing::{self, SinkRegistry};
+
+use std::thread;
+
+// Captures entries until stopped, sending them to every sink in the config.
+//
+// Usage: daemon
+//
+// The source is the game's memory if a PID is configured, or else the ACT log. Ctrl-C and SIGTERM
+// stop the daemon cleanly, flushing sinks and saving the checkpoint. A warning is printed if no
+// entries arrive for stall_minutes, or if the ACT log is rotated or removed.
+//
+// See the [daemon] section of the config for the checkpoint, health endpoint, and stall_minutes,
+// and the [telemetry] section to opt in to reports of unknown payloads.
+
+fn main() {
+  // Load the config file, if any.
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
+    }
+  };
+  let mut pipeline = Pipeline::new(1, 256, Backpressure::Block);
+  pipeline.set_filter(filter);
+  if let Err((name, e)) = routing::add_routes(&mut pipeline, &config, &SinkRegistry::new()) {
+    println!("Could not set up sink {}: {}.", name, e);
+    return;
+  }
+
+  // Keep the readers alive for as long as the daemon runs.
+  let memory_reader = config.pid.map(|pid| MemoryEntryReader::new(pid, false));
+  let act_reader = config.act_log.as_ref().map(|path| ActReader::new(path, false));
+  let source = match (memory_reader.as_ref(), act_reader.as_ref()) {
+    (Some(r), _) => r.start_traced().map(Source::Raw),
+    (None, Some(r)) => r.start().map(Source::Parsed),
+    (None, None) => {
+      println!("Please configure a PID or an ACT log.");
+      return;
+    }
+  };
+  let source = match source {
+    Some(s) => s,
+    None => {
+      println!("Could not start reading.");
+      return;
+    }
+  };
+
+  let mut options = DaemonOptions::from_config(&config.daemon);
+  options.payload_report = config.telemetry.payload_report.clone();
+  if memory_reader.is_none() {
+    options.watch_file = config.act_log.clone();
+  }
+  let daemon = Daemon::new(options);
+  let events = daemon.events();
+  thread::spawn(move || {
+    for event in events {
+      if event.is_problem() {
+        println!("Warning: {}.", event);
+      } else {
+        println!("{}.", event);
+      }
+    }
+  });
+  let stop = daemon.stop_handle();
+  if let Err(e) = ctrlc::set_handler(move || stop.stop()) {
+    println!("Could not handle signals: {}.", e);
+    return;
+  }
+  if let Err(e) = daemon.run(source, pipeline) {
+    println!("Daemon stopped with an error: {}.", e);
+  }
+  if let Some(r) = memory_reader {
+    r.stop();
+  }
+}
@@ -0,0 +1,91 @@
+extern crate ffxiv_reader;
+extern crate chrono;
+
+use ffxiv_reader::archive::{Archive, Format};
+use ffxiv_reader::diff::diff;
+use ffxiv_reader::messages::HasDisplayText;
+use ffxiv_reader::messages::entries::Entry;
+
+use chrono::{Local, TimeZone};
+
+use std::env::args;
+use std::path::Path;
+
+// Compares two captures of the same period and lists the entries only one of them has.
+//
+// Usage: diff_captures <left> <right> [--all]
+//
+// Each capture can be a log file, a JSON lines file, or an archive directory. Only the period both
+// captures cover is compared, unless --all is given. Entries only in the left capture are listed
+// with <, and entries only in the right capture with >. For example, to check a memory capture
+// against the game's own logs:
+//
+//   diff_captures memory.jsonl "FFXIV_CHR0040000000000000/log"
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.len() < 2 {
+    println!("Please supply two captures to compare.");
+    return;
+  }
+  let overlap_only = !args.iter().any(|x| x == "--all");
+  let left = match read_capture(&args[0]) {
+    Ok(e) => e,
+    Err(e) => {
+      println!("Could not read {}: {}", args[0], e);
+      return;
+    }
+  };
+  let right = match read_capture(&args[1]) {
+    Ok(e) => e,
+    Err(e) => {
+      println!("Could not read {}: {}", args[1], e);
+      return;
+    }
+  };
+  let diff = diff(&left, &right, overlap_only);
+  let mut lines: Vec<(&Entry, char)> = diff.only_left.iter().map(|x| (x, '<'))
+    .chain(diff.only_right.iter().map(|x| (x, '>')))
+    .collect();
+  lines.sort_by_key(|&(e, _)| e.timestamp);
+  for (entry, side) in lines {
+    let sender = match entry.sender {
+      Some(ref s) => format!(" <{}>", s.display_text()),
+      None => String::new()
+    };
+    println!("{} [{}], {},{} {}",
+             side,
+             Local.timestamp(entry.timestamp as i64, 0).format("%d/%m/%Y %H:%M:%S"),
+             entry.message_type,
+             sender,
+             entry.message.display_text().replace('\r', "\n"));
+  }
+  match diff.range {
+    Some(range) => println!(
+      "{} matched, {} only in {}, {} only in {} (comparing {} to {}).",
+      diff.matched,
+      diff.only_left.len(),
+      args[0],
+      diff.only_right.len(),
+      args[1],
+      Local.timestamp(range.start as i64, 0).format("%d/%m/%Y %H:%M:%S"),
+      Local.timestamp(range.end as i64, 0).format("%d/%m/%Y %H:%M:%S")
+    ),
+    None if overlap_only && !left.is_empty() && !right.is_empty() => {
+      println!("Nothing was compared, since the captures don't cover any of the same period. Use --all to compare them anyway.")
+    },
+    None => println!("Nothing was compared, since the captures are empty.")
+  }
+}
+
+fn read_capture(path: &str) -> Result<Vec<Entry>, String> {
+  if Path::new(path).is_dir() {
+    let archive = Archive::open(path).map_err(|e| e.to_string())?;
+    return Ok(archive.entries().collect());
+  }
+  match Format::from_path(path) {
+    Some(f) => f.read_entries(path).map_err(|e| e.to_string()),
+    None => Err(String::from("unknown file format, expected .log, .jsonl, .jsonl.xz, .ffxb, or a directory"))
+  }
+}
@@ -1,6 +1,8 @@
 extern crate ffxiv_reader;
 extern crate serde_json;
 
+use ffxiv_reader::archive::jsonl;
+use ffxiv_reader::config::Config;
 use ffxiv_reader::messages::entries::Entry;
 use ffxiv_reader::messages::MessageType;
 use ffxiv_reader::messages::parts::Part;
@@ -15,6 +17,21 @@ fn main() {
     println!("Specify a file with one JSON object per line.");
     return;
   }
+  // Load the config file, if any, for its default filter and blocklist.
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
+    }
+  };
   let file_name = &args[0];
   let mut file = match File::open(file_name) {
     Ok(f) => f,
@@ -28,7 +45,15 @@ fn main() {
     println!("Could not read {}: {}", file_name, e);
     return;
   }
-  let lines = data.split('\n').filter(|x| !x.is_empty() && x.starts_with('{'));
+  // Make sure the file was written by a version that can be read.
+  let header = match jsonl::check_header(data.lines().next().unwrap_or_default()) {
+    Ok(h) => h,
+    Err(e) => {
+      println!("Could not read {}: {}", file_name, e);
+      return;
+    }
+  };
+  let lines = data.split('\n').filter(|x| !x.is_empty() && x.starts_with('{')).skip(header.is_some() as usize);
   let entries: Result<Vec<Entry>, serde_json::Error> = lines.map(serde_json::from_str).collect();
   let entries = match entries {
     Ok(e) => e,
@@ -37,7 +62,7 @@ fn main() {
       return;
     }
   };
-  for entry in entries {
+  for entry in entries.into_iter().filter(|x| filter.matches(x)) {
     if entry.message_type != MessageType::Party &&
        entry.message_type != MessageType::StandardEmotes &&
        entry.message_type != MessageType::CustomEmotes {
@@ -0,0 +1,69 @@
+extern crate ffxiv_reader;
+extern crate chrono;
+
+use ffxiv_reader::archive::jsonl;
+use ffxiv_reader::import::{self, ImportOptions};
+use ffxiv_reader::schema::Metadata;
+
+use chrono::NaiveDate;
+
+use std::env::args;
+
+// Imports logs exported by other tools into a JSON lines file, which can be put in an archive.
+//
+// Usage: import_logs <output.jsonl> <input>... [--date YYYY-MM-DD] [--utc-offset hours]
+//
+// Inputs can be plain text (.txt, .log) or HTML (.html, .htm). The date is needed for exports that
+// only show the time of day.
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  let mut options = ImportOptions::default();
+  let mut paths = Vec::new();
+  let mut rest = args.iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--date" => {
+        options.date = match rest.next().and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok()) {
+          Some(d) => Some(d),
+          None => {
+            println!("Invalid date. Please specify it like 2017-06-27.");
+            return;
+          }
+        };
+      },
+      "--utc-offset" => {
+        options.utc_offset = match rest.next().and_then(|x| x.parse::<f32>().ok()) {
+          Some(h) => (h * 3600.0) as i32,
+          None => {
+            println!("Invalid UTC offset. Please specify it in hours, like -5.");
+            return;
+          }
+        };
+      },
+      _ => paths.push(arg)
+    }
+  }
+  if paths.len() < 2 {
+    println!("Please supply an output file and at least one file to import.");
+    return;
+  }
+  let mut entries = Vec::new();
+  for path in &paths[1..] {
+    match import::import_file(path, &options) {
+      Ok(e) => {
+        println!("Imported {} entries from {}.", e.len(), path);
+        entries.extend(e);
+      },
+      Err(e) => {
+        println!("Could not import {}: {}", path, e);
+        return;
+      }
+    }
+  }
+  entries.sort_by_key(|x| x.timestamp);
+  if let Err(e) = jsonl::write_file(paths[0], &Metadata::new(Some("import")), &entries, false) {
+    println!("Could not write {}: {}", paths[0], e);
+  }
+}
@@ -2,6 +2,7 @@ extern crate ffxiv_reader;
 extern crate serde_json;
 
 use ffxiv_reader::*;
+use ffxiv_reader::config::Config;
 
 use std::env::args;
 
@@ -19,33 +20,55 @@ use std::env::args;
 fn main() {
   // Gather the arguments supplied to the program.
   let args: Vec<String> = args().skip(1).collect();
-  // Ensure they are not empty.
-  if args.is_empty() {
-    println!("Please supply a PID.");
-    return;
-  }
-  // Attempt to parse a PID from the first arg.
-  let pid: u32 = match args[0].parse() {
-    Ok(p) => p,
+  // Load the config file, if any. Arguments override anything in it.
+  let config = match Config::load_default() {
+    Ok(c) => c,
     Err(e) => {
-      println!("Invalid PID: {}.", e);
+      println!("Could not load config: {}.", e);
       return;
     }
   };
+  // Attempt to parse a PID from the first arg, or use the one in the config.
+  let pid: u32 = match args.get(0) {
+    Some(arg) => match arg.parse() {
+      Ok(p) => p,
+      Err(e) => {
+        println!("Invalid PID: {}.", e);
+        return;
+      }
+    },
+    None => match config.pid {
+      Some(p) => p,
+      None => {
+        println!("Please supply a PID.");
+        return;
+      }
+    }
+  };
   // Check whether the program should continue scanning memory or just stop.
-  let stop = if args.len() > 1 {
-    match args[1].to_lowercase().parse() {
+  let stop = match args.get(1) {
+    Some(arg) => match arg.to_lowercase().parse() {
       Ok(b) => b,
       Err(e) => {
         println!("Invalid stop argument. Please specify true/false. {}", e);
         return;
       }
+    },
+    None => config.stop.unwrap_or(false)
+  };
+  // Only print entries that match the configured filter and aren't from blocked names.
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
     }
-  } else { false };
+  };
   // Create a log reader.
   let reader = MemoryEntryReader::new(pid, stop);
-  // Print out every entry.
-  for entry in reader.iter() {
+  // Print the metadata header, then every entry.
+  println!("{}", config.metadata("memory").to_line());
+  for entry in reader.iter().filter(|x| filter.matches(x)) {
     println!("{}", serde_json::to_string(&entry).unwrap());
   }
 }
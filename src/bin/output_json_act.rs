@@ -2,8 +2,10 @@ extern crate ffxiv_reader;
 extern crate serde_json;
 
 use ffxiv_reader::*;
+use ffxiv_reader::config::Config;
 
 use std::env::args;
+use std::path::PathBuf;
 
 // The main loop checks the game's memory for a list of indices that point to where messages start
 // in the chat log kept in memory. The loop checks for new indices by checking a pointer, then reads
@@ -19,29 +21,47 @@ use std::env::args;
 fn main() {
   // Gather the arguments supplied to the program.
   let args: Vec<String> = args().skip(1).collect();
-  // Ensure they are not empty.
-  if args.is_empty() {
-    println!("Please supply a path.");
-    return;
-  }
+  // Load the config file, if any. Arguments override anything in it.
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
   // Get path to the file.
-  let path = &args[0];
+  let path = match args.get(0).map(PathBuf::from).or(config.act_log.clone()) {
+    Some(p) => p,
+    None => {
+      println!("Please supply a path.");
+      return;
+    }
+  };
   // Check whether the program should continue scanning the file or just stop.
-  let stop = if args.len() > 1 {
-    match args[1].to_lowercase().parse() {
+  let stop = match args.get(1) {
+    Some(arg) => match arg.to_lowercase().parse() {
       Ok(b) => b,
       Err(e) => {
         println!("Invalid stop argument. Please specify true/false. {}", e);
         return;
       }
+    },
+    None => config.stop.unwrap_or(false)
+  };
+  // Only print entries that match the configured filter and aren't from blocked names.
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
     }
-  } else { false };
+  };
   // Create a log reader.
   let reader = ActReader::new(path, stop);
   let rx = reader.start().unwrap();
-  // Print out every entry.
-  loop {
-    let entry = rx.recv().unwrap();
+  // Print the metadata header, then every entry.
+  println!("{}", config.metadata("act").to_line());
+  for entry in rx.iter().filter(|x| filter.matches(x)) {
     println!("{}", serde_json::to_string(&entry).unwrap());
   }
 }
@@ -2,7 +2,8 @@ extern crate ffxiv_reader;
 extern crate time;
 
 use ffxiv_reader::MemoryEntryReader;
-use ffxiv_reader::messages::HasDisplayText;
+use ffxiv_reader::config::Config;
+use ffxiv_reader::names;
 
 use std::env::args;
 use time::Timespec;
@@ -10,42 +11,65 @@ use time::Timespec;
 fn main() {
   // Gather the arguments supplied to the program.
   let args: Vec<String> = args().skip(1).collect();
-  // Ensure they are not empty.
-  if args.is_empty() {
-    println!("Please supply a PID.");
-    return;
-  }
-  // Attempt to parse a PID from the first arg.
-  let pid: u32 = match args[0].parse() {
-    Ok(p) => p,
+  // Load the config file, if any. Arguments override anything in it.
+  let config = match Config::load_default() {
+    Ok(c) => c,
     Err(e) => {
-      println!("Invalid PID: {}.", e);
+      println!("Could not load config: {}.", e);
       return;
     }
   };
+  // Attempt to parse a PID from the first arg, or use the one in the config.
+  let pid: u32 = match args.get(0) {
+    Some(arg) => match arg.parse() {
+      Ok(p) => p,
+      Err(e) => {
+        println!("Invalid PID: {}.", e);
+        return;
+      }
+    },
+    None => match config.pid {
+      Some(p) => p,
+      None => {
+        println!("Please supply a PID.");
+        return;
+      }
+    }
+  };
   // Check whether the program should continue scanning memory or just stop.
-  let stop = if args.len() > 1 {
-    match args[1].to_lowercase().parse() {
+  let stop = match args.get(1) {
+    Some(arg) => match arg.to_lowercase().parse() {
       Ok(b) => b,
       Err(e) => {
         println!("Invalid stop argument. Please specify true/false. {}", e);
         return;
       }
+    },
+    None => config.stop.unwrap_or(false)
+  };
+  // Show names the way the config asks.
+  names::set_display_policy(config.names.clone());
+  // Only print entries that match the configured filter and aren't from blocked names.
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
     }
-  } else { false };
+  };
   // Create a log reader.
   let reader = MemoryEntryReader::new(pid, stop);
   // Print out every entry.
-  for entry in reader.iter() {
+  for entry in reader.iter().filter(|x| filter.matches(x)) {
     let t = time::at(Timespec::new(entry.timestamp as i64, 0));
     let time_string = t.strftime("%d/%m/%Y %H:%M:%S").unwrap();
 
     let sender = match entry.sender {
-      Some(s) => format!(" <{}>", s.display_text()),
+      Some(s) => format!(" <{}>", names::sender_display_text(&s)),
       None => String::new()
     };
 
-    let message = entry.message.display_text().replace('\r', "\n");
+    let message = names::message_display_text(&entry.message).replace('\r', "\n");
 
     println!("[{}], {},{} {}",
       time_string,
@@ -2,47 +2,67 @@ extern crate ffxiv_reader;
 extern crate chrono;
 
 use ffxiv_reader::ActReader;
-use ffxiv_reader::messages::HasDisplayText;
+use ffxiv_reader::config::Config;
+use ffxiv_reader::names;
 
 use std::env::args;
+use std::path::PathBuf;
 use chrono::{Utc, TimeZone};
 
 fn main() {
   // Gather the arguments supplied to the program.
   let args: Vec<String> = args().skip(1).collect();
-  // Ensure they are not empty.
-  if args.is_empty() {
-    println!("Please supply a path.");
-    return;
-  }
+  // Load the config file, if any. Arguments override anything in it.
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
   // Get path to the file.
-  let path = &args[0];
+  let path = match args.get(0).map(PathBuf::from).or(config.act_log.clone()) {
+    Some(p) => p,
+    None => {
+      println!("Please supply a path.");
+      return;
+    }
+  };
   // Check whether the program should continue scanning the file or just stop.
-  let stop = if args.len() > 1 {
-    match args[1].to_lowercase().parse() {
+  let stop = match args.get(1) {
+    Some(arg) => match arg.to_lowercase().parse() {
       Ok(b) => b,
       Err(e) => {
         println!("Invalid stop argument. Please specify true/false. {}", e);
         return;
       }
+    },
+    None => config.stop.unwrap_or(false)
+  };
+  // Show names the way the config asks.
+  names::set_display_policy(config.names.clone());
+  // Only print entries that match the configured filter and aren't from blocked names.
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
     }
-  } else { false };
+  };
   // Create a log reader.
   let reader = ActReader::new(path, stop);
   let rx = reader.start().unwrap();
   // Print out every entry.
-  loop {
-    let entry = rx.recv().unwrap();
-
+  for entry in rx.iter().filter(|x| filter.matches(x)) {
     let timestamp = Utc.timestamp(entry.timestamp as i64, 0);
     let time_string = timestamp.format("%d/%m/%Y %H:%M:%S");
 
     let sender = match entry.sender {
-      Some(s) => format!(" <{}>", s.display_text()),
+      Some(s) => format!(" <{}>", names::sender_display_text(&s)),
       None => String::new()
     };
 
-    let message = entry.message.display_text().replace('\r', "\n");
+    let message = names::message_display_text(&entry.message).replace('\r', "\n");
 
     println!("[{}], {},{} {}",
              time_string,
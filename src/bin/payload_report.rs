@@ -0,0 +1,61 @@
+extern crate ffxiv_reader;
+
+use ffxiv_reader::archive::Archive;
+use ffxiv_reader::config::Config;
+use ffxiv_reader::telemetry::PayloadCollector;
+
+use std::env::args;
+
+// Adds the unknown payloads in an archive to a report.
+//
+// Usage: payload_report <archive> [report]
+//
+// The report defaults to the payload_report in the [telemetry] section of the config. It holds only
+// the marker byte, length, and shape of each unknown payload, never any text, so it can be attached
+// to a bug report after a patch. For example:
+//
+//   payload_report logs unknown_payloads.json
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.is_empty() {
+    println!("Please supply an archive directory.");
+    return;
+  }
+  // Load the config file, if any.
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
+  let report = match args.get(1).map(Into::into).or(config.telemetry.payload_report) {
+    Some(r) => r,
+    None => {
+      println!("Please supply a report file or configure one in the [telemetry] section.");
+      return;
+    }
+  };
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  let mut collector = match PayloadCollector::open(&report) {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not open report {}: {}", report.display(), e);
+      return;
+    }
+  };
+  let found: usize = archive.entries().map(|x| collector.observe(&x)).sum();
+  if let Err(e) = collector.save() {
+    println!("Could not save report {}: {}", report.display(), e);
+    return;
+  }
+  println!("Found {} unknown payloads, {} distinct shapes in total.", found, collector.report().payloads.len());
+}
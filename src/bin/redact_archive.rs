@@ -0,0 +1,89 @@
+extern crate ffxiv_reader;
+
+use ffxiv_reader::archive::Archive;
+use ffxiv_reader::messages::MessageType;
+use ffxiv_reader::redact::Redactor;
+
+use std::env::args;
+
+// Writes a redacted copy of an archive that is safe to share.
+//
+// Usage: redact_archive <archive> <output> [--drop <channels>] [--strip <channels>] [--mask <name>]... [--mask-all]
+//
+// Channels are comma-separated message type names. Dropped channels are left out entirely, while
+// stripped channels keep their entries with the text replaced by asterisks. Masked names are
+// replaced with pseudonyms like "Player 1" everywhere they appear. For example:
+//
+//   redact_archive logs shared --drop tell,tell_receive,free_company --strip party --mask-all
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  // Ensure there is an archive and an output directory.
+  if args.len() < 2 {
+    println!("Please supply an archive directory and an output directory.");
+    return;
+  }
+  let mut redactor = Redactor::new();
+  // Parse the options after the paths.
+  let mut rest = args[2..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--drop" | "--strip" => {
+        let channels = match rest.next().and_then(|x| parse_channels(x)) {
+          Some(c) => c,
+          None => {
+            println!("Invalid channels. Please specify message types separated by commas, like tell,party.");
+            return;
+          }
+        };
+        if arg == "--drop" {
+          redactor.drop_channels.extend(channels);
+        } else {
+          redactor.strip_channels.extend(channels);
+        }
+      },
+      "--mask" => match rest.next() {
+        Some(name) => redactor.masked_senders.push(name.clone()),
+        None => {
+          println!("Please supply a name to mask.");
+          return;
+        }
+      },
+      "--mask-all" => redactor.mask_all_senders = true,
+      _ => {
+        println!("Unknown option: {}.", arg);
+        return;
+      }
+    }
+  }
+  // Open the archive, indexing it if needed.
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  // Writing into the archive would replace the originals with the redacted copies.
+  if let Err(e) = archive.check_output(&args[1]) {
+    println!("Could not redact archive: {}", e);
+    return;
+  }
+  let stats = match redactor.redact_archive(&archive, &args[1]) {
+    Ok(s) => s,
+    Err(e) => {
+      println!("Could not redact archive: {}", e);
+      return;
+    }
+  };
+  println!("Read {} entries.", stats.read);
+  println!("Dropped {} entries.", stats.dropped);
+  println!("Stripped the text of {} entries.", stats.stripped);
+  println!("Masked names in {} entries, using {} pseudonyms.", stats.masked, redactor.pseudonyms().len());
+  println!("Wrote {} entries to {} files.", stats.written, stats.files);
+}
+
+fn parse_channels(list: &str) -> Option<Vec<MessageType>> {
+  list.split(',').map(|x| x.trim().parse().ok()).collect()
+}
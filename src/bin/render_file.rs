@@ -0,0 +1,102 @@
+extern crate ffxiv_reader;
+
+use ffxiv_reader::archive::Format;
+use ffxiv_reader::archive::bookmarks::Bookmarks;
+use ffxiv_reader::config::Config;
+use ffxiv_reader::names;
+use ffxiv_reader::render::{IconMap, Renderer, HtmlRenderer, MarkdownRenderer};
+
+use std::env::args;
+
+// Renders a log file as HTML, Markdown, or a template from the config file.
+//
+// Usage: render_file <file> [html|markdown|template <name>]
+//
+// The file can be a game log or a JSON lines file. Icons from the config file are used on top of the
+// built-in ones. If the file is in an archive with bookmarks, bookmarked entries are marked.
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.is_empty() {
+    println!("Please supply a log file.");
+    return;
+  }
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
+  let format = match Format::from_path(&args[0]) {
+    Some(f) => f,
+    None => {
+      println!("Unknown file format. Please supply a .log, .jsonl, .jsonl.xz, or .ffxb file.");
+      return;
+    }
+  };
+  let entries = match format.read_entries(&args[0]) {
+    Ok(e) => e,
+    Err(e) => {
+      println!("Could not read {}: {}", args[0], e);
+      return;
+    }
+  };
+  names::set_display_policy(config.names.clone());
+  let mut icons = IconMap::builtin();
+  icons.extend(config.icons);
+  let bookmarks = match Bookmarks::find(&args[0]) {
+    Some(Ok(b)) => Some(b),
+    Some(Err(e)) => {
+      println!("Could not read bookmarks: {}", e);
+      return;
+    },
+    None => None
+  };
+  let output = match args.get(1).map(|x| x.as_str()).unwrap_or("html") {
+    "html" => {
+      let mut renderer = HtmlRenderer::new();
+      renderer.icons = icons;
+      renderer.bookmarks = bookmarks;
+      renderer.render_log(&entries)
+    },
+    "markdown" | "md" => {
+      let mut renderer = MarkdownRenderer::new();
+      renderer.icons = icons;
+      renderer.bookmarks = bookmarks;
+      renderer.render_log(&entries)
+    },
+    "template" => {
+      let name = match args.get(2) {
+        Some(n) => n,
+        None => {
+          println!("Please supply the name of a template from the config file.");
+          return;
+        }
+      };
+      let template = match config.templates.get(name) {
+        Some(t) => t,
+        None => {
+          println!("There is no template named {} in the config file.", name);
+          return;
+        }
+      };
+      let mut renderer = match template.renderer() {
+        Ok(r) => r,
+        Err(e) => {
+          println!("Invalid template {}: {}.", name, e);
+          return;
+        }
+      };
+      renderer.html.icons = icons.clone();
+      renderer.markdown.icons = icons;
+      renderer.render_log(&entries)
+    },
+    x => {
+      println!("Unknown output format: {}. Please specify html, markdown, or template.", x);
+      return;
+    }
+  };
+  println!("{}", output);
+}
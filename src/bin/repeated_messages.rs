@@ -0,0 +1,62 @@
+extern crate ffxiv_reader;
+
+use ffxiv_reader::archive::Archive;
+use ffxiv_reader::filter::EntryFilter;
+use ffxiv_reader::messages::MessageType;
+use ffxiv_reader::similar::SimilarityIndex;
+
+use std::env::args;
+
+// Lists messages in an archive that were repeated, grouping nearly identical messages together.
+//
+// Usage: repeated_messages <archive> [channels] [minimum]
+//
+// Channels are comma-separated message type names, defaulting to shout and yell. Only messages seen
+// at least the minimum number of times are listed, defaulting to 5. For example, to see how often
+// venue ads were shouted:
+//
+//   repeated_messages logs shout 10
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.is_empty() {
+    println!("Please supply an archive directory.");
+    return;
+  }
+  let channels: Vec<MessageType> = match args.get(1) {
+    Some(list) => match list.split(',').map(|x| x.trim().parse().ok()).collect() {
+      Some(c) => c,
+      None => {
+        println!("Invalid channels. Please specify message types separated by commas, like shout,yell.");
+        return;
+      }
+    },
+    None => vec![MessageType::Shout, MessageType::Yell]
+  };
+  let minimum = match args.get(2).map(|x| x.parse()) {
+    Some(Ok(m)) => m,
+    Some(Err(e)) => {
+      println!("Invalid minimum: {}.", e);
+      return;
+    },
+    None => 5
+  };
+  // Open the archive, indexing it if needed.
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  let mut index = SimilarityIndex::new();
+  for entry in archive.query(0..::std::u32::MAX, EntryFilter::MessageTypes(channels)) {
+    index.add(&entry);
+  }
+  let mut clusters: Vec<_> = index.clusters().iter().filter(|x| x.count() >= minimum).collect();
+  clusters.sort_by(|a, b| b.count().cmp(&a.count()));
+  for cluster in clusters {
+    println!("{} times by {} senders: {}", cluster.count(), cluster.senders.len(), cluster.text);
+  }
+}
@@ -0,0 +1,70 @@
+extern crate ffxiv_reader;
+
+use ffxiv_reader::MemoryEntryReader;
+use ffxiv_reader::config::Config;
+use ffxiv_reader::pipeline::{Pipeline, Backpressure};
+use ffxiv_reader::routing::{self, SinkRegistry};
+
+use std::env::args;
+
+// Reads entries from memory and sends them to every sink in the config, according to each sink's
+// channels and filter.
+//
+// Usage: route [pid]
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  // Load the config file, if any. Arguments override anything in it.
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
+  // Attempt to parse a PID from the first arg, or use the one in the config.
+  let pid: u32 = match args.get(0) {
+    Some(arg) => match arg.parse() {
+      Ok(p) => p,
+      Err(e) => {
+        println!("Invalid PID: {}.", e);
+        return;
+      }
+    },
+    None => match config.pid {
+      Some(p) => p,
+      None => {
+        println!("Please supply a PID.");
+        return;
+      }
+    }
+  };
+  if config.sinks.is_empty() {
+    println!("No sinks are configured.");
+    return;
+  }
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
+    }
+  };
+  let mut pipeline = Pipeline::new(2, 256, Backpressure::DropNewest);
+  pipeline.set_filter(filter);
+  if let Err((name, e)) = routing::add_routes(&mut pipeline, &config, &SinkRegistry::new()) {
+    println!("Could not set up sink {}: {}.", name, e);
+    return;
+  }
+  let reader = MemoryEntryReader::new(pid, config.stop.unwrap_or(false));
+  let source = match reader.start_traced() {
+    Some(s) => s,
+    None => {
+      println!("Could not read the game's memory.");
+      return;
+    }
+  };
+  pipeline.start(source);
+  pipeline.join();
+}
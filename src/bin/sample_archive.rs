@@ -0,0 +1,132 @@
+extern crate ffxiv_reader;
+extern crate chrono;
+
+use ffxiv_reader::archive::{Archive, jsonl};
+use ffxiv_reader::filter::EntryFilter;
+use ffxiv_reader::messages::MessageType;
+use ffxiv_reader::sample::Sampler;
+use ffxiv_reader::schema::Metadata;
+
+use chrono::NaiveDate;
+
+use std::env::args;
+
+// Writes a sample of an archive to a JSON lines file.
+//
+// Usage: sample_archive <archive> <output.jsonl> [--count <n>] [--every <n>] [--seed <n>] [--by-channel] [--by-day] [--channels <channels>] [--filter <expression>] [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--max-files <n>]
+//
+// The count is the most entries to take from each group, defaulting to 100. Entries are grouped by
+// channel, day, or both if asked. Entries are chosen at random unless --every is given, which takes
+// every nth entry instead. --filter only samples entries matching a filter expression. --to is
+// exclusive. --max-files reads only that many of the archive's files, for a quick look at a large
+// archive. For example, a test corpus of 20 entries from each channel:
+//
+//   sample_archive logs corpus.jsonl --count 20 --by-channel --seed 7
+//
+// Or 50 party messages mentioning a raid:
+//
+//   sample_archive logs raids.jsonl --count 50 --filter 'channel = party and text contains "raid"'
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.len() < 2 {
+    println!("Please supply an archive directory and an output file.");
+    return;
+  }
+  let mut count = 100;
+  let mut every = None;
+  let mut seed = 0;
+  let mut by_channel = false;
+  let mut by_day = false;
+  let mut filters = Vec::new();
+  let mut range = 0..::std::u32::MAX;
+  let mut max_files = None;
+  let mut rest = args[2..].iter();
+  while let Some(arg) = rest.next() {
+    match arg.as_str() {
+      "--by-channel" => by_channel = true,
+      "--by-day" => by_day = true,
+      "--count" | "--every" | "--seed" | "--max-files" => {
+        let value = match rest.next().and_then(|x| x.parse::<u64>().ok()) {
+          Some(v) => v,
+          None => {
+            println!("Please supply a number for {}.", arg);
+            return;
+          }
+        };
+        match arg.as_str() {
+          "--count" => count = value as usize,
+          "--every" => every = Some(value as usize),
+          "--seed" => seed = value,
+          _ => max_files = Some(value as usize)
+        }
+      },
+      "--channels" => {
+        match rest.next().and_then(|x| x.split(',').map(|x| x.trim().parse().ok()).collect::<Option<Vec<MessageType>>>()) {
+          Some(c) => filters.push(EntryFilter::MessageTypes(c)),
+          None => {
+            println!("Invalid channels. Please specify message types separated by commas, like say,party.");
+            return;
+          }
+        }
+      },
+      "--filter" => match rest.next().map(|x| x.parse::<EntryFilter>()) {
+        Some(Ok(f)) => filters.push(f),
+        Some(Err(e)) => {
+          println!("Invalid filter: {}.", e);
+          return;
+        },
+        None => {
+          println!("Please supply a filter expression for --filter.");
+          return;
+        }
+      },
+      "--from" | "--to" => {
+        let timestamp = match rest.next().and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok()) {
+          Some(d) => d.and_hms(0, 0, 0).timestamp() as u32,
+          None => {
+            println!("Invalid date. Please specify it like 2017-06-27.");
+            return;
+          }
+        };
+        if arg == "--from" {
+          range.start = timestamp;
+        } else {
+          range.end = timestamp;
+        }
+      },
+      _ => {
+        println!("Unknown option: {}.", arg);
+        return;
+      }
+    }
+  }
+  let mut sampler = match every {
+    Some(n) => Sampler::systematic(count, n),
+    None => Sampler::random(count, seed)
+  };
+  sampler.by_channel = by_channel;
+  sampler.by_day = by_day;
+  sampler.filter = match filters.len() {
+    0 => EntryFilter::All,
+    1 => filters.remove(0),
+    _ => EntryFilter::And(filters)
+  };
+  sampler.range = range;
+  sampler.max_files = max_files;
+  // Open the archive, indexing it if needed.
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  let sample = sampler.sample_archive(&archive);
+  if let Err(e) = jsonl::write_file(&args[1], &Metadata::new(Some("sample")), &sample, false) {
+    println!("Could not write {}: {}", args[1], e);
+    return;
+  }
+  println!("Wrote {} entries to {}.", sample.len(), args[1]);
+}
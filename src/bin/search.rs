@@ -0,0 +1,110 @@
+extern crate ffxiv_reader;
+#[cfg(feature = "search")]
+extern crate chrono;
+
+#[cfg(feature = "search")]
+use ffxiv_reader::archive::Archive;
+#[cfg(feature = "search")]
+use ffxiv_reader::search::{SearchIndex, SearchQuery};
+
+#[cfg(feature = "search")]
+use chrono::{Local, TimeZone};
+
+#[cfg(feature = "search")]
+use std::env::args;
+
+// Searches an archive for messages containing words.
+//
+// Usage: search <archive> <words> [--sender <name>] [--channels <channels>] [--limit <count>]
+//
+// The index is kept in .ffxiv_reader_search in the archive and brought up to date before
+// searching, so the first search of a large archive takes a while. Channels are comma-separated
+// message type names. For example:
+//
+//   search logs "savage clear" --channels free_company --limit 20
+//
+// This needs the search feature.
+
+#[cfg(not(feature = "search"))]
+fn main() {
+  println!("This was built without the search feature.");
+}
+
+#[cfg(feature = "search")]
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.len() < 2 {
+    println!("Please supply an archive directory and words to search for.");
+    return;
+  }
+  let mut query = SearchQuery::new(args[1].as_str());
+  let mut options = args[2..].iter();
+  while let Some(option) = options.next() {
+    let value = match options.next() {
+      Some(v) => v,
+      None => {
+        println!("Please supply a value for {}.", option);
+        return;
+      }
+    };
+    match option.as_str() {
+      "--sender" => query.sender = Some(value.clone()),
+      "--channels" => match value.split(',').map(|x| x.trim().parse().ok()).collect() {
+        Some(c) => query.channels = c,
+        None => {
+          println!("Invalid channels. Please specify message types separated by commas, like say,party.");
+          return;
+        }
+      },
+      "--limit" => match value.parse() {
+        Ok(l) => query.limit = l,
+        Err(e) => {
+          println!("Invalid limit: {}.", e);
+          return;
+        }
+      },
+      x => {
+        println!("Unknown option: {}.", x);
+        return;
+      }
+    }
+  }
+  // Open the archive, indexing it if needed.
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  let mut index = match SearchIndex::open(archive.root().join(".ffxiv_reader_search")) {
+    Ok(i) => i,
+    Err(e) => {
+      println!("Could not open search index: {}", e);
+      return;
+    }
+  };
+  if let Err(e) = index.update(&archive) {
+    println!("Could not update search index: {}", e);
+    return;
+  }
+  let hits = match index.search(&query) {
+    Ok(h) => h,
+    Err(e) => {
+      println!("Could not search: {}", e);
+      return;
+    }
+  };
+  for hit in hits {
+    let sender = match hit.sender {
+      Some(ref s) => format!(" <{}>", s),
+      None => String::new()
+    };
+    println!("[{}], {},{} {}",
+             Local.timestamp(hit.timestamp as i64, 0).format("%d/%m/%Y %H:%M:%S"),
+             hit.message_type,
+             sender,
+             hit.text.replace('\r', "\n"));
+  }
+}
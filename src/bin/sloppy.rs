@@ -3,6 +3,7 @@ extern crate serde_json;
 extern crate time;
 
 use ffxiv_reader::MemoryEntryReader;
+use ffxiv_reader::config::Config;
 use ffxiv_reader::messages::parts::Part;
 use ffxiv_reader::messages::MessageType;
 use time::Timespec;
@@ -18,6 +19,21 @@ fn main() {
     println!("Please supply a PID and device ID.");
     return;
   }
+  // Load the config file, if any. Arguments override anything in it.
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
+  let filter = match config.entry_filter() {
+    Ok(f) => f,
+    Err(e) => {
+      println!("Could not load blocklist: {}.", e);
+      return;
+    }
+  };
   // Attempt to parse a PID from the first arg.
   let pid: u32 = match args[0].parse() {
     Ok(p) => p,
@@ -43,7 +59,7 @@ fn main() {
         return;
       }
     }
-  } else { false };
+  } else { config.stop.unwrap_or(false) };
   // Create a log reader.
   let reader = MemoryEntryReader::new(pid, stop);
   // Record program start time, so it doesn't replay deaths.
@@ -51,7 +67,7 @@ fn main() {
   // Last sloppy time
   let mut last_sloppy: Option<time::Tm> = None;
   // Loop over every old and new entry
-  for entry in reader.iter() {
+  for entry in reader.iter().filter(|x| filter.matches(x)) {
     // Skip anything that's not a death
     if entry.message_type != MessageType::BattleSystemMessages && entry.message_type != MessageType::BattleDeath {
       continue;
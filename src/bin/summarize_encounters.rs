@@ -0,0 +1,89 @@
+extern crate ffxiv_reader;
+extern crate chrono;
+
+use ffxiv_reader::archive::Archive;
+use ffxiv_reader::config::Config;
+use ffxiv_reader::events::battle::{Encounter, EncounterTracker};
+use ffxiv_reader::localization;
+
+use chrono::{Local, TimeZone};
+
+use std::env::args;
+
+// Summarizes the combat encounters in an archive from its battle lines.
+//
+// Usage: summarize_encounters <archive> [minimum seconds]
+//
+// Encounters shorter than the minimum are skipped, defaulting to 10 seconds so stray hits on the way
+// to a fight don't get their own summary. Damage is credited to whoever last used an action, so the
+// numbers are a rough parse, not a match for ACT. Battle lines from clients in other languages than
+// English are only recognized with patterns in the `[localization]` section of the config. For
+// example:
+//
+//   summarize_encounters logs 60
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.is_empty() {
+    println!("Please supply an archive directory.");
+    return;
+  }
+  let minimum: u32 = match args.get(1).map(|x| x.parse()) {
+    Some(Ok(m)) => m,
+    Some(Err(e)) => {
+      println!("Invalid minimum: {}.", e);
+      return;
+    },
+    None => 10
+  };
+  let config = match Config::load_default() {
+    Ok(c) => c,
+    Err(e) => {
+      println!("Could not load config: {}.", e);
+      return;
+    }
+  };
+  // Recognize battle lines in the languages the config has patterns for.
+  match config.localizer() {
+    Ok(l) => localization::set_localizer(l),
+    Err(e) => {
+      println!("Could not load localization: {}.", e);
+      return;
+    }
+  }
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  let mut tracker = EncounterTracker::new();
+  let mut encounters: Vec<Encounter> = archive.entries().filter_map(|x| tracker.observe(&x)).collect();
+  encounters.extend(tracker.finish());
+  let mut shown = 0;
+  for encounter in encounters.iter().filter(|x| x.duration() >= minimum) {
+    shown += 1;
+    let duration = encounter.duration();
+    println!("[{}] {} ({}:{:02})",
+             Local.timestamp(encounter.started as i64, 0).format("%d/%m/%Y %H:%M:%S"),
+             encounter.duty.as_ref().map(|x| x.as_str()).unwrap_or("Open world"),
+             duration / 60,
+             duration % 60);
+    println!("  {:<24} {:>10} {:>8} {:>10} {:>6} {:>6} {:>6}", "Name", "Damage", "DPS", "Taken", "Crit%", "DH%", "Deaths");
+    for (name, stats) in encounter.by_damage() {
+      let percent = |n: u64| if stats.hits == 0 { 0.0 } else { n as f64 * 100.0 / stats.hits as f64 };
+      println!("  {:<24} {:>10} {:>8.1} {:>10} {:>6.1} {:>6.1} {:>6}",
+               name,
+               stats.damage_done,
+               encounter.dps(name),
+               stats.damage_taken,
+               percent(stats.critical_hits),
+               percent(stats.direct_hits),
+               stats.deaths);
+    }
+    println!();
+  }
+  println!("{} encounters.", shown);
+}
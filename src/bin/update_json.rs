@@ -2,8 +2,10 @@ extern crate ffxiv_reader;
 extern crate serde_json;
 extern crate time;
 
+use ffxiv_reader::archive::jsonl;
 use ffxiv_reader::messages::entries::Entry;
 use ffxiv_reader::messages::MessageType;
+use ffxiv_reader::schema::Metadata;
 use std::env::args;
 use std::fs::File;
 use std::io::Read;
@@ -27,7 +29,15 @@ fn main() {
     println!("Could not read {}: {}", file_name, e);
     return;
   }
-  let lines = data.split('\n').filter(|x| !x.is_empty());
+  // Make sure the file was written by a version that can be read.
+  let header = match jsonl::check_header(data.lines().next().unwrap_or_default()) {
+    Ok(h) => h,
+    Err(e) => {
+      println!("Could not read {}: {}", file_name, e);
+      return;
+    }
+  };
+  let lines = data.split('\n').filter(|x| !x.is_empty()).skip(header.is_some() as usize);
   let entries: Result<Vec<Entry>, serde_json::Error> = lines.map(serde_json::from_str).collect();
   let entries = match entries {
     Ok(e) => e,
@@ -36,6 +46,9 @@ fn main() {
       return;
     }
   };
+  // The entries are rewritten by this version, so the header is too.
+  let metadata = header.map(|x| x.upgraded()).unwrap_or_else(|| Metadata::new(None));
+  println!("{}", metadata.to_line());
   for mut entry in entries {
     let message_type = if let MessageType::Unknown(id) = entry.message_type {
       id.into()
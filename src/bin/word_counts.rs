@@ -0,0 +1,58 @@
+extern crate ffxiv_reader;
+
+use ffxiv_reader::archive::Archive;
+use ffxiv_reader::filter::EntryFilter;
+use ffxiv_reader::messages::MessageType;
+use ffxiv_reader::tokenize::{default_tokenizer, word_frequencies};
+
+use std::env::args;
+
+// Lists the most common words in an archive.
+//
+// Usage: word_counts <archive> [channels] [count]
+//
+// Channels are comma-separated message type names, defaulting to every channel. The count is how
+// many words to list, defaulting to 50. Japanese is split into words too, best with the japanese
+// feature. For example, to see what a Free Company talks about:
+//
+//   word_counts logs free_company 100
+
+fn main() {
+  // Gather the arguments supplied to the program.
+  let args: Vec<String> = args().skip(1).collect();
+  if args.is_empty() {
+    println!("Please supply an archive directory.");
+    return;
+  }
+  let filter = match args.get(1) {
+    Some(list) => match list.split(',').map(|x| x.trim().parse().ok()).collect::<Option<Vec<MessageType>>>() {
+      Some(c) => EntryFilter::MessageTypes(c),
+      None => {
+        println!("Invalid channels. Please specify message types separated by commas, like say,party.");
+        return;
+      }
+    },
+    None => EntryFilter::All
+  };
+  let count = match args.get(2).map(|x| x.parse()) {
+    Some(Ok(c)) => c,
+    Some(Err(e)) => {
+      println!("Invalid count: {}.", e);
+      return;
+    },
+    None => 50
+  };
+  // Open the archive, indexing it if needed.
+  let archive = match Archive::open(&args[0]) {
+    Ok(a) => a,
+    Err(e) => {
+      println!("Could not open archive {}: {}", args[0], e);
+      return;
+    }
+  };
+  let entries: Vec<_> = archive.query(0..::std::u32::MAX, filter).collect();
+  let tokenizer = default_tokenizer();
+  for (word, times) in word_frequencies(&entries, &tokenizer).into_iter().take(count) {
+    println!("{}\t{}", times, word);
+  }
+}
@@ -0,0 +1,177 @@
+//! Characters and their log folders
+//!
+//! The game keeps the settings and chat logs of each character in a folder named like
+//! `FFXIV_CHR0040000000000000`, where the hex digits are the character's ID. A
+//! [`Character`](struct.Character.html) wraps one of these folders and the
+//! [`Archive`](../archive/struct.Archive.html) of its logs, so applications can ask for a
+//! character's entries instead of tracking paths.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! for character in Character::discover("C:/Users/me/Documents/My Games/FINAL FANTASY XIV - A Realm Reborn").unwrap() {
+//!   println!("{} ({:?})", character.id, character.name);
+//!   for entry in character.entries_between(1483228800..1514764800) {
+//!     println!("{:?}", entry);
+//!   }
+//! }
+//! ```
+
+use archive::{Archive, ArchiveQuery, FileMetadata};
+use config::Config;
+use filter::EntryFilter;
+use messages::MessageType;
+use messages::entries::Entry;
+use messages::parts::Part;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// The prefix of character folder names.
+pub const FOLDER_PREFIX: &'static str = "FFXIV_CHR";
+
+/// The number of most recent log files read when detecting a character's name.
+const NAME_DETECTION_FILES: usize = 5;
+
+/// A character and their logs.
+#[derive(Debug)]
+pub struct Character {
+  /// The character's ID, from the name of their folder.
+  pub id: String,
+  /// The character's name, if it could be detected from their logs.
+  pub name: Option<String>,
+  /// The character's home world.
+  ///
+  /// The game never logs a character's own world, so this is only known if it is set.
+  pub world: Option<String>,
+  folder: PathBuf,
+  archive: Archive
+}
+
+impl Character {
+  /// Opens a character folder, indexing its logs and detecting the character's name.
+  pub fn open<P: AsRef<Path>>(folder: P) -> io::Result<Character> {
+    let folder = folder.as_ref().to_path_buf();
+    let id = match Character::folder_id(&folder) {
+      Some(id) => id,
+      None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a character folder"))
+    };
+    let archive = Archive::open(folder.join("log"))?;
+    let mut character = Character {
+      id,
+      name: None,
+      world: None,
+      folder,
+      archive
+    };
+    character.name = character.detect_name();
+    Ok(character)
+  }
+
+  /// Opens every character folder in the game's `My Games` folder.
+  ///
+  /// Characters are returned in order of their IDs.
+  pub fn discover<P: AsRef<Path>>(game_folder: P) -> io::Result<Vec<Character>> {
+    let mut folders = Vec::new();
+    for entry in fs::read_dir(game_folder)? {
+      let path = entry?.path();
+      if path.is_dir() && Character::folder_id(&path).is_some() {
+        folders.push(path);
+      }
+    }
+    folders.sort();
+    folders.into_iter().map(Character::open).collect()
+  }
+
+  /// Opens the character folders in the configuration.
+  pub fn from_config(config: &Config) -> io::Result<Vec<Character>> {
+    config.character_folders.iter().map(Character::open).collect()
+  }
+
+  /// Gets the character ID from the name of a character folder.
+  pub fn folder_id<P: AsRef<Path>>(folder: P) -> Option<String> {
+    let name = opt!(folder.as_ref().file_name().and_then(|x| x.to_str()));
+    if !name.starts_with(FOLDER_PREFIX) {
+      return None;
+    }
+    let id = &name[FOLDER_PREFIX.len()..];
+    if id.is_empty() || !id.chars().all(|c| c.is_digit(16)) {
+      return None;
+    }
+    Some(id.to_owned())
+  }
+
+  /// The character's folder.
+  pub fn folder(&self) -> &Path {
+    &self.folder
+  }
+
+  /// The archive of the character's logs.
+  pub fn archive(&self) -> &Archive {
+    &self.archive
+  }
+
+  /// The character's indexed log files, in order of their earliest timestamp.
+  pub fn log_files(&self) -> &[FileMetadata] {
+    self.archive.files()
+  }
+
+  /// Indexes any log files that are new or have changed. Returns whether anything changed.
+  pub fn refresh(&mut self) -> io::Result<bool> {
+    let changed = self.archive.refresh()?;
+    if changed || self.name.is_none() {
+      self.name = self.detect_name().or_else(|| self.name.take());
+    }
+    Ok(changed)
+  }
+
+  /// Iterates over every entry in the character's logs.
+  pub fn entries(&self) -> ArchiveQuery {
+    self.archive.entries()
+  }
+
+  /// Iterates over the entries in the time range.
+  pub fn entries_between(&self, range: Range<u32>) -> ArchiveQuery {
+    self.archive.query(range, EntryFilter::All)
+  }
+
+  /// Queries the character's logs for entries in the time range that match the filter.
+  pub fn query(&self, range: Range<u32>, filter: EntryFilter) -> ArchiveQuery {
+    self.archive.query(range, filter)
+  }
+
+  /// Detects the character's name from their most recent logs.
+  ///
+  /// The game links every sender's name except the character's own, so the most common sender in
+  /// chat channels whose name is plain text is assumed to be the character.
+  fn detect_name(&self) -> Option<String> {
+    let files = self.archive.files();
+    let start = files.len().saturating_sub(NAME_DETECTION_FILES);
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for file in &files[start..] {
+      let path = self.archive.root().join(&file.path);
+      let entries = try_or!(file.format.read_entries(&path), continue);
+      for name in entries.iter().filter_map(own_name) {
+        *counts.entry(name).or_insert(0) += 1;
+      }
+    }
+    counts.into_iter().max_by_key(|&(_, count)| count).map(|(name, _)| name)
+  }
+}
+
+fn own_name(entry: &Entry) -> Option<String> {
+  match entry.message_type {
+    MessageType::Say | MessageType::Shout | MessageType::Yell | MessageType::Party | MessageType::Alliance |
+    MessageType::FreeCompany | MessageType::Linkshell1 | MessageType::Linkshell2 | MessageType::Linkshell3 |
+    MessageType::Linkshell4 | MessageType::Linkshell5 | MessageType::Linkshell6 | MessageType::Linkshell7 |
+    MessageType::Linkshell8 => {},
+    _ => return None
+  }
+  match entry.sender {
+    Some(Part::PlainText(_)) => entry.sender_name().filter(|x| !x.is_empty()),
+    _ => None
+  }
+}
@@ -0,0 +1,470 @@
+//! Configuration files
+//!
+//! The binaries look for a TOML configuration file at the path in the `FFXIV_READER_CONFIG`
+//! environment variable, falling back to `ffxiv_reader.toml` in the current directory. Arguments
+//! given on the command line always override the values in the file.
+//!
+//! ```toml
+//! pid = 1234
+//! stop = false
+//! act_log = "C:/Users/me/AppData/Roaming/Advanced Combat Tracker/FFXIVLogs/Network.log"
+//! blocklist = "blocked.txt"
+//! game_patch = "4.0"
+//! character_folders = ["C:/Users/me/Documents/My Games/FINAL FANTASY XIV - A Realm Reborn/FFXIV_CHR0040000000000000"]
+//!
+//! [filter]
+//! include = ["say", "party", "free_company"]
+//! exclude_senders = ["Some Spammer"]
+//! expression = 'not text contains "wts"'
+//!
+//! [[highlights]]
+//! name = "mentions"
+//! text = "my name"
+//! sound = "sounds/mention.wav"
+//!
+//! [sounds]
+//! command = "aplay"
+//! effects = { "1" = "sounds/se1.wav", "2" = "sounds/se2.wav" }
+//!
+//! [sinks.discord]
+//! type = "webhook"
+//! url = "https://discordapp.com/api/webhooks/..."
+//! channels = ["free_company"]
+//! throttle = 0.5
+//!
+//! [sinks.tells]
+//! type = "file"
+//! path = "tells.jsonl"
+//! channels = ["tell", "tell_receive"]
+//!
+//! [sinks.disk]
+//! type = "file"
+//! path = "everything.jsonl"
+//!
+//! [daemon]
+//! checkpoint = "daemon.checkpoint.json"
+//! health = "127.0.0.1:9370"
+//! stall_minutes = 15
+//!
+//! [telemetry]
+//! payload_report = "unknown_payloads.json"
+//!
+//! [names]
+//! style = "initials"
+//! strip_world = true
+//!
+//! [localization.de]
+//! obtain_gil = "Du hast {amount} Gil erhalten."
+//!
+//! [templates.forum]
+//! header = "[quote]\n"
+//! entry = "[b]{{sender}}[/b]: {{message}}"
+//! footer = "\n[/quote]"
+//!
+//! [icons.ids]
+//! "62119" = { image = "https://example.com/icons/pld.png" }
+//! ```
+
+use extract::{Extractor, PatternError};
+use filter::{EntryFilter, Blocklist};
+use filter::expression::ExpressionError;
+use localization::{Language, Localizer};
+use messages::{MessageType, HasDisplayText};
+use messages::entries::Entry;
+use names::NameDisplayPolicy;
+use render::{IconMap, TemplateError, TemplateRenderer};
+use schema::Metadata;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// The environment variable that can point to a configuration file.
+pub const CONFIG_ENV_VAR: &'static str = "FFXIV_READER_CONFIG";
+
+/// The file name used when the environment variable is not set.
+pub const DEFAULT_CONFIG_FILE: &'static str = "ffxiv_reader.toml";
+
+/// The configuration shared by the binaries and daemons.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+  /// The PID of the game, for readers that read from memory.
+  pub pid: Option<u32>,
+  /// Whether readers should stop once they run out of entries.
+  pub stop: Option<bool>,
+  /// The path to an ACT network log, for readers that read from ACT.
+  pub act_log: Option<PathBuf>,
+  /// The `FFXIV_CHR` folders of characters whose logs should be read.
+  #[serde(default)]
+  pub character_folders: Vec<PathBuf>,
+  /// The filter applied to entries by default.
+  #[serde(default)]
+  pub filter: FilterConfig,
+  /// A file of names to block, one per line.
+  pub blocklist: Option<PathBuf>,
+  /// The game patch being played, recorded in the headers of files written.
+  pub game_patch: Option<String>,
+  /// Rules for entries that should be highlighted.
+  #[serde(default)]
+  pub highlights: Vec<HighlightRule>,
+  /// Settings and credentials for sinks, by name.
+  #[serde(default)]
+  pub sinks: BTreeMap<String, SinkConfig>,
+  /// Icons for renderers to use, on top of the built-in ones.
+  #[serde(default)]
+  pub icons: IconMap,
+  /// How names are shown by the text outputs and renderers.
+  #[serde(default)]
+  pub names: NameDisplayPolicy,
+  /// Patterns for system messages in languages other than English, by language code and then
+  /// [template](../localization/index.html) key.
+  #[serde(default)]
+  pub localization: BTreeMap<String, BTreeMap<String, String>>,
+  /// Templates for exporting entries, by name.
+  #[serde(default)]
+  pub templates: BTreeMap<String, TemplateConfig>,
+  /// How to play sounds for sound effects and highlights.
+  #[serde(default)]
+  pub sounds: SoundConfig,
+  /// User-defined event extractors.
+  #[serde(default)]
+  pub extractors: Vec<ExtractorConfig>,
+  /// Settings for the capture daemon.
+  #[serde(default)]
+  pub daemon: DaemonConfig,
+  /// Opt-in reports of unknown payloads.
+  #[serde(default)]
+  pub telemetry: TelemetryConfig
+}
+
+impl Config {
+  /// Finds the configuration file to use, if there is one.
+  pub fn find() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(CONFIG_ENV_VAR) {
+      return Some(PathBuf::from(path));
+    }
+    let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+    if path.exists() {
+      Some(path)
+    } else {
+      None
+    }
+  }
+
+  /// Loads the configuration file found by `find`.
+  ///
+  /// If there is no configuration file, the default configuration is returned.
+  pub fn load_default() -> Result<Config, ConfigError> {
+    match Config::find() {
+      Some(path) => Config::load(path),
+      None => Ok(Config::default())
+    }
+  }
+
+  /// Loads a configuration file.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+    let mut data = String::new();
+    File::open(path).and_then(|mut f| f.read_to_string(&mut data)).map_err(ConfigError::Io)?;
+    Config::parse(&data)
+  }
+
+  /// Parses a configuration from TOML.
+  pub fn parse(data: &str) -> Result<Config, ConfigError> {
+    let config: Config = ::toml::from_str(data).map_err(ConfigError::Toml)?;
+    config.validate()?;
+    Ok(config)
+  }
+
+  /// The filter for entries, combining the default filter with the blocklist.
+  pub fn entry_filter(&self) -> Result<EntryFilter, ConfigError> {
+    let filter = self.filter.to_filter();
+    let path = match self.blocklist {
+      Some(ref p) => p,
+      None => return Ok(filter)
+    };
+    let blocklist = Blocklist::load(path).map_err(ConfigError::Io)?;
+    Ok(EntryFilter::And(vec![filter, blocklist.to_filter()]))
+  }
+
+  /// The localizer for system messages, with the built-in templates and the configured patterns.
+  pub fn localizer(&self) -> Result<Localizer, ConfigError> {
+    let mut localizer = Localizer::new();
+    for (code, patterns) in &self.localization {
+      let language = opt_or!(Language::from_code(code), return Err(ConfigError::UnknownLanguage(code.clone())));
+      for (key, pattern) in patterns {
+        if !localizer.add_pattern(key, language, pattern) {
+          return Err(ConfigError::InvalidLocalization(code.clone(), key.clone()));
+        }
+      }
+    }
+    Ok(localizer)
+  }
+
+  /// The metadata header for a file of entries from `source`, with the configured game patch.
+  pub fn metadata(&self, source: &str) -> Metadata {
+    let mut metadata = Metadata::new(Some(source));
+    metadata.game_patch = self.game_patch.clone();
+    metadata
+  }
+
+  fn validate(&self) -> Result<(), ConfigError> {
+    let channels = self.filter.include.iter()
+      .chain(self.filter.exclude.iter())
+      .chain(self.highlights.iter().flat_map(|x| x.channels.iter()))
+      .chain(self.extractors.iter().flat_map(|x| x.channels.iter()))
+      .chain(self.sinks.values().flat_map(|x| x.channels.iter()
+        .chain(x.filter.include.iter())
+        .chain(x.filter.exclude.iter())));
+    for channel in channels {
+      if channel.parse::<MessageType>().is_err() {
+        return Err(ConfigError::UnknownChannel(channel.clone()));
+      }
+    }
+    let expressions = Some(&self.filter).into_iter()
+      .chain(self.sinks.values().map(|x| &x.filter))
+      .filter_map(|x| x.expression.as_ref());
+    for expression in expressions {
+      if let Err(e) = expression.parse::<EntryFilter>() {
+        return Err(ConfigError::InvalidFilter(e));
+      }
+    }
+    for extractor in &self.extractors {
+      if let Err(e) = Extractor::from_config(extractor) {
+        return Err(ConfigError::InvalidPattern(extractor.name.clone(), e));
+      }
+    }
+    self.localizer()?;
+    Ok(())
+  }
+}
+
+/// The default filter section of the configuration.
+///
+/// Channels are message type names, such as `free_company` or `FreeCompany`, or numeric IDs.
+#[derive(Debug, Default, Deserialize)]
+pub struct FilterConfig {
+  /// Only keep entries from these channels. Everything is kept if this is empty.
+  #[serde(default)]
+  pub include: Vec<String>,
+  /// Never keep entries from these channels.
+  #[serde(default)]
+  pub exclude: Vec<String>,
+  /// Only keep entries from these senders. Everything is kept if this is empty.
+  #[serde(default)]
+  pub senders: Vec<String>,
+  /// Never keep entries from these senders.
+  #[serde(default)]
+  pub exclude_senders: Vec<String>,
+  /// Only keep entries matching this [filter expression](../filter/expression/index.html).
+  pub expression: Option<String>
+}
+
+impl FilterConfig {
+  /// Converts this section into an [`EntryFilter`](../filter/enum.EntryFilter.html).
+  pub fn to_filter(&self) -> EntryFilter {
+    let mut filters = Vec::new();
+    if !self.include.is_empty() {
+      filters.push(EntryFilter::MessageTypes(parse_channels(&self.include)));
+    }
+    if !self.exclude.is_empty() {
+      filters.push(EntryFilter::Not(Box::new(EntryFilter::MessageTypes(parse_channels(&self.exclude)))));
+    }
+    if !self.senders.is_empty() {
+      filters.push(EntryFilter::Senders(self.senders.clone()));
+    }
+    if !self.exclude_senders.is_empty() {
+      filters.push(EntryFilter::Not(Box::new(EntryFilter::Senders(self.exclude_senders.clone()))));
+    }
+    // The expression is checked when the config is loaded, so it only fails to parse here if this
+    // section was built by hand.
+    if let Some(filter) = self.expression.as_ref().and_then(|x| x.parse().ok()) {
+      filters.push(filter);
+    }
+    match filters.len() {
+      0 => EntryFilter::All,
+      1 => filters.remove(0),
+      _ => EntryFilter::And(filters)
+    }
+  }
+}
+
+/// A template for exporting entries.
+///
+/// See [`Template`](../render/struct.Template.html) for what the entry template can hold.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateConfig {
+  /// The template for each entry.
+  pub entry: String,
+  /// Text at the start of the export.
+  #[serde(default)]
+  pub header: String,
+  /// Text at the end of the export.
+  #[serde(default)]
+  pub footer: String,
+  /// Text between entries, defaulting to a newline.
+  pub separator: Option<String>
+}
+
+impl TemplateConfig {
+  /// Creates a renderer for this template.
+  pub fn renderer(&self) -> Result<TemplateRenderer, TemplateError> {
+    let mut renderer = TemplateRenderer::parse(&self.entry)?;
+    renderer.header = self.header.clone();
+    renderer.footer = self.footer.clone();
+    if let Some(ref separator) = self.separator {
+      renderer.separator = separator.clone();
+    }
+    Ok(renderer)
+  }
+}
+
+/// A rule for highlighting entries.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HighlightRule {
+  /// The name of the rule.
+  pub name: String,
+  /// The text to look for in messages, ignoring case.
+  pub text: String,
+  /// The channels the rule applies to. The rule applies to every channel if this is empty.
+  #[serde(default)]
+  pub channels: Vec<String>,
+  /// A sound file to play when the rule matches.
+  pub sound: Option<PathBuf>
+}
+
+impl HighlightRule {
+  /// Checks if the entry should be highlighted by this rule.
+  pub fn matches(&self, entry: &Entry) -> bool {
+    if !self.channels.is_empty() && !parse_channels(&self.channels).contains(&entry.message_type) {
+      return false;
+    }
+    entry.message.display_text().to_lowercase().contains(&self.text.to_lowercase())
+  }
+}
+
+/// The sound section of the configuration.
+#[derive(Debug, Default, Deserialize)]
+pub struct SoundConfig {
+  /// The program used to play sound files, such as `aplay` or `afplay`.
+  pub command: Option<String>,
+  /// Arguments given to the program before the file.
+  #[serde(default)]
+  pub args: Vec<String>,
+  /// Sound files for each sound effect, keyed by number (`"1"` for `<se.1>`).
+  #[serde(default)]
+  pub effects: BTreeMap<String, PathBuf>
+}
+
+/// The daemon section of the configuration.
+#[derive(Debug, Default, Deserialize)]
+pub struct DaemonConfig {
+  /// The file the daemon saves its place in, so restarts don't repeat entries.
+  pub checkpoint: Option<PathBuf>,
+  /// How often to save the checkpoint, in seconds.
+  pub checkpoint_interval: Option<u64>,
+  /// The address to serve the health endpoint on, such as `127.0.0.1:9370`.
+  pub health: Option<String>,
+  /// How many minutes without entries count as capture having stalled. `0` turns this off.
+  pub stall_minutes: Option<u64>
+}
+
+/// The telemetry section of the configuration.
+#[derive(Debug, Default, Deserialize)]
+pub struct TelemetryConfig {
+  /// The file to keep a report of unknown payloads in. Nothing is collected without it. See
+  /// [`telemetry`](../telemetry/index.html).
+  pub payload_report: Option<PathBuf>
+}
+
+/// A user-defined event extractor. See [`extract`](../extract/index.html).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExtractorConfig {
+  /// The name given to events from this extractor.
+  pub name: String,
+  /// The regular expression matched against the plain text of messages.
+  pub pattern: String,
+  /// The channels the extractor applies to. The extractor applies to every channel if this is empty.
+  #[serde(default)]
+  pub channels: Vec<String>
+}
+
+/// Settings and credentials for a sink.
+///
+/// What each field means is up to the sink using it.
+#[derive(Debug, Deserialize)]
+pub struct SinkConfig {
+  /// The kind of sink, such as `file` or `webhook`.
+  #[serde(rename = "type")]
+  pub kind: String,
+  /// A path, for sinks that write to files.
+  pub path: Option<PathBuf>,
+  /// A URL, for sinks that send entries over the network.
+  pub url: Option<String>,
+  /// A token or password for the sink.
+  pub token: Option<String>,
+  /// The channels routed to the sink. Every channel is routed if this is empty.
+  #[serde(default)]
+  pub channels: Vec<String>,
+  /// A further filter entries must match to be routed to the sink.
+  #[serde(default)]
+  pub filter: FilterConfig,
+  /// The most entries per second to send from a channel while it is bursting, such as during shout
+  /// spam. Entries are never throttled if this is not set.
+  pub throttle: Option<f64>
+}
+
+impl SinkConfig {
+  /// The filter deciding which entries are routed to the sink.
+  pub fn route_filter(&self) -> EntryFilter {
+    let filter = self.filter.to_filter();
+    if self.channels.is_empty() {
+      return filter;
+    }
+    EntryFilter::And(vec![EntryFilter::MessageTypes(parse_channels(&self.channels)), filter])
+  }
+}
+
+fn parse_channels(channels: &[String]) -> Vec<MessageType> {
+  channels.iter().filter_map(|x| x.parse().ok()).collect()
+}
+
+/// An error encountered while loading a configuration file.
+#[derive(Debug)]
+pub enum ConfigError {
+  /// The file could not be read.
+  Io(io::Error),
+  /// The file is not valid TOML or does not match the configuration format.
+  Toml(::toml::de::Error),
+  /// A channel name did not match any message type.
+  UnknownChannel(String),
+  /// An extractor's pattern is not a valid regular expression. Contains the extractor's name.
+  InvalidPattern(String, PatternError),
+  /// A filter expression could not be parsed.
+  InvalidFilter(ExpressionError),
+  /// A localization language code did not match any language.
+  UnknownLanguage(String),
+  /// A localized pattern did not match any template, or had different placeholders than it.
+  /// Contains the language code and the template key.
+  InvalidLocalization(String, String)
+}
+
+impl Display for ConfigError {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match *self {
+      ConfigError::Io(ref e) => write!(f, "could not read config: {}", e),
+      ConfigError::Toml(ref e) => write!(f, "invalid config: {}", e),
+      ConfigError::UnknownChannel(ref c) => write!(f, "unknown channel in config: {}", c),
+      ConfigError::InvalidPattern(ref n, ref e) => write!(f, "invalid pattern for extractor {}: {}", n, e),
+      ConfigError::InvalidFilter(ref e) => write!(f, "invalid config: {}", e),
+      ConfigError::UnknownLanguage(ref l) => write!(f, "unknown language in config: {}", l),
+      ConfigError::InvalidLocalization(ref l, ref k) => write!(f, "invalid {} pattern for template in config: {}", l, k)
+    }
+  }
+}
+
+impl Error for ConfigError {}
@@ -0,0 +1,462 @@
+//! A long-running capture daemon
+//!
+//! The [`Daemon`](struct.Daemon.html) sits between a reader and a [`Pipeline`](../pipeline/struct.Pipeline.html).
+//! It parses entries, drops any it has already seen, and keeps a
+//! [`Checkpoint`](struct.Checkpoint.html) on disk so a restarted daemon doesn't send the same entries
+//! to its sinks again. The game keeps its whole chat buffer in memory, so without a checkpoint every
+//! restart would repeat everything still in it. The checkpoint only moves past entries once every
+//! sink has [synced](../pipeline/trait.Sink.html#method.sync) them, so a crash repeats a few entries
+//! rather than losing them.
+//!
+//! Entries are watched by a [`FormatMonitor`](../diagnostics/struct.FormatMonitor.html). If the
+//! game's format looks like it changed, entries with anomalies are withheld from the pipeline
+//! instead of being sent on as garbage.
+//!
+//! An optional health endpoint answers every HTTP request with a JSON
+//! [`StatusReport`](struct.StatusReport.html). It responds with `503 Service Unavailable` while the
+//! format looks changed, so ordinary HTTP checks notice.
+//!
+//! A [`Watchdog`](../watchdog/struct.Watchdog.html) notices when capture stops, such as when no
+//! entries arrive for a while or the log being read is rotated. Each problem is sent as an event to
+//! everything listening on [`Daemon::events`](struct.Daemon.html#method.events), and the health
+//! endpoint answers `503` until it clears.
+//!
+//! If the user opts in, unknown payloads are also collected into a
+//! [report](../telemetry/index.html) they can send along with a bug report.
+
+use config::DaemonConfig;
+use diagnostics::{Anomaly, FormatMonitor, FormatReport, FormatState};
+use messages::entries::{Entry, Provenance, RawEntry};
+use pipeline::Pipeline;
+use rate::{ChannelRate, RateMonitor};
+use telemetry::PayloadCollector;
+use watchdog::{Watchdog, WatchdogEvent};
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Where the daemon left off.
+///
+/// Entries are expected in timestamp order. The keys of the entries at the latest timestamp are kept
+/// too, since many entries can share a second.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+  /// The latest timestamp seen.
+  pub timestamp: u32,
+  /// The [`dedup_key`s](../messages/entries/struct.Entry.html#method.dedup_key) of the entries seen
+  /// at `timestamp`.
+  pub keys: Vec<u64>
+}
+
+impl Checkpoint {
+  /// Loads a checkpoint, or returns an empty one if the file doesn't exist.
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Checkpoint> {
+    let mut data = String::new();
+    match File::open(path) {
+      Ok(mut f) => f.read_to_string(&mut data)?,
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Checkpoint::default()),
+      Err(e) => return Err(e)
+    };
+    ::serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+
+  /// Saves the checkpoint, replacing the file atomically where the platform allows.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let data = ::serde_json::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let temp = path.with_extension("tmp");
+    File::create(&temp)?.write_all(data.as_bytes())?;
+    ::std::fs::rename(&temp, path)
+  }
+
+  /// Checks if an entry comes after the checkpoint, moving the checkpoint up to it if it does.
+  pub fn admits(&mut self, entry: &Entry) -> bool {
+    if entry.timestamp < self.timestamp {
+      return false;
+    }
+    let key = entry.dedup_key();
+    if entry.timestamp == self.timestamp {
+      if self.keys.contains(&key) {
+        return false;
+      }
+      self.keys.push(key);
+      return true;
+    }
+    self.timestamp = entry.timestamp;
+    self.keys.clear();
+    self.keys.push(key);
+    true
+  }
+}
+
+/// Counters shared between the daemon and its health endpoint.
+#[derive(Debug)]
+pub struct Status {
+  started: Instant,
+  entries: AtomicUsize,
+  duplicates: AtomicUsize,
+  withheld: AtomicUsize,
+  last_entry: AtomicUsize,
+  format: Mutex<FormatMonitor>,
+  rates: Mutex<RateMonitor>,
+  stall: Mutex<Option<WatchdogEvent>>,
+  capture: Mutex<Option<WatchdogEvent>>
+}
+
+impl Status {
+  fn new() -> Self {
+    Status {
+      started: Instant::now(),
+      entries: AtomicUsize::new(0),
+      duplicates: AtomicUsize::new(0),
+      withheld: AtomicUsize::new(0),
+      last_entry: AtomicUsize::new(0),
+      format: Mutex::new(FormatMonitor::new()),
+      rates: Mutex::new(RateMonitor::new()),
+      stall: Mutex::new(None),
+      capture: Mutex::new(None)
+    }
+  }
+
+  /// A snapshot of the counters.
+  pub fn report(&self) -> StatusReport {
+    let last_entry = match self.last_entry.load(Ordering::Relaxed) {
+      0 => None,
+      t => Some(t as u32)
+    };
+    StatusReport {
+      uptime: self.started.elapsed().as_secs(),
+      entries: self.entries.load(Ordering::Relaxed),
+      duplicates: self.duplicates.load(Ordering::Relaxed),
+      withheld: self.withheld.load(Ordering::Relaxed),
+      last_entry,
+      lag: last_entry.map(|x| now().saturating_sub(x)),
+      format: self.format.lock().unwrap().report(),
+      rates: self.rates.lock().unwrap().rates(),
+      stall: self.stall.lock().unwrap().clone(),
+      capture: self.capture.lock().unwrap().clone()
+    }
+  }
+}
+
+/// What the health endpoint reports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusReport {
+  /// Seconds since the daemon started.
+  pub uptime: u64,
+  /// The number of entries sent to the pipeline.
+  pub entries: usize,
+  /// The number of entries dropped for being before the checkpoint.
+  pub duplicates: usize,
+  /// The number of entries withheld because the format looked changed.
+  pub withheld: usize,
+  /// The timestamp of the latest entry sent to the pipeline.
+  pub last_entry: Option<u32>,
+  /// Seconds between the latest entry and now.
+  pub lag: Option<u32>,
+  /// Whether the game's format looks like it changed.
+  pub format: FormatReport,
+  /// How busy each channel is, busiest first.
+  pub rates: Vec<ChannelRate>,
+  /// The current stall, if entries haven't resumed since.
+  pub stall: Option<WatchdogEvent>,
+  /// The latest problem with the source, like the log file being rotated. These don't clear when
+  /// entries resume, since the daemon could be reading the wrong file.
+  pub capture: Option<WatchdogEvent>
+}
+
+/// Options for a [`Daemon`](struct.Daemon.html).
+#[derive(Debug, Clone)]
+pub struct DaemonOptions {
+  /// The checkpoint file. Entries are only deduplicated within one run without it.
+  pub checkpoint: Option<PathBuf>,
+  /// How often to save the checkpoint while running. It is always saved on shutdown.
+  pub checkpoint_interval: Duration,
+  /// The address to serve the health endpoint on.
+  pub health: Option<String>,
+  /// How long without entries counts as capture having stalled. Zero turns this off.
+  pub stall_after: Duration,
+  /// The log file the source reads from, if any, watched for being rotated or removed.
+  pub watch_file: Option<PathBuf>,
+  /// The file to report unknown payloads in, if the user opted in.
+  ///
+  /// The report is saved along with the checkpoint.
+  pub payload_report: Option<PathBuf>
+}
+
+impl DaemonOptions {
+  /// Create options from the daemon section of the configuration.
+  pub fn from_config(config: &DaemonConfig) -> Self {
+    DaemonOptions {
+      checkpoint: config.checkpoint.clone(),
+      checkpoint_interval: Duration::from_secs(config.checkpoint_interval.unwrap_or(10)),
+      health: config.health.clone(),
+      stall_after: Duration::from_secs(60 * config.stall_minutes.unwrap_or(15)),
+      watch_file: None,
+      payload_report: None
+    }
+  }
+}
+
+impl Default for DaemonOptions {
+  fn default() -> Self {
+    DaemonOptions::from_config(&DaemonConfig::default())
+  }
+}
+
+/// Where the daemon gets its entries from.
+pub enum Source {
+  /// Raw bytes and where they were read from, from
+  /// [`MemoryEntryReader::start_traced`](../struct.MemoryEntryReader.html#method.start_traced).
+  Raw(Receiver<(Vec<u8>, Provenance)>),
+  /// Parsed entries, from [`ActReader::start`](../act/struct.ActReader.html#method.start).
+  Parsed(Receiver<Entry>)
+}
+
+/// A handle that can stop a running daemon from another thread, such as a signal handler.
+#[derive(Debug, Clone)]
+pub struct StopHandle {
+  run: Arc<AtomicBool>
+}
+
+impl StopHandle {
+  pub fn stop(&self) {
+    self.run.store(false, Ordering::Relaxed);
+  }
+}
+
+/// Composes a reader, deduplication, checkpoints, and a pipeline into something that can run until
+/// it is stopped.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let reader = MemoryEntryReader::new(my_pid, false);
+/// let daemon = Daemon::new(DaemonOptions::default());
+/// let stop = daemon.stop_handle();
+/// // Call stop.stop() from a signal handler.
+/// daemon.run(Source::Raw(reader.start_traced().unwrap()), my_pipeline).unwrap();
+/// ```
+pub struct Daemon {
+  options: DaemonOptions,
+  status: Arc<Status>,
+  run: Arc<AtomicBool>,
+  listeners: Mutex<Vec<Sender<WatchdogEvent>>>
+}
+
+impl Daemon {
+  pub fn new(options: DaemonOptions) -> Self {
+    Daemon {
+      options,
+      status: Arc::new(Status::new()),
+      run: Arc::new(AtomicBool::new(true)),
+      listeners: Mutex::new(Vec::new())
+    }
+  }
+
+  /// Listens for problems with capture, and for when they clear.
+  pub fn events(&self) -> Receiver<WatchdogEvent> {
+    let (tx, rx) = channel();
+    self.listeners.lock().unwrap().push(tx);
+    rx
+  }
+
+  /// The daemon's counters.
+  pub fn status(&self) -> Arc<Status> {
+    self.status.clone()
+  }
+
+  /// A handle for stopping the daemon.
+  pub fn stop_handle(&self) -> StopHandle {
+    StopHandle {
+      run: self.run.clone()
+    }
+  }
+
+  /// Runs until the source is exhausted, the daemon is stopped, or saving fails.
+  ///
+  /// On the way out, the pipeline is drained and joined so every sink is flushed, and then the
+  /// checkpoint is saved, even if an error is being returned.
+  ///
+  /// The pipeline should use [`Backpressure::Block`](../pipeline/enum.Backpressure.html). Entries
+  /// are admitted by the checkpoint before they reach the pipeline, so any it drops are never sent
+  /// again.
+  pub fn run(&self, source: Source, mut pipeline: Pipeline) -> io::Result<()> {
+    let mut checkpoint = match self.options.checkpoint {
+      Some(ref p) => Checkpoint::load(p)?,
+      None => Checkpoint::default()
+    };
+    let mut payloads = match self.options.payload_report {
+      Some(ref p) => Some(PayloadCollector::open(p)?),
+      None => None
+    };
+    let mut watchdog = Watchdog::new(self.options.stall_after);
+    if let Some(ref p) = self.options.watch_file {
+      watchdog.watch_file(p)?;
+    }
+    if let Some(ref addr) = self.options.health {
+      serve_health(addr, self.status.clone(), self.run.clone())?;
+    }
+
+    let (tx, rx) = sync_channel(256);
+    pipeline.set_sync_interval(self.options.checkpoint_interval);
+    pipeline.start_parsed(rx);
+    // The number of entries sent to the pipeline, and the checkpoint as it was at earlier counts,
+    // waiting for the sinks to sync that far.
+    let mut sent = 0;
+    let mut unsynced: VecDeque<(usize, Checkpoint)> = VecDeque::new();
+    let mut last_save = Instant::now();
+    let mut result = Ok(());
+    while self.run.load(Ordering::Relaxed) {
+      let entry = match source {
+        Source::Raw(ref rx) => match rx.recv_timeout(Duration::from_millis(100)) {
+          Ok((bytes, provenance)) => self.status.format.lock().unwrap().observe_raw(RawEntry::new(bytes)).map(|mut x| {
+            x.provenance = Some(provenance);
+            x
+          }),
+          Err(RecvTimeoutError::Timeout) => None,
+          Err(RecvTimeoutError::Disconnected) => {
+            self.emit(WatchdogEvent::Disconnected);
+            break;
+          }
+        },
+        Source::Parsed(ref rx) => match rx.recv_timeout(Duration::from_millis(100)) {
+          Ok(entry) => {
+            self.status.format.lock().unwrap().observe(&entry);
+            Some(entry)
+          },
+          Err(RecvTimeoutError::Timeout) => None,
+          Err(RecvTimeoutError::Disconnected) => {
+            self.emit(WatchdogEvent::Disconnected);
+            break;
+          }
+        }
+      };
+      if entry.is_some() {
+        if let Some(event) = watchdog.entry() {
+          self.emit(event);
+        }
+      }
+      for event in watchdog.check() {
+        self.emit(event);
+      }
+      if let Some(entry) = entry {
+        if let Some(ref mut p) = payloads {
+          p.observe(&entry);
+        }
+        if self.withholds(&entry) {
+          self.status.withheld.fetch_add(1, Ordering::Relaxed);
+        } else if checkpoint.admits(&entry) {
+          self.status.entries.fetch_add(1, Ordering::Relaxed);
+          self.status.last_entry.store(entry.timestamp as usize, Ordering::Relaxed);
+          self.status.rates.lock().unwrap().observe(&entry);
+          try_or!(tx.send(entry), break);
+          sent += 1;
+        } else {
+          self.status.duplicates.fetch_add(1, Ordering::Relaxed);
+        }
+      }
+      if last_save.elapsed() >= self.options.checkpoint_interval {
+        unsynced.push_back((sent, checkpoint.clone()));
+        let synced = pipeline.synced();
+        let mut latest = None;
+        while unsynced.front().map(|x| x.0 <= synced).unwrap_or(false) {
+          latest = unsynced.pop_front();
+        }
+        let saved = match latest {
+          Some((_, ref c)) => self.save_checkpoint(c),
+          None => Ok(())
+        };
+        let saved = match payloads {
+          Some(ref mut p) => saved.and_then(|_| p.save()),
+          None => saved
+        };
+        if let Err(e) = saved {
+          result = Err(e);
+          break;
+        }
+        last_save = Instant::now();
+      }
+    }
+    self.run.store(false, Ordering::Relaxed);
+
+    // Closing the channel lets the pipeline finish what it has and flush the sinks, after which
+    // everything sent is written and the checkpoint can be saved as it is.
+    drop(tx);
+    pipeline.join();
+    let saved = match payloads {
+      Some(ref mut p) => p.save(),
+      None => Ok(())
+    };
+    let checkpointed = self.save_checkpoint(&checkpoint);
+    result.and(saved).and(checkpointed)
+  }
+
+  /// Checks if an entry should be kept from the pipeline, which is when it has an anomaly and the
+  /// format looks changed.
+  fn withholds(&self, entry: &Entry) -> bool {
+    let monitor = self.status.format.lock().unwrap();
+    monitor.state() == FormatState::Changed && Anomaly::find(entry, now()).is_some()
+  }
+
+  /// Records an event in the status and sends it to every listener still listening.
+  fn emit(&self, event: WatchdogEvent) {
+    match event {
+      // Entries arriving only fixes a stall, not a rotated file.
+      WatchdogEvent::Resumed { .. } => *self.status.stall.lock().unwrap() = None,
+      WatchdogEvent::Stalled { .. } => *self.status.stall.lock().unwrap() = Some(event.clone()),
+      _ => *self.status.capture.lock().unwrap() = Some(event.clone())
+    }
+    self.listeners.lock().unwrap().retain(|x| x.send(event.clone()).is_ok());
+  }
+
+  fn save_checkpoint(&self, checkpoint: &Checkpoint) -> io::Result<()> {
+    match self.options.checkpoint {
+      Some(ref p) => checkpoint.save(p),
+      None => Ok(())
+    }
+  }
+}
+
+fn serve_health(addr: &str, status: Arc<Status>, run: Arc<AtomicBool>) -> io::Result<()> {
+  let listener = TcpListener::bind(addr)?;
+  listener.set_nonblocking(true)?;
+  thread::spawn(move || {
+    while run.load(Ordering::Relaxed) {
+      match listener.accept() {
+        Ok((stream, _)) => { let _ = respond(stream, &status); },
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(100)),
+        Err(_) => break
+      }
+    }
+  });
+  Ok(())
+}
+
+fn respond(mut stream: TcpStream, status: &Status) -> io::Result<()> {
+  stream.set_nonblocking(false)?;
+  stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+  // The request doesn't matter, but it should be read before answering.
+  let mut request = [0; 1024];
+  let _ = stream.read(&mut request);
+  let report = status.report();
+  let line = if report.format.state == FormatState::Changed || report.stall.is_some() || report.capture.is_some() {
+    "503 Service Unavailable"
+  } else {
+    "200 OK"
+  };
+  let body = ::serde_json::to_string(&report).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+  write!(stream, "HTTP/1.0 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", line, body.len(), body)
+}
+
+fn now() -> u32 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs() as u32).unwrap_or(0)
+}
@@ -0,0 +1,201 @@
+//! Detecting changes to the game's formats
+//!
+//! Patches can change the layout of entries in memory without warning. When that happens, the
+//! parser keeps going and produces entries full of unknown types and unparsed bytes. A
+//! [`FormatMonitor`](struct.FormatMonitor.html) watches for a spike in these over a window of
+//! recent entries, so a changed format shows up as a clear state instead of garbage in the logs.
+//!
+//! Some anomalies are normal, since not every message type and structure is known yet, so the
+//! monitor only changes state when they make up a large part of the window.
+
+use messages::MessageType;
+use messages::entries::{Entry, RawEntry};
+use messages::parts::Part;
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The earliest plausible timestamp, from the release of A Realm Reborn.
+pub const EARLIEST_TIMESTAMP: u32 = 1377561600;
+
+/// How far in the future a timestamp can be before it is implausible, in seconds.
+pub const MAX_CLOCK_SKEW: u32 = 86400;
+
+/// Something about an entry that suggests the format has changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anomaly {
+  /// The raw bytes couldn't be parsed into an entry at all.
+  Unparsed,
+  /// The message type isn't known.
+  UnknownType,
+  /// Part of the sender or message wasn't valid text.
+  RawBytes,
+  /// The message contains the start of a structure that couldn't be parsed.
+  StrayMarker,
+  /// The timestamp is before the game was released or too far in the future.
+  BadTimestamp
+}
+
+impl Anomaly {
+  /// Finds the first anomaly in an entry, if any.
+  pub fn find(entry: &Entry, now: u32) -> Option<Anomaly> {
+    if entry.timestamp < EARLIEST_TIMESTAMP || entry.timestamp > now.saturating_add(MAX_CLOCK_SKEW) {
+      return Some(Anomaly::BadTimestamp);
+    }
+    if let MessageType::Unknown(_) = entry.message_type {
+      return Some(Anomaly::UnknownType);
+    }
+    let parts = entry.sender.iter().chain(entry.message.parts.iter());
+    for part in parts {
+      match *part {
+        Part::Bytes(_) => return Some(Anomaly::RawBytes),
+        // Structures start with 0x02, so one left in text wasn't recognized.
+        Part::PlainText(ref t) if t.contains('\u{2}') => return Some(Anomaly::StrayMarker),
+        _ => {}
+      }
+    }
+    None
+  }
+}
+
+/// What the monitor thinks of the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormatState {
+  /// Too few entries have been seen to tell.
+  Unknown,
+  /// Entries look normal.
+  Ok,
+  /// More entries than usual have anomalies.
+  Suspect,
+  /// Most entries have anomalies, so the format may have changed.
+  Changed
+}
+
+/// A snapshot of a [`FormatMonitor`](struct.FormatMonitor.html).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormatReport {
+  pub state: FormatState,
+  /// The share of entries in the window with an anomaly, from 0 to 1.
+  pub anomaly_rate: f64,
+  /// The number of entries seen.
+  pub entries: usize,
+  /// The number of raw entries that couldn't be parsed.
+  pub unparsed: usize,
+  /// The number of entries with unknown message types.
+  pub unknown_types: usize,
+  /// The number of entries with unparsed bytes or structures.
+  pub malformed: usize,
+  /// The number of entries with implausible timestamps.
+  pub bad_timestamps: usize
+}
+
+/// Watches entries for signs that the game's format has changed.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut monitor = FormatMonitor::new();
+/// for bytes in reader.start().unwrap() {
+///   let entry = monitor.observe_raw(RawEntry::new(bytes));
+///   if monitor.state() == FormatState::Changed {
+///     println!("The chat log format may have changed.");
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FormatMonitor {
+  /// The number of recent entries the anomaly rate is measured over.
+  pub window: usize,
+  /// The fewest entries needed before leaving the unknown state.
+  pub min_entries: usize,
+  /// The anomaly rate at which the format is suspect.
+  pub suspect_rate: f64,
+  /// The anomaly rate at which the format is considered changed.
+  pub changed_rate: f64,
+  recent: VecDeque<bool>,
+  report: FormatReport
+}
+
+impl FormatMonitor {
+  pub fn new() -> Self {
+    FormatMonitor {
+      window: 200,
+      min_entries: 50,
+      suspect_rate: 0.2,
+      changed_rate: 0.5,
+      recent: VecDeque::new(),
+      report: FormatReport {
+        state: FormatState::Unknown,
+        anomaly_rate: 0.0,
+        entries: 0,
+        unparsed: 0,
+        unknown_types: 0,
+        malformed: 0,
+        bad_timestamps: 0
+      }
+    }
+  }
+
+  /// Parses a raw entry, recording whether it parsed and any anomaly in it.
+  pub fn observe_raw(&mut self, raw: RawEntry) -> Option<Entry> {
+    let entry = raw.as_entry();
+    match entry {
+      Some(ref e) => { self.observe(e); },
+      None => self.record(Some(Anomaly::Unparsed))
+    }
+    entry
+  }
+
+  /// Records any anomaly in an entry, returning it.
+  pub fn observe(&mut self, entry: &Entry) -> Option<Anomaly> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs() as u32).unwrap_or(0);
+    let anomaly = Anomaly::find(entry, now);
+    self.record(anomaly);
+    anomaly
+  }
+
+  /// Records an entry with the given anomaly, or none.
+  pub fn record(&mut self, anomaly: Option<Anomaly>) {
+    self.report.entries += 1;
+    match anomaly {
+      Some(Anomaly::Unparsed) => self.report.unparsed += 1,
+      Some(Anomaly::UnknownType) => self.report.unknown_types += 1,
+      Some(Anomaly::RawBytes) | Some(Anomaly::StrayMarker) => self.report.malformed += 1,
+      Some(Anomaly::BadTimestamp) => self.report.bad_timestamps += 1,
+      None => {}
+    }
+    self.recent.push_back(anomaly.is_some());
+    while self.recent.len() > self.window {
+      self.recent.pop_front();
+    }
+    let anomalies = self.recent.iter().filter(|x| **x).count();
+    self.report.anomaly_rate = anomalies as f64 / self.recent.len() as f64;
+    self.report.state = if self.recent.len() < self.min_entries {
+      FormatState::Unknown
+    } else if self.report.anomaly_rate >= self.changed_rate {
+      FormatState::Changed
+    } else if self.report.anomaly_rate >= self.suspect_rate {
+      FormatState::Suspect
+    } else {
+      FormatState::Ok
+    };
+  }
+
+  /// The current state.
+  pub fn state(&self) -> FormatState {
+    self.report.state
+  }
+
+  /// The current state and counters.
+  pub fn report(&self) -> FormatReport {
+    self.report.clone()
+  }
+}
+
+impl Default for FormatMonitor {
+  fn default() -> Self {
+    FormatMonitor::new()
+  }
+}
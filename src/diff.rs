@@ -0,0 +1,167 @@
+//! Comparing two captures of the same period
+//!
+//! Captures made in different ways, like reading memory and reading the game's log files, or on two
+//! machines in the same party, should hold the same entries. [`diff`](fn.diff.html) matches entries
+//! by their [`dedup_key`](../messages/entries/struct.Entry.html#method.dedup_key) and reports the
+//! ones only one capture has, so missing or mangled entries can be found.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let memory = jsonl::read_entries("memory.jsonl").unwrap();
+//! let files = Archive::open("logs").unwrap().entries().collect::<Vec<_>>();
+//! let diff = diff(&memory, &files, true);
+//! println!("{} matched, {} only in memory, {} only in files", diff.matched, diff.only_left.len(), diff.only_right.len());
+//! ```
+
+use messages::entries::Entry;
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// The differences between two captures.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureDiff {
+  /// The number of entries in both captures.
+  pub matched: usize,
+  /// Entries only in the left capture, sorted by timestamp.
+  pub only_left: Vec<Entry>,
+  /// Entries only in the right capture, sorted by timestamp.
+  pub only_right: Vec<Entry>,
+  /// The range of timestamps that was compared, or `None` if there was nothing to compare.
+  pub range: Option<Range<u32>>
+}
+
+impl CaptureDiff {
+  /// Checks if any entries were compared. Nothing is compared if both captures are empty, or if
+  /// only the overlap was compared and the captures don't overlap.
+  pub fn compared(&self) -> bool {
+    self.range.is_some()
+  }
+
+  /// Checks if the captures were compared and had the same entries.
+  ///
+  /// This is false if nothing was compared, since that says nothing about whether they agree.
+  pub fn is_empty(&self) -> bool {
+    self.compared() && self.only_left.is_empty() && self.only_right.is_empty()
+  }
+}
+
+/// Compares two captures.
+///
+/// Entries that appear more than once, like a message sent twice in the same second, must appear
+/// the same number of times in both captures to match.
+///
+/// If `overlap_only` is true, only entries in the range of timestamps both captures cover are
+/// compared, so captures that started or stopped at different times aren't reported as missing
+/// everything the other saw before or after.
+pub fn diff<'a, L, R>(left: L, right: R, overlap_only: bool) -> CaptureDiff
+  where L: IntoIterator<Item = &'a Entry>,
+        R: IntoIterator<Item = &'a Entry>
+{
+  let left: Vec<&Entry> = left.into_iter().collect();
+  let right: Vec<&Entry> = right.into_iter().collect();
+  let range = if overlap_only {
+    match (span(&left), span(&right)) {
+      (Some(l), Some(r)) if l.start.max(r.start) < l.end.min(r.end) => l.start.max(r.start)..l.end.min(r.end),
+      _ => return CaptureDiff::default()
+    }
+  } else {
+    match (span(&left), span(&right)) {
+      (Some(l), Some(r)) => l.start.min(r.start)..l.end.max(r.end),
+      (Some(x), None) | (None, Some(x)) => x,
+      (None, None) => return CaptureDiff::default()
+    }
+  };
+  let (start, end) = (range.start, range.end);
+  let in_range = |e: &&Entry| e.timestamp >= start && e.timestamp < end;
+  let left: Vec<&Entry> = left.into_iter().filter(&in_range).collect();
+  let right: Vec<&Entry> = right.into_iter().filter(&in_range).collect();
+
+  // Count the right entries, then cross off each left entry that has a match.
+  let mut counts: HashMap<u64, usize> = HashMap::new();
+  for entry in &right {
+    *counts.entry(entry.dedup_key()).or_insert(0) += 1;
+  }
+  let mut matched = 0;
+  let mut only_left = Vec::new();
+  for entry in left {
+    let count = counts.entry(entry.dedup_key()).or_insert(0);
+    if *count > 0 {
+      *count -= 1;
+      matched += 1;
+    } else {
+      only_left.push(entry.clone());
+    }
+  }
+  // Whatever is left uncrossed is only on the right, keeping the last copies of duplicates.
+  let mut only_right = Vec::new();
+  for entry in right.into_iter().rev() {
+    if let Some(count) = counts.get_mut(&entry.dedup_key()) {
+      if *count > 0 {
+        *count -= 1;
+        only_right.push(entry.clone());
+      }
+    }
+  }
+  only_right.reverse();
+  only_left.sort_by_key(|x| x.timestamp);
+  only_right.sort_by_key(|x| x.timestamp);
+  CaptureDiff {
+    matched,
+    only_left,
+    only_right,
+    range: Some(range)
+  }
+}
+
+fn span(entries: &[&Entry]) -> Option<Range<u32>> {
+  let first = opt!(entries.iter().map(|x| x.timestamp).min());
+  let last = opt!(entries.iter().map(|x| x.timestamp).max());
+  Some(first..last.saturating_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use messages::{Message, MessageType};
+  use messages::parts::Part;
+
+  use std::collections::BTreeMap;
+
+  fn entry(timestamp: u32, text: &str) -> Entry {
+    Entry {
+      message_type: MessageType::Say,
+      timestamp,
+      sender: None,
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  #[test]
+  fn compares_the_overlap() {
+    let left = vec![entry(1, "a"), entry(2, "b"), entry(3, "c")];
+    let right = vec![entry(2, "b"), entry(3, "d"), entry(9, "e")];
+    let diff = diff(&left, &right, true);
+    assert_eq!(diff.range, Some(2..4));
+    assert_eq!(diff.matched, 1);
+    assert_eq!(diff.only_left.len(), 1);
+    assert_eq!(diff.only_right.len(), 1);
+    assert!(!diff.is_empty());
+  }
+
+  #[test]
+  fn captures_without_overlap_are_not_compared() {
+    let left = vec![entry(1, "a")];
+    let right = vec![entry(5, "a")];
+    let overlap = diff(&left, &right, true);
+    assert!(!overlap.compared());
+    assert!(!overlap.is_empty());
+    let all = diff(&left, &right, false);
+    assert!(all.compared());
+    assert_eq!(all.only_left.len() + all.only_right.len(), 2);
+  }
+}
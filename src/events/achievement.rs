@@ -0,0 +1,40 @@
+//! Achievements
+
+use events::{FromEntry, match_template};
+use messages::entries::Entry;
+use messages::parts::{Part, LinkPart};
+
+/// Someone earned an achievement.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AchievementEarned {
+  /// Who earned the achievement, or `None` if it was the player.
+  pub player: Option<String>,
+  /// The name of the achievement.
+  pub achievement: String,
+  /// The ID of the achievement, if the message linked to it.
+  pub id: Option<u32>
+}
+
+impl FromEntry for AchievementEarned {
+  fn from_entry(entry: &Entry) -> Option<AchievementEarned> {
+    let (player, m) = match match_template("achievement_earned", entry) {
+      Some(m) => (m.get("player").map(|x| x.to_owned()), m),
+      None => (None, opt!(match_template("achievement_earned_self", entry)))
+    };
+    Some(AchievementEarned {
+      player,
+      achievement: opt!(m.get("achievement")).to_owned(),
+      id: entry.message.parts.iter().filter_map(find_achievement_id).next()
+    })
+  }
+}
+
+fn find_achievement_id(part: &Part) -> Option<u32> {
+  match *part {
+    Part::Link { kind, id, .. } if kind == LinkPart::ACHIEVEMENT => Some(id),
+    Part::Multi(ref parts) => parts.iter().filter_map(|x| find_achievement_id(x)).next(),
+    Part::Colored { ref display, .. }
+      | Part::Formatted { ref display, .. } => find_achievement_id(display),
+    _ => None
+  }
+}
@@ -0,0 +1,287 @@
+//! Battle lines and combat encounters
+//!
+//! The chat log only has a rough account of combat. Actions are logged as `You use Fast Blade.`,
+//! and the damage they did follows on its own line as `The striking dummy takes 312 damage.`,
+//! without saying who did it. An [`EncounterTracker`](struct.EncounterTracker.html) puts the two
+//! together, crediting damage to whoever last used an action, and splits combat into encounters at
+//! duty boundaries and pauses. The totals are good for a rough parse, but they won't match ACT,
+//! which reads the network instead of the log.
+
+use events::{FromEntry, parse_number};
+use events::duty::DutyEvent;
+use localization;
+use messages::MessageType;
+use messages::entries::Entry;
+
+use std::collections::BTreeMap;
+
+/// The name used for the player, whose own actions are logged without a name, as in `You use Fast Blade.`
+pub const PLAYER: &'static str = "You";
+
+/// How long combat can pause, in seconds, before the encounter is considered over.
+pub const DEFAULT_IDLE_TIMEOUT: u32 = 20;
+
+/// The templates for the prefixes the game puts in front of damage from critical and direct hits.
+///
+/// Each is `(key, critical, direct hit)`, with the combined prefix first.
+static HIT_PREFIXES: &'static [(&'static str, bool, bool)] = &[
+  ("hit_critical_direct", true, true),
+  ("hit_critical", true, false),
+  ("hit_direct", false, true)
+];
+
+/// A single line of combat.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BattleEvent {
+  /// Someone used or cast an action.
+  Action {
+    /// Who used the action, or [`PLAYER`](constant.PLAYER.html) for the player.
+    actor: String,
+    action: String
+  },
+  /// Someone took damage. The log doesn't say from whom.
+  Damage {
+    /// Who took the damage, or [`PLAYER`](constant.PLAYER.html) for the player.
+    target: String,
+    amount: u64,
+    critical: bool,
+    direct_hit: bool
+  },
+  /// Someone was defeated.
+  Defeated {
+    /// Who was defeated, or [`PLAYER`](constant.PLAYER.html) for the player.
+    target: String,
+    /// Who defeated them, or [`PLAYER`](constant.PLAYER.html) for the player.
+    by: String
+  }
+}
+
+impl FromEntry for BattleEvent {
+  fn from_entry(entry: &Entry) -> Option<BattleEvent> {
+    if !is_battle(entry.message_type) {
+      return None;
+    }
+    let text = entry.message.plain_text();
+    // Damage lines start with an arrow, and names often start with a glyph for their party slot.
+    let text = text.trim().trim_start_matches(|c: char| c == '⇒' || c.is_whitespace() || (c >= '\u{e000}' && c <= '\u{f8ff}'));
+    let (text, critical, direct_hit) = HIT_PREFIXES.iter()
+      .filter_map(|&(key, critical, direct_hit)| {
+        let line = localization::match_key(key, text).and_then(|m| m.captures.get("line").cloned());
+        line.map(|x| (x, critical, direct_hit))
+      })
+      .next()
+      .unwrap_or_else(|| (text.to_owned(), false, false));
+    let text = text.as_str();
+    for key in &["battle_use_self", "battle_cast_self", "battle_use", "battle_cast"] {
+      if let Some(m) = localization::match_key(key, text) {
+        return Some(BattleEvent::Action {
+          actor: m.get("actor").unwrap_or(PLAYER).to_owned(),
+          action: opt!(m.get("action")).to_owned()
+        });
+      }
+    }
+    for key in &["battle_damage_self", "battle_damage"] {
+      if let Some(m) = localization::match_key(key, text) {
+        return Some(BattleEvent::Damage {
+          target: m.get("target").unwrap_or(PLAYER).to_owned(),
+          amount: opt!(m.get("amount").and_then(parse_number)),
+          critical,
+          direct_hit
+        });
+      }
+    }
+    for key in &["battle_defeated_self", "battle_defeat_self", "battle_defeated", "battle_defeat"] {
+      if let Some(m) = localization::match_key(key, text) {
+        // Whoever isn't named in the message is the player.
+        return Some(BattleEvent::Defeated {
+          target: m.get("target").unwrap_or(PLAYER).to_owned(),
+          by: m.get("actor").unwrap_or(PLAYER).to_owned()
+        });
+      }
+    }
+    None
+  }
+}
+
+/// Checks if a message type is one the game logs combat in.
+fn is_battle(message_type: MessageType) -> bool {
+  match message_type {
+    MessageType::Damage
+      | MessageType::FailedAttacks
+      | MessageType::Actions
+      | MessageType::BattleSystemMessages
+      | MessageType::BattleReceiveDamage
+      | MessageType::BattleCast
+      | MessageType::BattleDeath => true,
+    _ => false
+  }
+}
+
+/// What one actor did in an encounter.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActorStats {
+  /// The number of actions used or cast.
+  pub actions: u64,
+  pub damage_done: u64,
+  pub damage_taken: u64,
+  /// The number of hits that did damage.
+  pub hits: u64,
+  pub critical_hits: u64,
+  pub direct_hits: u64,
+  /// The number of times the actor was defeated.
+  pub deaths: u64
+}
+
+/// One stretch of combat and who did what in it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Encounter {
+  /// The duty the encounter was in, if it was in one.
+  pub duty: Option<String>,
+  /// When the first damage was done.
+  pub started: u32,
+  /// When the last line of combat was logged.
+  pub ended: u32,
+  /// The stats for everyone who acted or was hit, keyed by name.
+  pub actors: BTreeMap<String, ActorStats>
+}
+
+impl Encounter {
+  /// How long the encounter lasted, in seconds. This is never less than one.
+  pub fn duration(&self) -> u32 {
+    self.ended.saturating_sub(self.started).max(1)
+  }
+
+  /// The damage an actor did per second over the whole encounter.
+  pub fn dps(&self, actor: &str) -> f64 {
+    let done = self.actors.get(actor).map(|x| x.damage_done).unwrap_or(0);
+    done as f64 / self.duration() as f64
+  }
+
+  /// The total damage done by everyone.
+  pub fn total_damage(&self) -> u64 {
+    self.actors.values().map(|x| x.damage_done).sum()
+  }
+
+  /// The actors, most damage done first.
+  pub fn by_damage(&self) -> Vec<(&str, &ActorStats)> {
+    let mut actors: Vec<(&str, &ActorStats)> = self.actors.iter().map(|(k, v)| (k.as_str(), v)).collect();
+    actors.sort_by(|a, b| b.1.damage_done.cmp(&a.1.damage_done));
+    actors
+  }
+
+  fn actor(&mut self, name: &str) -> &mut ActorStats {
+    self.actors.entry(name.to_owned()).or_insert_with(ActorStats::default)
+  }
+}
+
+/// Builds [`Encounter`s](struct.Encounter.html) from a stream of entries.
+///
+/// An encounter starts with the first damage logged and ends when a duty begins or ends, or when no
+/// combat has been logged for `idle_timeout` seconds. Damage is credited to whoever last used an
+/// action, which is right for most lines but will misattribute damage over time and hits that land
+/// after someone else acts.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut tracker = EncounterTracker::new();
+/// let mut encounters: Vec<Encounter> = entries.iter().filter_map(|x| tracker.observe(x)).collect();
+/// encounters.extend(tracker.finish());
+/// ```
+#[derive(Debug)]
+pub struct EncounterTracker {
+  /// How long combat can pause, in seconds, before the encounter is over.
+  pub idle_timeout: u32,
+  current: Option<Encounter>,
+  last_actor: Option<String>,
+  last_combat: u32,
+  duty: Option<String>
+}
+
+impl Default for EncounterTracker {
+  fn default() -> Self {
+    EncounterTracker {
+      idle_timeout: DEFAULT_IDLE_TIMEOUT,
+      current: None,
+      last_actor: None,
+      last_combat: 0,
+      duty: None
+    }
+  }
+}
+
+impl EncounterTracker {
+  pub fn new() -> Self {
+    EncounterTracker::default()
+  }
+
+  /// Looks at the next entry, returning an encounter if one just ended.
+  pub fn observe(&mut self, entry: &Entry) -> Option<Encounter> {
+    match DutyEvent::from_entry(entry) {
+      Some(DutyEvent::Commenced { duty }) => {
+        let finished = self.finish();
+        self.duty = Some(duty);
+        return finished;
+      },
+      Some(DutyEvent::Ended { .. }) => {
+        let finished = self.finish();
+        self.duty = None;
+        return finished;
+      },
+      _ => {}
+    }
+    let event = BattleEvent::from_entry(entry);
+    let idle = entry.timestamp.saturating_sub(self.last_combat) > self.idle_timeout;
+    let finished = if idle { self.finish() } else { None };
+    let event = match event {
+      Some(e) => e,
+      None => return finished
+    };
+    self.last_combat = entry.timestamp;
+    match event {
+      BattleEvent::Action { actor, .. } => {
+        if let Some(ref mut current) = self.current {
+          current.actor(&actor).actions += 1;
+          current.ended = entry.timestamp;
+        }
+        self.last_actor = Some(actor);
+      },
+      BattleEvent::Damage { target, amount, critical, direct_hit } => {
+        let duty = self.duty.clone();
+        let current = self.current.get_or_insert_with(|| Encounter {
+          duty,
+          started: entry.timestamp,
+          ended: entry.timestamp,
+          actors: BTreeMap::new()
+        });
+        current.ended = entry.timestamp;
+        current.actor(&target).damage_taken += amount;
+        if let Some(ref source) = self.last_actor {
+          let stats = current.actor(source);
+          stats.damage_done += amount;
+          stats.hits += 1;
+          if critical {
+            stats.critical_hits += 1;
+          }
+          if direct_hit {
+            stats.direct_hits += 1;
+          }
+        }
+      },
+      BattleEvent::Defeated { target, .. } => {
+        if let Some(ref mut current) = self.current {
+          current.actor(&target).deaths += 1;
+          current.ended = entry.timestamp;
+        }
+      }
+    }
+    finished
+  }
+
+  /// Returns the encounter currently in progress, if any.
+  pub fn finish(&mut self) -> Option<Encounter> {
+    self.last_actor = None;
+    self.current.take()
+  }
+}
@@ -0,0 +1,258 @@
+//! One stream for every kind of event
+//!
+//! Each event module has its own output, some from single entries and some from trackers that
+//! watch several. The [`EventBus`](struct.EventBus.html) runs all of them, wraps what they find in a
+//! [`GameEvent`](enum.GameEvent.html), and hands each event to whoever subscribed to its kind.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let mut bus = EventBus::new();
+//! let duties = bus.subscribe(&[EventKind::Duty, EventKind::DutyRun]);
+//! pipeline.add_sink(bus);
+//! for event in duties {
+//!   println!("{}: {:?}", event.timestamp, event.event);
+//! }
+//! ```
+
+use events::{FromEntry, Timed};
+use events::achievement::AchievementEarned;
+use events::battle::{BattleEvent, Encounter, EncounterTracker};
+use events::duty::{DutyEvent, DutyRun, DutyTracker};
+use events::free_company::FreeCompanyEvent;
+use events::gold_saucer::GoldSaucerEvent;
+use events::movement::MovementEvent;
+use events::party::PartyEvent;
+use events::progression::{ProgressionEvent, ProgressionTracker};
+use events::retainer::{VentureResult, VentureTracker};
+use events::trade::{Trade, TradeTracker};
+use extract::{CustomEvent, ExtractorSet};
+use messages::entries::Entry;
+use pipeline::Sink;
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// Any event that can be derived from entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameEvent {
+  Venture(VentureResult),
+  FreeCompany(FreeCompanyEvent),
+  Achievement(AchievementEarned),
+  Progression(ProgressionEvent),
+  Movement(MovementEvent),
+  GoldSaucer(GoldSaucerEvent),
+  Party(PartyEvent),
+  Trade(Trade),
+  Duty(DutyEvent),
+  /// A whole duty, from commencement to completion, sent when it ends.
+  DutyRun(DutyRun),
+  Battle(BattleEvent),
+  /// A whole stretch of combat, sent when it ends.
+  Encounter(Encounter),
+  /// An event from a user-defined extractor.
+  Custom(CustomEvent)
+}
+
+impl GameEvent {
+  /// The kind of this event.
+  pub fn kind(&self) -> EventKind {
+    match *self {
+      GameEvent::Venture(_) => EventKind::Venture,
+      GameEvent::FreeCompany(_) => EventKind::FreeCompany,
+      GameEvent::Achievement(_) => EventKind::Achievement,
+      GameEvent::Progression(_) => EventKind::Progression,
+      GameEvent::Movement(_) => EventKind::Movement,
+      GameEvent::GoldSaucer(_) => EventKind::GoldSaucer,
+      GameEvent::Party(_) => EventKind::Party,
+      GameEvent::Trade(_) => EventKind::Trade,
+      GameEvent::Duty(_) => EventKind::Duty,
+      GameEvent::DutyRun(_) => EventKind::DutyRun,
+      GameEvent::Battle(_) => EventKind::Battle,
+      GameEvent::Encounter(_) => EventKind::Encounter,
+      GameEvent::Custom(_) => EventKind::Custom
+    }
+  }
+}
+
+/// The kinds of [`GameEvent`](enum.GameEvent.html), for subscribing to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+  Venture,
+  FreeCompany,
+  Achievement,
+  Progression,
+  Movement,
+  GoldSaucer,
+  Party,
+  Trade,
+  Duty,
+  DutyRun,
+  Battle,
+  Encounter,
+  Custom
+}
+
+/// Runs every event parser and tracker over entries.
+#[derive(Debug, Default)]
+pub struct EventParser {
+  /// User-defined extractors, whose events are [`Custom`](enum.GameEvent.html#variant.Custom).
+  pub extractors: ExtractorSet,
+  ventures: VentureTracker,
+  progression: ProgressionTracker,
+  trades: TradeTracker,
+  duties: DutyTracker,
+  encounters: EncounterTracker
+}
+
+impl EventParser {
+  pub fn new() -> Self {
+    EventParser::default()
+  }
+
+  /// Looks at the next entry, returning every event it completed.
+  pub fn observe(&mut self, entry: &Entry) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    if let Some(e) = self.ventures.observe(entry) {
+      events.push(GameEvent::Venture(e));
+    }
+    if let Some(e) = FreeCompanyEvent::from_entry(entry) {
+      events.push(GameEvent::FreeCompany(e));
+    }
+    if let Some(e) = AchievementEarned::from_entry(entry) {
+      events.push(GameEvent::Achievement(e));
+    }
+    if let Some(e) = self.progression.observe(entry) {
+      events.push(GameEvent::Progression(e));
+    }
+    if let Some(e) = MovementEvent::from_entry(entry) {
+      events.push(GameEvent::Movement(e));
+    }
+    if let Some(e) = GoldSaucerEvent::from_entry(entry) {
+      events.push(GameEvent::GoldSaucer(e));
+    }
+    if let Some(e) = PartyEvent::from_entry(entry) {
+      events.push(GameEvent::Party(e));
+    }
+    if let Some(e) = self.trades.observe(entry) {
+      events.push(GameEvent::Trade(e));
+    }
+    if let Some(e) = DutyEvent::from_entry(entry) {
+      events.push(GameEvent::Duty(e));
+    }
+    if let Some(e) = self.duties.observe(entry) {
+      events.push(GameEvent::DutyRun(e));
+    }
+    if let Some(e) = BattleEvent::from_entry(entry) {
+      events.push(GameEvent::Battle(e));
+    }
+    if let Some(e) = self.encounters.observe(entry) {
+      events.push(GameEvent::Encounter(e));
+    }
+    events.extend(self.extractors.extract(entry).into_iter().map(GameEvent::Custom));
+    events
+  }
+
+  /// Returns the events the trackers were still building, once there are no more entries.
+  pub fn finish(&mut self) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    if let Some(e) = self.ventures.finish() {
+      events.push(GameEvent::Venture(e));
+    }
+    if let Some(e) = self.trades.finish() {
+      events.push(GameEvent::Trade(e));
+    }
+    if let Some(e) = self.duties.finish() {
+      events.push(GameEvent::DutyRun(e));
+    }
+    if let Some(e) = self.encounters.finish() {
+      events.push(GameEvent::Encounter(e));
+    }
+    events
+  }
+}
+
+struct Subscriber {
+  kinds: Vec<EventKind>,
+  tx: Sender<Timed<GameEvent>>
+}
+
+/// Sends the events parsed from entries to subscribers.
+///
+/// The bus is a [`Sink`](../../pipeline/trait.Sink.html), so it can be added to a pipeline. When the
+/// pipeline flushes it, events that trackers were still building are sent too.
+#[derive(Default)]
+pub struct EventBus {
+  parser: EventParser,
+  subscribers: Vec<Subscriber>,
+  last_timestamp: u32
+}
+
+impl EventBus {
+  pub fn new() -> Self {
+    EventBus::default()
+  }
+
+  /// Create a bus that also sends events from user-defined extractors.
+  pub fn with_extractors(extractors: ExtractorSet) -> Self {
+    let mut bus = EventBus::new();
+    bus.parser.extractors = extractors;
+    bus
+  }
+
+  /// Subscribes to events of the given kinds, or every kind if `kinds` is empty.
+  ///
+  /// Subscribers that drop their receiver are removed the next time an event is sent.
+  pub fn subscribe(&mut self, kinds: &[EventKind]) -> Receiver<Timed<GameEvent>> {
+    let (tx, rx) = channel();
+    self.subscribers.push(Subscriber {
+      kinds: kinds.to_vec(),
+      tx
+    });
+    rx
+  }
+
+  /// Parses an entry and sends any events to subscribers, returning how many events there were.
+  pub fn publish(&mut self, entry: &Entry) -> usize {
+    self.last_timestamp = entry.timestamp;
+    let events = self.parser.observe(entry);
+    let count = events.len();
+    self.send(entry.timestamp, events);
+    count
+  }
+
+  /// Sends the events trackers were still building, once there are no more entries.
+  pub fn finish(&mut self) -> usize {
+    let events = self.parser.finish();
+    let count = events.len();
+    let timestamp = self.last_timestamp;
+    self.send(timestamp, events);
+    count
+  }
+
+  fn send(&mut self, timestamp: u32, events: Vec<GameEvent>) {
+    for event in events {
+      let kind = event.kind();
+      self.subscribers.retain(|s| {
+        if !s.kinds.is_empty() && !s.kinds.contains(&kind) {
+          return true;
+        }
+        s.tx.send(Timed {
+          timestamp,
+          event: event.clone()
+        }).is_ok()
+      });
+    }
+  }
+}
+
+impl Sink for EventBus {
+  fn handle(&mut self, entry: &Entry) {
+    self.publish(entry);
+  }
+
+  fn flush(&mut self) {
+    self.finish();
+  }
+}
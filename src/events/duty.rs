@@ -0,0 +1,199 @@
+//! Duties, duty roulette bonuses, and completion rewards
+
+use events::{FromEntry, match_template, parse_number};
+use messages::entries::Entry;
+
+use chrono::{TimeZone, Utc};
+
+use std::collections::BTreeMap;
+
+/// A reward, either from finishing a duty or from a roulette bonus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reward {
+  Experience(u64),
+  Gil(u64),
+  Tomestones {
+    /// The kind of tomestone, such as `poetics`.
+    kind: String,
+    amount: u64
+  }
+}
+
+/// Something that happened in a duty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DutyEvent {
+  /// A duty began.
+  Commenced {
+    duty: String
+  },
+  /// A duty ended.
+  Ended {
+    duty: String
+  },
+  /// The player received a duty roulette bonus.
+  RouletteBonus(Reward),
+  /// The player received a reward.
+  Reward(Reward)
+}
+
+impl FromEntry for DutyEvent {
+  fn from_entry(entry: &Entry) -> Option<DutyEvent> {
+    // GATEs are announced the same way duties are in English.
+    if match_template("gate_started", entry).is_some() {
+      return None;
+    }
+    if let Some(m) = match_template("duty_commenced", entry) {
+      return Some(DutyEvent::Commenced {
+        duty: opt!(m.get("duty")).to_owned()
+      });
+    }
+    if let Some(m) = match_template("duty_ended", entry) {
+      return Some(DutyEvent::Ended {
+        duty: opt!(m.get("duty")).to_owned()
+      });
+    }
+    if let Some(m) = match_template("roulette_bonus_tomestones", entry) {
+      return Some(DutyEvent::RouletteBonus(Reward::Tomestones {
+        kind: opt!(m.get("kind")).to_owned(),
+        amount: opt!(m.get("amount").and_then(parse_number))
+      }));
+    }
+    if let Some(m) = match_template("roulette_bonus_exp", entry) {
+      return Some(DutyEvent::RouletteBonus(Reward::Experience(opt!(m.get("amount").and_then(parse_number)))));
+    }
+    if let Some(m) = match_template("roulette_bonus_gil", entry) {
+      return Some(DutyEvent::RouletteBonus(Reward::Gil(opt!(m.get("amount").and_then(parse_number)))));
+    }
+    if let Some(m) = match_template("obtain_tomestones", entry) {
+      return Some(DutyEvent::Reward(Reward::Tomestones {
+        kind: opt!(m.get("kind")).to_owned(),
+        amount: opt!(m.get("amount").and_then(parse_number))
+      }));
+    }
+    if let Some(m) = match_template("gain_experience", entry) {
+      return Some(DutyEvent::Reward(Reward::Experience(opt!(m.get("amount").and_then(parse_number)))));
+    }
+    if let Some(m) = match_template("obtain_gil", entry) {
+      return Some(DutyEvent::Reward(Reward::Gil(opt!(m.get("amount").and_then(parse_number)))));
+    }
+    None
+  }
+}
+
+/// One run through a duty and what it paid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DutyRun {
+  /// The name of the duty.
+  pub duty: String,
+  /// When the duty began.
+  pub started: u32,
+  /// When the duty ended, or `None` if the log stopped first.
+  pub ended: Option<u32>,
+  /// Whether a roulette bonus was received, meaning the duty was run from a roulette.
+  pub roulette: bool,
+  /// Every reward received during the duty, including roulette bonuses.
+  pub rewards: Vec<Reward>
+}
+
+/// Builds [`DutyRun`s](struct.DutyRun.html) from a stream of entries.
+///
+/// Rewards are only counted between a duty beginning and ending, so experience and gil from outside
+/// of duties aren't mixed in.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut tracker = DutyTracker::new();
+/// let mut income = RouletteIncome::default();
+/// for run in entries.iter().filter_map(|x| tracker.observe(x)).filter(|x| x.roulette) {
+///   income.add(&run);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct DutyTracker {
+  current: Option<DutyRun>
+}
+
+impl DutyTracker {
+  pub fn new() -> Self {
+    DutyTracker::default()
+  }
+
+  /// Looks at the next entry, returning a duty run if one just ended.
+  pub fn observe(&mut self, entry: &Entry) -> Option<DutyRun> {
+    match opt!(DutyEvent::from_entry(entry)) {
+      DutyEvent::Commenced { duty } => {
+        let unfinished = self.finish();
+        self.current = Some(DutyRun {
+          duty,
+          started: entry.timestamp,
+          ended: None,
+          roulette: false,
+          rewards: Vec::new()
+        });
+        unfinished
+      },
+      DutyEvent::Ended { .. } => {
+        let mut run = opt!(self.finish());
+        run.ended = Some(entry.timestamp);
+        Some(run)
+      },
+      DutyEvent::RouletteBonus(reward) => {
+        let current = opt!(self.current.as_mut());
+        current.roulette = true;
+        current.rewards.push(reward);
+        None
+      },
+      DutyEvent::Reward(reward) => {
+        opt!(self.current.as_mut()).rewards.push(reward);
+        None
+      }
+    }
+  }
+
+  /// Returns the duty run currently in progress, if any.
+  pub fn finish(&mut self) -> Option<DutyRun> {
+    self.current.take()
+  }
+}
+
+/// Totals of what duty runs paid.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouletteIncome {
+  /// The number of runs added.
+  pub runs: usize,
+  pub experience: u64,
+  pub gil: u64,
+  /// Tomestones by kind.
+  pub tomestones: BTreeMap<String, u64>
+}
+
+impl RouletteIncome {
+  /// Adds the rewards from a run.
+  pub fn add(&mut self, run: &DutyRun) {
+    self.runs += 1;
+    for reward in &run.rewards {
+      match *reward {
+        Reward::Experience(n) => self.experience += n,
+        Reward::Gil(n) => self.gil += n,
+        Reward::Tomestones { ref kind, amount } => *self.tomestones.entry(kind.clone()).or_insert(0) += amount
+      }
+    }
+  }
+
+  /// Totals runs by the day they started on, in UTC, keyed like `2017-06-27`.
+  ///
+  /// Only runs from a roulette are counted.
+  pub fn by_day<'a, I>(runs: I) -> BTreeMap<String, RouletteIncome>
+    where I: IntoIterator<Item = &'a DutyRun>
+  {
+    let mut days: BTreeMap<String, RouletteIncome> = BTreeMap::new();
+    for run in runs.into_iter().filter(|x| x.roulette) {
+      let day = Utc.timestamp(run.started as i64, 0).format("%Y-%m-%d").to_string();
+      days.entry(day).or_insert_with(RouletteIncome::default).add(run);
+    }
+    days
+  }
+}
@@ -0,0 +1,63 @@
+//! Free Company credits, ranks, and members
+
+use events::{FromEntry, match_template, parse_number};
+use messages::entries::Entry;
+
+/// Something that happened in a Free Company.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreeCompanyEvent {
+  /// The player earned company credits.
+  CreditsGained {
+    amount: u64
+  },
+  /// The Free Company reached a new rank.
+  RankUp {
+    rank: u32
+  },
+  /// A member was given a new rank within the Free Company.
+  MemberRankChanged {
+    member: String,
+    rank: String
+  },
+  /// Someone joined the Free Company.
+  MemberJoined {
+    name: String
+  },
+  /// Someone left the Free Company.
+  MemberLeft {
+    name: String
+  }
+}
+
+impl FromEntry for FreeCompanyEvent {
+  fn from_entry(entry: &Entry) -> Option<FreeCompanyEvent> {
+    if let Some(m) = match_template("fc_credits", entry) {
+      return Some(FreeCompanyEvent::CreditsGained {
+        amount: opt!(m.get("amount").and_then(parse_number))
+      });
+    }
+    if let Some(m) = match_template("fc_rank_up", entry) {
+      return Some(FreeCompanyEvent::RankUp {
+        rank: opt!(m.get("rank").and_then(parse_number)) as u32
+      });
+    }
+    if let Some(m) = match_template("fc_member_rank", entry) {
+      return Some(FreeCompanyEvent::MemberRankChanged {
+        member: opt!(m.get("member")).to_owned(),
+        rank: opt!(m.get("rank")).to_owned()
+      });
+    }
+    if let Some(m) = match_template("fc_member_joined", entry) {
+      return Some(FreeCompanyEvent::MemberJoined {
+        name: opt!(m.get("name")).to_owned()
+      });
+    }
+    if let Some(m) = match_template("fc_member_left", entry) {
+      return Some(FreeCompanyEvent::MemberLeft {
+        name: opt!(m.get("name")).to_owned()
+      });
+    }
+    None
+  }
+}
@@ -0,0 +1,97 @@
+//! MGP, the Jumbo Cactpot, and GATEs at the Gold Saucer
+
+use events::{FromEntry, match_template, parse_number};
+use messages::entries::Entry;
+
+/// Something that happened at the Gold Saucer.
+///
+/// Cactpot numbers are kept as text, since their leading zeros matter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoldSaucerEvent {
+  /// The player gained MGP.
+  MgpGained {
+    amount: u64
+  },
+  /// The player bought a Jumbo Cactpot ticket.
+  JumboCactpotPurchased {
+    number: String
+  },
+  /// The winning Jumbo Cactpot number was announced.
+  JumboCactpotWinningNumber {
+    number: String
+  },
+  /// One of the player's Jumbo Cactpot tickets won.
+  JumboCactpotWon {
+    number: String,
+    amount: u64
+  },
+  /// A GATE is about to begin.
+  GateAnnounced {
+    gate: String,
+    minutes: u32
+  },
+  /// A GATE began.
+  GateStarted {
+    gate: String
+  }
+}
+
+impl GoldSaucerEvent {
+  /// The MGP the player gained from this event, if any.
+  pub fn mgp(&self) -> u64 {
+    match *self {
+      GoldSaucerEvent::MgpGained { amount } => amount,
+      _ => 0
+    }
+  }
+}
+
+impl FromEntry for GoldSaucerEvent {
+  fn from_entry(entry: &Entry) -> Option<GoldSaucerEvent> {
+    if let Some(m) = match_template("obtain_mgp", entry) {
+      return Some(GoldSaucerEvent::MgpGained {
+        amount: opt!(m.get("amount").and_then(parse_number))
+      });
+    }
+    // Checked before the purchase, since the win message also carries a ticket number.
+    if let Some(m) = match_template("jumbo_cactpot_won", entry) {
+      return Some(GoldSaucerEvent::JumboCactpotWon {
+        number: opt!(m.get("number").and_then(cactpot_number)),
+        amount: opt!(m.get("amount").and_then(parse_number))
+      });
+    }
+    if let Some(m) = match_template("jumbo_cactpot_purchased", entry) {
+      return Some(GoldSaucerEvent::JumboCactpotPurchased {
+        number: opt!(m.get("number").and_then(cactpot_number))
+      });
+    }
+    if let Some(m) = match_template("jumbo_cactpot_winning_number", entry) {
+      return Some(GoldSaucerEvent::JumboCactpotWinningNumber {
+        number: opt!(m.get("number").and_then(cactpot_number))
+      });
+    }
+    if let Some(m) = match_template("gate_announced", entry) {
+      return Some(GoldSaucerEvent::GateAnnounced {
+        gate: opt!(m.get("gate")).to_owned(),
+        minutes: opt!(m.get("minutes").and_then(parse_number)) as u32
+      });
+    }
+    if let Some(m) = match_template("gate_started", entry) {
+      return Some(GoldSaucerEvent::GateStarted {
+        gate: opt!(m.get("gate")).to_owned()
+      });
+    }
+    None
+  }
+}
+
+/// Jumbo Cactpot numbers are always four digits.
+fn cactpot_number(text: &str) -> Option<String> {
+  let text = text.trim();
+  if text.len() == 4 && text.chars().all(|c| c.is_ascii_digit()) {
+    Some(text.to_owned())
+  } else {
+    None
+  }
+}
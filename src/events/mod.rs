@@ -0,0 +1,209 @@
+//! Structured events parsed from entries
+//!
+//! Each module here turns one kind of system message into a typed event. The messages are matched
+//! with the templates in [`localization`](../localization/index.html), so they are recognized in
+//! English and in any other language the localizer has patterns for.
+
+pub mod retainer;
+pub mod free_company;
+pub mod achievement;
+pub mod progression;
+pub mod movement;
+pub mod gold_saucer;
+pub mod party;
+pub mod trade;
+pub mod duty;
+pub mod battle;
+pub mod bus;
+
+use localization::{self, TemplateMatch};
+use messages::MessageType;
+use messages::entries::Entry;
+use pipeline::Sink;
+
+use std::marker::PhantomData;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// An event that can be parsed from a single entry.
+pub trait FromEntry: Sized {
+  /// Parses the event from the entry, if the entry is one.
+  fn from_entry(entry: &Entry) -> Option<Self>;
+}
+
+/// An event along with when it happened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timed<E> {
+  /// The timestamp of the entry the event came from.
+  pub timestamp: u32,
+  /// The event.
+  pub event: E
+}
+
+/// Turns entries into a stream of one kind of event, skipping entries that aren't one.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// for party in events::<PartyEvent, _>(reader.iter()) {
+///   println!("{}: {:?}", party.timestamp, party.event);
+/// }
+/// ```
+pub fn events<E, I>(entries: I) -> Events<E, I::IntoIter>
+  where E: FromEntry,
+        I: IntoIterator<Item = Entry>
+{
+  Events {
+    entries: entries.into_iter(),
+    _event: PhantomData
+  }
+}
+
+/// The iterator returned by [`events`](fn.events.html).
+pub struct Events<E, I> {
+  entries: I,
+  _event: PhantomData<E>
+}
+
+impl<E, I> Iterator for Events<E, I>
+  where E: FromEntry,
+        I: Iterator<Item = Entry>
+{
+  type Item = Timed<E>;
+
+  fn next(&mut self) -> Option<Timed<E>> {
+    loop {
+      let entry = opt!(self.entries.next());
+      if let Some(event) = E::from_entry(&entry) {
+        return Some(Timed {
+          timestamp: entry.timestamp,
+          event
+        });
+      }
+    }
+  }
+}
+
+/// A [`Sink`](../pipeline/trait.Sink.html) that sends one kind of event down a channel.
+///
+/// This gives a live stream of events from a pipeline, separate from the entries going to other
+/// sinks.
+pub struct EventSink<E> {
+  tx: Sender<Timed<E>>
+}
+
+impl<E> EventSink<E>
+  where E: FromEntry + Send
+{
+  /// Create a sink and the receiving end of its channel.
+  pub fn channel() -> (EventSink<E>, Receiver<Timed<E>>) {
+    let (tx, rx) = channel();
+    (EventSink { tx }, rx)
+  }
+}
+
+impl<E> Sink for EventSink<E>
+  where E: FromEntry + Send
+{
+  fn handle(&mut self, entry: &Entry) {
+    if let Some(event) = E::from_entry(entry) {
+      let _ = self.tx.send(Timed {
+        timestamp: entry.timestamp,
+        event
+      });
+    }
+  }
+}
+
+/// An item and how many of it there were.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemQuantity {
+  /// The name of the item.
+  pub name: String,
+  /// How many of the item there were.
+  pub quantity: u32,
+  /// Whether the item was high quality.
+  pub high_quality: bool
+}
+
+impl ItemQuantity {
+  /// Parses an item as it appears in a message, such as `3 copper ores`, `a potion`, or `an ether`.
+  pub fn parse(text: &str) -> ItemQuantity {
+    // The game marks high quality items with a glyph after the name.
+    let high_quality = text.contains(HQ_GLYPH);
+    let text = text.replace(HQ_GLYPH, "");
+    let text = text.trim();
+    let (quantity, name) = match text.find(' ') {
+      Some(i) => {
+        let (start, rest) = text.split_at(i);
+        match start {
+          "a" | "an" | "A" | "An" => (Some(1), rest),
+          _ => match parse_number(start) {
+            Some(n) => (Some(n as u32), rest),
+            None => (None, text)
+          }
+        }
+      },
+      None => (None, text)
+    };
+    ItemQuantity {
+      name: name.trim().to_owned(),
+      quantity: quantity.unwrap_or(1),
+      high_quality
+    }
+  }
+}
+
+/// The glyph the game puts after the names of high quality items.
+pub const HQ_GLYPH: char = '\u{e03c}';
+
+/// Parses a number as the game writes it, ignoring thousands separators.
+///
+/// This handles `1,234` (English), `1.234` (German), and `1 234` (French).
+pub fn parse_number(text: &str) -> Option<u64> {
+  let digits: String = text.chars()
+    .filter(|c| !(*c == ',' || *c == '.' || c.is_whitespace() || *c == '\u{202f}'))
+    .collect();
+  if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  digits.parse().ok()
+}
+
+/// Matches the plain text of an entry's message against one of the built-in templates.
+///
+/// Only entries in channels the game writes are matched, so a player can't fake an event by typing
+/// its text in chat.
+pub fn match_template(key: &str, entry: &Entry) -> Option<TemplateMatch> {
+  if !is_game_message(entry.message_type) {
+    return None;
+  }
+  localization::match_key(key, entry.message.plain_text().trim())
+}
+
+/// Checks if a message type is one only the game writes in, as opposed to one players can type in.
+fn is_game_message(message_type: MessageType) -> bool {
+  match message_type {
+    MessageType::UrgentInformation
+      | MessageType::GeneralInformation
+      | MessageType::SystemMessages
+      | MessageType::SystemErrorMessages
+      | MessageType::BattleSystemMessages
+      | MessageType::GatheringSystemMessages
+      | MessageType::LootNotices
+      | MessageType::CharacterProgress
+      | MessageType::LootMessages
+      | MessageType::CraftingMessages
+      | MessageType::GatheringMessages
+      | MessageType::NpcAnnouncements
+      | MessageType::FcAnnouncements
+      | MessageType::FcLoginMessages
+      | MessageType::RetainerSaleReports
+      | MessageType::PartySearchInfo
+      | MessageType::SignSettings
+      | MessageType::DiceRolls
+      | MessageType::NoviceNetworkNotifications
+      | MessageType::TrialUpdate
+      | MessageType::GainMgp => true,
+    _ => false
+  }
+}
@@ -0,0 +1,184 @@
+//! Zone changes and teleports
+
+use events::{FromEntry, match_template};
+use messages::entries::Entry;
+
+/// The player moved somewhere.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementEvent {
+  /// The player teleported. The destination is usually the name of an aetheryte.
+  Teleported {
+    destination: String
+  },
+  /// The player entered a new zone.
+  ZoneChanged {
+    zone: String
+  },
+  /// The player entered a sanctuary, like a city or inn.
+  EnteredSanctuary,
+  /// The player left a sanctuary.
+  LeftSanctuary
+}
+
+impl MovementEvent {
+  /// The place the player is now at, if the event says.
+  pub fn location(&self) -> Option<&str> {
+    match *self {
+      MovementEvent::Teleported { ref destination } => Some(destination),
+      MovementEvent::ZoneChanged { ref zone } => Some(zone),
+      _ => None
+    }
+  }
+}
+
+impl FromEntry for MovementEvent {
+  fn from_entry(entry: &Entry) -> Option<MovementEvent> {
+    if let Some(m) = match_template("teleport", entry) {
+      return Some(MovementEvent::Teleported {
+        destination: opt!(m.get("destination")).to_owned()
+      });
+    }
+    if let Some(m) = match_template("zone_entered", entry) {
+      return Some(MovementEvent::ZoneChanged {
+        zone: opt!(m.get("zone")).to_owned()
+      });
+    }
+    if match_template("sanctuary_entered", entry).is_some() {
+      return Some(MovementEvent::EnteredSanctuary);
+    }
+    if match_template("sanctuary_left", entry).is_some() {
+      return Some(MovementEvent::LeftSanctuary);
+    }
+    None
+  }
+}
+
+/// Where the player was over time.
+///
+/// # Examples
+/// This prints every entry along with where the player was when it was logged.
+///
+/// ```rust,no_run
+/// let timeline = MovementTimeline::from_entries(&entries);
+/// for entry in &entries {
+///   println!("{:?} {:?}", timeline.location_at(entry.timestamp), entry);
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct MovementTimeline {
+  locations: Vec<(u32, String)>
+}
+
+impl MovementTimeline {
+  pub fn new() -> Self {
+    MovementTimeline::default()
+  }
+
+  /// Builds a timeline from entries.
+  pub fn from_entries<'a, I>(entries: I) -> Self
+    where I: IntoIterator<Item = &'a Entry>
+  {
+    let mut timeline = MovementTimeline::new();
+    for entry in entries {
+      timeline.observe(entry);
+    }
+    timeline
+  }
+
+  /// Looks at the next entry, returning a movement event if it was one.
+  pub fn observe(&mut self, entry: &Entry) -> Option<MovementEvent> {
+    let event = opt!(MovementEvent::from_entry(entry));
+    if let Some(location) = event.location() {
+      // Keep the timeline sorted, even if entries are a little out of order. Locations logged in the
+      // same second stay in the order they were seen.
+      let i = self.first_after(entry.timestamp);
+      self.locations.insert(i, (entry.timestamp, location.to_owned()));
+    }
+    Some(event)
+  }
+
+  /// Every location in order, with the time the player arrived there.
+  pub fn locations(&self) -> &[(u32, String)] {
+    &self.locations
+  }
+
+  /// Where the player was at `timestamp`, if known.
+  ///
+  /// If the player moved more than once in that second, this is the last place they went.
+  pub fn location_at(&self, timestamp: u32) -> Option<&str> {
+    match self.first_after(timestamp) {
+      0 => None,
+      i => Some(&self.locations[i - 1].1)
+    }
+  }
+
+  /// The index of the first location arrived at after `timestamp`.
+  fn first_after(&self, timestamp: u32) -> usize {
+    let (mut low, mut high) = (0, self.locations.len());
+    while low < high {
+      let mid = low + (high - low) / 2;
+      if self.locations[mid].0 <= timestamp {
+        low = mid + 1;
+      } else {
+        high = mid;
+      }
+    }
+    low
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use messages::{Message, MessageType};
+  use messages::parts::Part;
+
+  use std::collections::BTreeMap;
+
+  fn entry(timestamp: u32, text: &str) -> Entry {
+    Entry {
+      message_type: MessageType::SystemMessages,
+      timestamp,
+      sender: None,
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  #[test]
+  fn latest_location_in_a_second_wins() {
+    let entries = vec![
+      entry(10, "Now entering Limsa Lominsa."),
+      entry(20, "You teleport to Aleport."),
+      entry(20, "Now entering Western La Noscea."),
+      entry(20, "Now entering Eastern La Noscea."),
+      entry(30, "Now entering Wineport.")
+    ];
+    let timeline = MovementTimeline::from_entries(&entries);
+    assert_eq!(timeline.locations().len(), 5);
+    assert_eq!(timeline.location_at(9), None);
+    assert_eq!(timeline.location_at(10), Some("Limsa Lominsa"));
+    assert_eq!(timeline.location_at(19), Some("Limsa Lominsa"));
+    assert_eq!(timeline.location_at(20), Some("Eastern La Noscea"));
+    assert_eq!(timeline.location_at(29), Some("Eastern La Noscea"));
+    assert_eq!(timeline.location_at(30), Some("Wineport"));
+  }
+
+  #[test]
+  fn out_of_order_entries_stay_sorted() {
+    let entries = vec![
+      entry(20, "Now entering Western La Noscea."),
+      entry(20, "Now entering Eastern La Noscea."),
+      entry(10, "Now entering Limsa Lominsa."),
+      entry(20, "Now entering Wineport.")
+    ];
+    let timeline = MovementTimeline::from_entries(&entries);
+    let times: Vec<u32> = timeline.locations().iter().map(|x| x.0).collect();
+    assert_eq!(times, vec![10, 20, 20, 20]);
+    assert_eq!(timeline.location_at(15), Some("Limsa Lominsa"));
+    assert_eq!(timeline.location_at(20), Some("Wineport"));
+  }
+}
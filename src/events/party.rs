@@ -0,0 +1,97 @@
+//! Party invites, joins, and departures
+//!
+//! Bots that only care about the party can use [`events`](../fn.events.html) or an
+//! [`EventSink`](../struct.EventSink.html) to get a stream of `PartyEvent`s instead of entries.
+
+use events::{FromEntry, match_template};
+use messages::entries::Entry;
+
+/// Something that happened to the party.
+///
+/// Names are `None` when it was the player.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartyEvent {
+  /// Someone was invited to a party.
+  Invited {
+    /// Who was invited.
+    player: Option<String>,
+    /// Who sent the invite.
+    by: Option<String>
+  },
+  /// Someone joined the party.
+  Joined {
+    player: Option<String>,
+    /// The leader of the party, if the message said.
+    leader: Option<String>
+  },
+  /// Someone left the party.
+  Left {
+    player: Option<String>
+  },
+  /// The party was disbanded.
+  Disbanded,
+  /// A party member went offline.
+  Offline {
+    player: String
+  }
+}
+
+impl PartyEvent {
+  /// The other player involved in this event, if any.
+  pub fn player(&self) -> Option<&str> {
+    match *self {
+      PartyEvent::Invited { ref player, ref by } => player.as_ref().or(by.as_ref()).map(|x| x.as_str()),
+      PartyEvent::Joined { ref player, .. }
+        | PartyEvent::Left { ref player } => player.as_ref().map(|x| x.as_str()),
+      PartyEvent::Offline { ref player } => Some(player),
+      PartyEvent::Disbanded => None
+    }
+  }
+}
+
+impl FromEntry for PartyEvent {
+  fn from_entry(entry: &Entry) -> Option<PartyEvent> {
+    if let Some(m) = match_template("party_invite_sent", entry) {
+      return Some(PartyEvent::Invited {
+        player: Some(opt!(m.get("player")).to_owned()),
+        by: None
+      });
+    }
+    if let Some(m) = match_template("party_invite_received", entry) {
+      return Some(PartyEvent::Invited {
+        player: None,
+        by: Some(opt!(m.get("player")).to_owned())
+      });
+    }
+    if let Some(m) = match_template("party_joined_self", entry) {
+      return Some(PartyEvent::Joined {
+        player: None,
+        leader: Some(opt!(m.get("leader")).to_owned())
+      });
+    }
+    if let Some(m) = match_template("party_joined", entry) {
+      return Some(PartyEvent::Joined {
+        player: Some(opt!(m.get("player")).to_owned()),
+        leader: None
+      });
+    }
+    if match_template("party_left_self", entry).is_some() {
+      return Some(PartyEvent::Left { player: None });
+    }
+    if let Some(m) = match_template("party_left", entry) {
+      return Some(PartyEvent::Left {
+        player: Some(opt!(m.get("player")).to_owned())
+      });
+    }
+    if match_template("party_disbanded", entry).is_some() {
+      return Some(PartyEvent::Disbanded);
+    }
+    if let Some(m) = match_template("party_member_offline", entry) {
+      return Some(PartyEvent::Offline {
+        player: opt!(m.get("player")).to_owned()
+      });
+    }
+    None
+  }
+}
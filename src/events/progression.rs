@@ -0,0 +1,69 @@
+//! Levels and jobs
+
+use events::{match_template, parse_number};
+use messages::entries::Entry;
+
+/// A change in someone's progression.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressionEvent {
+  /// Someone reached a new level.
+  LevelUp {
+    /// Who levelled up, or `None` if it was the player.
+    player: Option<String>,
+    /// The player's job at the time, if a job change has been seen. Always `None` for other players.
+    job: Option<String>,
+    /// The new level.
+    level: u32
+  },
+  /// The player changed class or job.
+  JobChange {
+    job: String
+  }
+}
+
+/// Builds [`ProgressionEvent`s](enum.ProgressionEvent.html) from a stream of entries.
+///
+/// Level-up messages don't say which job levelled up, so this remembers the last job the player
+/// changed to. Entries should be given in order.
+#[derive(Debug, Default)]
+pub struct ProgressionTracker {
+  job: Option<String>
+}
+
+impl ProgressionTracker {
+  pub fn new() -> Self {
+    ProgressionTracker::default()
+  }
+
+  /// The player's current job, if a job change has been seen.
+  pub fn job(&self) -> Option<&str> {
+    self.job.as_ref().map(|x| x.as_str())
+  }
+
+  /// Looks at the next entry, returning an event if it was a level-up or job change.
+  pub fn observe(&mut self, entry: &Entry) -> Option<ProgressionEvent> {
+    if let Some(m) = match_template("job_change", entry) {
+      let job = opt!(m.get("job")).to_owned();
+      self.job = Some(job.clone());
+      return Some(ProgressionEvent::JobChange {
+        job
+      });
+    }
+    if let Some(m) = match_template("level_up_self", entry) {
+      return Some(ProgressionEvent::LevelUp {
+        player: None,
+        job: self.job.clone(),
+        level: opt!(m.get("level").and_then(parse_number)) as u32
+      });
+    }
+    if let Some(m) = match_template("level_up_other", entry) {
+      return Some(ProgressionEvent::LevelUp {
+        player: Some(opt!(m.get("player")).to_owned()),
+        job: None,
+        level: opt!(m.get("level").and_then(parse_number)) as u32
+      });
+    }
+    None
+  }
+}
@@ -0,0 +1,101 @@
+//! Retainer ventures
+
+use events::{ItemQuantity, match_template};
+use messages::MessageType;
+use messages::entries::Entry;
+
+/// How long after the last line of a venture's results, in seconds, more items can still follow.
+pub const ITEM_WINDOW: u32 = 5;
+
+/// The result of a retainer venture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VentureResult {
+  /// When the venture's results were collected.
+  pub timestamp: u32,
+  /// The name of the retainer.
+  pub retainer: String,
+  /// The venture that was completed, if the log shows which one it was.
+  pub venture: Option<String>,
+  /// The items the retainer brought back.
+  pub items: Vec<ItemQuantity>
+}
+
+/// Builds [`VentureResult`s](struct.VentureResult.html) from a stream of entries.
+///
+/// The game logs a venture's completion on one line and each item returned on the lines that follow,
+/// so this has to see the entries in order. A result is returned when another venture is assigned or
+/// completed, when another line is logged in the channel the completion was in, when no item has been
+/// logged for [`ITEM_WINDOW`](constant.ITEM_WINDOW.html) seconds, or when `finish` is called. Lines in
+/// other channels, like chat, don't end a result.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut tracker = VentureTracker::new();
+/// let mut results: Vec<VentureResult> = entries.iter().filter_map(|x| tracker.observe(x)).collect();
+/// results.extend(tracker.finish());
+/// ```
+#[derive(Debug, Default)]
+pub struct VentureTracker {
+  current: Option<VentureResult>,
+  /// The channel the current result's completion was logged in.
+  channel: Option<MessageType>,
+  /// When the current result last had a line logged.
+  last_line: u32,
+  /// The venture each retainer was last sent on.
+  assigned: Vec<(String, String)>
+}
+
+impl VentureTracker {
+  pub fn new() -> Self {
+    VentureTracker::default()
+  }
+
+  /// Looks at the next entry, returning a venture result if one was just completed.
+  pub fn observe(&mut self, entry: &Entry) -> Option<VentureResult> {
+    if let Some(m) = match_template("venture_assigned", entry) {
+      let retainer = opt_or!(m.get("retainer"), "").to_owned();
+      let venture = opt_or!(m.get("venture"), "").to_owned();
+      self.assigned.retain(|x| x.0 != retainer);
+      self.assigned.push((retainer, venture));
+      return self.finish();
+    }
+    if let Some(m) = match_template("venture_complete", entry) {
+      let retainer = opt_or!(m.get("retainer"), "").to_owned();
+      let venture = self.assigned.iter().find(|x| x.0 == retainer).map(|x| x.1.clone());
+      let finished = self.finish();
+      self.channel = Some(entry.message_type);
+      self.last_line = entry.timestamp;
+      self.current = Some(VentureResult {
+        timestamp: entry.timestamp,
+        retainer,
+        venture,
+        items: Vec::new()
+      });
+      return finished;
+    }
+    if self.current.is_none() {
+      return None;
+    }
+    if entry.timestamp.saturating_sub(self.last_line) > ITEM_WINDOW {
+      return self.finish();
+    }
+    if let Some(m) = match_template("obtain_item", entry) {
+      if let Some(ref mut current) = self.current {
+        current.items.push(ItemQuantity::parse(opt_or!(m.get("item"), "")));
+      }
+      self.last_line = entry.timestamp;
+      return None;
+    }
+    if self.channel == Some(entry.message_type) {
+      return self.finish();
+    }
+    None
+  }
+
+  /// Returns the venture result currently being built, if any.
+  pub fn finish(&mut self) -> Option<VentureResult> {
+    self.channel = None;
+    self.current.take()
+  }
+}
@@ -0,0 +1,116 @@
+//! Trades with other players
+
+use events::{ItemQuantity, match_template, parse_number};
+use messages::entries::Entry;
+
+/// A completed trade.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+  /// When the trade was completed.
+  pub timestamp: u32,
+  /// Who the trade was with, if the request was seen.
+  pub counterpart: Option<String>,
+  /// The items the player handed over.
+  pub items_given: Vec<ItemQuantity>,
+  /// The gil the player handed over.
+  pub gil_given: u64,
+  /// The items the player received.
+  pub items_received: Vec<ItemQuantity>,
+  /// The gil the player received.
+  pub gil_received: u64
+}
+
+impl Trade {
+  /// The gil the player came out ahead (or behind, if negative) from this trade.
+  pub fn net_gil(&self) -> i64 {
+    self.gil_received as i64 - self.gil_given as i64
+  }
+}
+
+/// Builds [`Trade`s](struct.Trade.html) from a stream of entries.
+///
+/// A trade starts with a request, and what was handed over and obtained is logged around the
+/// completion message. A trade is returned once an entry that isn't part of it is seen after it
+/// completed, or when `finish` is called. Canceled trades are never returned.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut tracker = TradeTracker::new();
+/// let mut trades: Vec<Trade> = entries.iter().filter_map(|x| tracker.observe(x)).collect();
+/// trades.extend(tracker.finish());
+/// ```
+#[derive(Debug, Default)]
+pub struct TradeTracker {
+  current: Option<Trade>,
+  completed: bool
+}
+
+impl TradeTracker {
+  pub fn new() -> Self {
+    TradeTracker::default()
+  }
+
+  /// Looks at the next entry, returning a trade if one was just finished.
+  pub fn observe(&mut self, entry: &Entry) -> Option<Trade> {
+    let counterpart = match_template("trade_request_sent", entry)
+      .or_else(|| match_template("trade_request_received", entry))
+      .and_then(|m| m.get("player").map(|x| x.to_owned()));
+    if counterpart.is_some() {
+      let finished = self.finish();
+      self.current = Some(TradeTracker::empty(entry.timestamp, counterpart));
+      return finished;
+    }
+    if match_template("trade_canceled", entry).is_some() {
+      self.current = None;
+      self.completed = false;
+      return None;
+    }
+    if match_template("trade_complete", entry).is_some() {
+      let current = self.current.get_or_insert_with(|| TradeTracker::empty(entry.timestamp, None));
+      current.timestamp = entry.timestamp;
+      self.completed = true;
+      return None;
+    }
+    if let Some(ref mut current) = self.current {
+      if let Some(m) = match_template("trade_gave_gil", entry) {
+        current.gil_given += opt_or!(m.get("amount").and_then(parse_number), 0);
+        return None;
+      }
+      if let Some(m) = match_template("trade_gave_item", entry) {
+        current.items_given.push(ItemQuantity::parse(opt_or!(m.get("item"), "")));
+        return None;
+      }
+      if let Some(m) = match_template("obtain_gil", entry) {
+        current.gil_received += opt_or!(m.get("amount").and_then(parse_number), 0);
+        return None;
+      }
+      if let Some(m) = match_template("obtain_item", entry) {
+        current.items_received.push(ItemQuantity::parse(opt_or!(m.get("item"), "")));
+        return None;
+      }
+    }
+    // Chat can happen while the trade window is open, so only a completed trade ends here.
+    self.finish()
+  }
+
+  /// Returns the trade currently being built, if it has completed.
+  pub fn finish(&mut self) -> Option<Trade> {
+    if !self.completed {
+      return None;
+    }
+    self.completed = false;
+    self.current.take()
+  }
+
+  fn empty(timestamp: u32, counterpart: Option<String>) -> Trade {
+    Trade {
+      timestamp,
+      counterpart,
+      items_given: Vec::new(),
+      gil_given: 0,
+      items_received: Vec::new(),
+      gil_received: 0
+    }
+  }
+}
@@ -0,0 +1,152 @@
+//! User-defined event extractors
+//!
+//! Not every community's messages are worth a built-in parser. An [`Extractor`](struct.Extractor.html)
+//! is a named regular expression, optionally limited to some channels, that turns matching entries
+//! into [`CustomEvent`s](struct.CustomEvent.html). Extractors can be defined in the configuration
+//! file:
+//!
+//! ```toml
+//! [[extractors]]
+//! name = "hunt_train"
+//! pattern = "(?i)train (?P<zone>\\w+) (?P<time>\\d+:\\d+)"
+//! channels = ["linkshell1", "free_company"]
+//! ```
+
+use config::ExtractorConfig;
+use messages::MessageType;
+use messages::entries::Entry;
+use pipeline::Sink;
+
+use regex::Regex;
+pub use regex::Error as PatternError;
+
+use std::collections::BTreeMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// An event from a user-defined extractor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomEvent {
+  /// The name of the extractor that matched.
+  pub name: String,
+  /// The text each capture group matched.
+  ///
+  /// Named groups are keyed by name and other groups by number. Groups that didn't take part in the
+  /// match are left out.
+  pub captures: BTreeMap<String, String>,
+  /// The entry that matched.
+  pub entry: Entry
+}
+
+/// A named pattern that entries are matched against.
+#[derive(Debug, Clone)]
+pub struct Extractor {
+  /// The name given to events from this extractor.
+  pub name: String,
+  regex: Regex,
+  channels: Vec<MessageType>
+}
+
+impl Extractor {
+  /// Create an extractor. If `channels` is empty, entries from every channel are matched.
+  pub fn new<S: Into<String>>(name: S, pattern: &str, channels: Vec<MessageType>) -> Result<Extractor, PatternError> {
+    Ok(Extractor {
+      name: name.into(),
+      regex: Regex::new(pattern)?,
+      channels
+    })
+  }
+
+  /// Create an extractor from its configuration.
+  ///
+  /// Channels that don't name a message type are ignored, since the configuration checks them when
+  /// it is loaded.
+  pub fn from_config(config: &ExtractorConfig) -> Result<Extractor, PatternError> {
+    let channels = config.channels.iter().filter_map(|x| x.parse().ok()).collect();
+    Extractor::new(config.name.clone(), &config.pattern, channels)
+  }
+
+  /// Matches the plain text of an entry's message, returning the captures if it matched.
+  pub fn captures(&self, entry: &Entry) -> Option<BTreeMap<String, String>> {
+    if !self.channels.is_empty() && !self.channels.contains(&entry.message_type) {
+      return None;
+    }
+    let text = entry.message.plain_text();
+    let captures = opt!(self.regex.captures(&text));
+    let mut map = BTreeMap::new();
+    for (i, name) in self.regex.capture_names().enumerate().skip(1) {
+      let (key, matched) = match name {
+        Some(n) => (n.to_owned(), captures.name(n)),
+        None => (i.to_string(), captures.get(i))
+      };
+      if let Some(m) = matched {
+        map.insert(key, m.as_str().to_owned());
+      }
+    }
+    Some(map)
+  }
+
+  /// Matches an entry, returning an event if it matched.
+  pub fn extract(&self, entry: &Entry) -> Option<CustomEvent> {
+    Some(CustomEvent {
+      name: self.name.clone(),
+      captures: opt!(self.captures(entry)),
+      entry: entry.clone()
+    })
+  }
+}
+
+/// A set of extractors that entries are matched against together.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractorSet {
+  extractors: Vec<Extractor>
+}
+
+impl ExtractorSet {
+  pub fn new() -> Self {
+    ExtractorSet::default()
+  }
+
+  /// Create a set from the extractors in a configuration.
+  pub fn from_config(configs: &[ExtractorConfig]) -> Result<ExtractorSet, PatternError> {
+    let extractors = configs.iter().map(Extractor::from_config).collect::<Result<Vec<_>, _>>()?;
+    Ok(ExtractorSet {
+      extractors
+    })
+  }
+
+  /// Adds an extractor.
+  pub fn add(&mut self, extractor: Extractor) {
+    self.extractors.push(extractor);
+  }
+
+  /// The extractors in this set.
+  pub fn extractors(&self) -> &[Extractor] {
+    &self.extractors
+  }
+
+  /// Matches an entry against every extractor, returning an event for each one that matched.
+  pub fn extract(&self, entry: &Entry) -> Vec<CustomEvent> {
+    self.extractors.iter().filter_map(|x| x.extract(entry)).collect()
+  }
+
+  /// Turns this set into a [`Sink`](../pipeline/trait.Sink.html) that sends every event down a
+  /// channel, returning the sink and the receiving end of the channel.
+  pub fn into_sink(self) -> (ExtractorSink, Receiver<CustomEvent>) {
+    let (tx, rx) = channel();
+    (ExtractorSink { set: self, tx }, rx)
+  }
+}
+
+/// The sink returned by [`ExtractorSet::into_sink`](struct.ExtractorSet.html#method.into_sink).
+pub struct ExtractorSink {
+  set: ExtractorSet,
+  tx: Sender<CustomEvent>
+}
+
+impl Sink for ExtractorSink {
+  fn handle(&mut self, entry: &Entry) {
+    for event in self.set.extract(entry) {
+      let _ = self.tx.send(event);
+    }
+  }
+}
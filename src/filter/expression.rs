@@ -0,0 +1,433 @@
+//! Filter expressions
+//!
+//! Building an [`EntryFilter`](../enum.EntryFilter.html) by hand is fine in code, but not on a
+//! command line or in a config file. Filter expressions are a short way to write one:
+//!
+//! ```text
+//! channel in (say, party) and sender ~ "Luna.*" and text contains "raid"
+//! ```
+//!
+//! An expression is made of comparisons joined with `and`, `or`, and `not`, with parentheses for
+//! grouping. `and` binds tighter than `or`. The comparisons are:
+//!
+//! - `channel = say`, `channel != say`, `channel in (say, party)`, and `channel not in (...)`
+//! - `sender = "Some Name"`, `sender !=`, `sender in (...)`, and `sender not in (...)`, ignoring case
+//! - `sender ~ "pattern"` and `sender !~ "pattern"`, matching a regular expression anywhere in the
+//!   sender's name
+//! - `text contains "words"` and `text not contains "words"`, ignoring case
+//! - `text ~ "pattern"` and `text !~ "pattern"`
+//! - `tag = name` and `tag != name`, for entries with or without a tag
+//! - `all`, which matches everything
+//!
+//! Values are either bare words or double-quoted strings, which can hold spaces and use `\"` and
+//! `\\` for quotes and backslashes. Keywords and field names ignore case.
+
+use filter::EntryFilter;
+use regex::{Regex, Error as PatternError};
+use messages::MessageType;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+
+/// An error in a filter expression.
+#[derive(Debug)]
+pub enum ExpressionError {
+  /// The expression ended where more was expected.
+  UnexpectedEnd,
+  /// Something unexpected was found. Contains the byte offset and what was found.
+  Unexpected(usize, String),
+  /// A quoted string was never closed. Contains the byte offset of its opening quote.
+  UnterminatedString(usize),
+  /// A field name was not one of the known fields.
+  UnknownField(String),
+  /// A channel name did not match any message type.
+  UnknownChannel(String),
+  /// A pattern is not a valid regular expression.
+  InvalidPattern(String, PatternError)
+}
+
+impl Display for ExpressionError {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match *self {
+      ExpressionError::UnexpectedEnd => write!(f, "unexpected end of filter"),
+      ExpressionError::Unexpected(at, ref found) => write!(f, "unexpected {} at position {} of filter", found, at),
+      ExpressionError::UnterminatedString(at) => write!(f, "unterminated string at position {} of filter", at),
+      ExpressionError::UnknownField(ref name) => write!(f, "unknown field in filter: {}", name),
+      ExpressionError::UnknownChannel(ref name) => write!(f, "unknown channel in filter: {}", name),
+      ExpressionError::InvalidPattern(ref p, ref e) => write!(f, "invalid pattern {} in filter: {}", p, e)
+    }
+  }
+}
+
+impl Error for ExpressionError {}
+
+/// Parses a filter expression into a filter.
+pub fn parse(expression: &str) -> Result<EntryFilter, ExpressionError> {
+  let mut parser = Parser {
+    tokens: tokenize(expression)?,
+    position: 0
+  };
+  let filter = parser.or()?;
+  match parser.next() {
+    Some(t) => Err(t.unexpected()),
+    None => Ok(filter)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+  /// A bare word, which may be a keyword.
+  Word(String),
+  /// A quoted string, which is never a keyword.
+  Quoted(String),
+  Operator(&'static str),
+  Open,
+  Close,
+  Comma
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+  kind: Kind,
+  at: usize
+}
+
+impl Token {
+  fn unexpected(&self) -> ExpressionError {
+    let found = match self.kind {
+      Kind::Word(ref w) => format!("'{}'", w),
+      Kind::Quoted(ref q) => format!("\"{}\"", q),
+      Kind::Operator(o) => format!("'{}'", o),
+      Kind::Open => "'('".to_owned(),
+      Kind::Close => "')'".to_owned(),
+      Kind::Comma => "','".to_owned()
+    };
+    ExpressionError::Unexpected(self.at, found)
+  }
+
+  /// Checks if this token is the given keyword.
+  fn is(&self, keyword: &str) -> bool {
+    match self.kind {
+      Kind::Word(ref w) => w.eq_ignore_ascii_case(keyword),
+      _ => false
+    }
+  }
+}
+
+/// Operators, longest first so `!=` isn't read as `!` and `=`.
+static OPERATORS: &'static [&'static str] = &["==", "!=", "!~", "=", "~"];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, ExpressionError> {
+  let mut tokens = Vec::new();
+  let mut chars = expression.char_indices().peekable();
+  while let Some(&(at, c)) = chars.peek() {
+    let kind = match c {
+      _ if c.is_whitespace() => {
+        chars.next();
+        continue;
+      },
+      '(' => Kind::Open,
+      ')' => Kind::Close,
+      ',' => Kind::Comma,
+      '"' => {
+        chars.next();
+        let mut text = String::new();
+        loop {
+          match chars.next() {
+            Some((_, '"')) => break,
+            Some((_, '\\')) => match chars.next() {
+              Some((_, c)) => text.push(c),
+              None => return Err(ExpressionError::UnterminatedString(at))
+            },
+            Some((_, c)) => text.push(c),
+            None => return Err(ExpressionError::UnterminatedString(at))
+          }
+        }
+        tokens.push(Token { kind: Kind::Quoted(text), at });
+        continue;
+      },
+      _ => {
+        if let Some(op) = OPERATORS.iter().find(|x| expression[at..].starts_with(*x)) {
+          for _ in 0..op.len() {
+            chars.next();
+          }
+          tokens.push(Token { kind: Kind::Operator(op), at });
+          continue;
+        }
+        let mut word = String::new();
+        while let Some(&(_, c)) = chars.peek() {
+          if c.is_whitespace() || "()\",=!~".contains(c) {
+            break;
+          }
+          word.push(c);
+          chars.next();
+        }
+        if word.is_empty() {
+          return Err(ExpressionError::Unexpected(at, format!("'{}'", c)));
+        }
+        tokens.push(Token { kind: Kind::Word(word), at });
+        continue;
+      }
+    };
+    chars.next();
+    tokens.push(Token { kind, at });
+  }
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<Token>,
+  position: usize
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.tokens.get(self.position).cloned();
+    self.position += 1;
+    token
+  }
+
+  fn expect(&mut self) -> Result<Token, ExpressionError> {
+    self.next().ok_or(ExpressionError::UnexpectedEnd)
+  }
+
+  /// Skips the next token if it's the given keyword.
+  fn eat(&mut self, keyword: &str) -> bool {
+    if self.peek().map(|x| x.is(keyword)).unwrap_or(false) {
+      self.position += 1;
+      return true;
+    }
+    false
+  }
+
+  fn or(&mut self) -> Result<EntryFilter, ExpressionError> {
+    let mut filters = vec![self.and()?];
+    while self.eat("or") {
+      filters.push(self.and()?);
+    }
+    Ok(if filters.len() == 1 { filters.remove(0) } else { EntryFilter::Or(filters) })
+  }
+
+  fn and(&mut self) -> Result<EntryFilter, ExpressionError> {
+    let mut filters = vec![self.unary()?];
+    while self.eat("and") {
+      filters.push(self.unary()?);
+    }
+    Ok(if filters.len() == 1 { filters.remove(0) } else { EntryFilter::And(filters) })
+  }
+
+  fn unary(&mut self) -> Result<EntryFilter, ExpressionError> {
+    if self.eat("not") {
+      return Ok(EntryFilter::Not(Box::new(self.unary()?)));
+    }
+    if self.eat("all") {
+      return Ok(EntryFilter::All);
+    }
+    let token = self.expect()?;
+    if token.kind == Kind::Open {
+      let filter = self.or()?;
+      let close = self.expect()?;
+      if close.kind != Kind::Close {
+        return Err(close.unexpected());
+      }
+      return Ok(filter);
+    }
+    let field = match token.kind {
+      Kind::Word(ref w) => w.to_lowercase(),
+      _ => return Err(token.unexpected())
+    };
+    match field.as_str() {
+      "channel" => self.comparison(Field::Channel),
+      "sender" => self.comparison(Field::Sender),
+      "text" => self.comparison(Field::Text),
+      "tag" => self.comparison(Field::Tag),
+      _ => Err(ExpressionError::UnknownField(field))
+    }
+  }
+
+  fn comparison(&mut self, field: Field) -> Result<EntryFilter, ExpressionError> {
+    let token = self.expect()?;
+    let negated = token.is("not");
+    let token = if negated { self.expect()? } else { token };
+    let (filter, negated) = match (field, &token.kind) {
+      (Field::Channel, &Kind::Operator(op)) if op != "~" && op != "!~" && !negated => {
+        (EntryFilter::MessageTypes(vec![channel(&self.value()?)?]), op == "!=")
+      },
+      (Field::Channel, &Kind::Word(_)) if token.is("in") => {
+        let channels = self.list()?.iter().map(|x| channel(x)).collect::<Result<_, _>>()?;
+        (EntryFilter::MessageTypes(channels), negated)
+      },
+      (Field::Sender, &Kind::Operator(op)) if (op == "~" || op == "!~") && !negated => {
+        (EntryFilter::SenderMatches(pattern(&self.value()?)?), op == "!~")
+      },
+      (Field::Sender, &Kind::Operator(op)) if !negated => (EntryFilter::Senders(vec![self.value()?]), op == "!="),
+      (Field::Sender, &Kind::Word(_)) if token.is("in") => (EntryFilter::Senders(self.list()?), negated),
+      (Field::Text, &Kind::Operator(op)) if (op == "~" || op == "!~") && !negated => {
+        (EntryFilter::TextMatches(pattern(&self.value()?)?), op == "!~")
+      },
+      (Field::Text, &Kind::Word(_)) if token.is("contains") => (EntryFilter::TextContains(self.value()?), negated),
+      (Field::Tag, &Kind::Operator(op)) if op != "~" && op != "!~" && !negated => {
+        (EntryFilter::HasTag(self.value()?), op == "!=")
+      },
+      _ => return Err(token.unexpected())
+    };
+    Ok(if negated { EntryFilter::Not(Box::new(filter)) } else { filter })
+  }
+
+  fn value(&mut self) -> Result<String, ExpressionError> {
+    let token = self.expect()?;
+    match token.kind {
+      Kind::Word(w) | Kind::Quoted(w) => Ok(w),
+      _ => Err(token.unexpected())
+    }
+  }
+
+  fn list(&mut self) -> Result<Vec<String>, ExpressionError> {
+    let open = self.expect()?;
+    if open.kind != Kind::Open {
+      return Err(open.unexpected());
+    }
+    let mut values = vec![self.value()?];
+    loop {
+      let token = self.expect()?;
+      match token.kind {
+        Kind::Comma => values.push(self.value()?),
+        Kind::Close => return Ok(values),
+        _ => return Err(token.unexpected())
+      }
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+  Channel,
+  Sender,
+  Text,
+  Tag
+}
+
+fn channel(name: &str) -> Result<MessageType, ExpressionError> {
+  name.parse().map_err(|_| ExpressionError::UnknownChannel(name.to_owned()))
+}
+
+fn pattern(pattern: &str) -> Result<Regex, ExpressionError> {
+  Regex::new(pattern).map_err(|e| ExpressionError::InvalidPattern(pattern.to_owned(), e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use messages::Message;
+  use messages::entries::Entry;
+  use messages::parts::Part;
+
+  use std::collections::BTreeMap;
+
+  fn entry(message_type: MessageType, sender: &str, text: &str) -> Entry {
+    Entry {
+      message_type,
+      timestamp: 0,
+      sender: Some(Part::PlainText(sender.to_owned())),
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  fn matches(expression: &str, entry: &Entry) -> bool {
+    parse(expression).unwrap().matches(entry)
+  }
+
+  #[test]
+  fn and_binds_tighter_than_or() {
+    let say = entry(MessageType::Say, "Someone", "hello");
+    let party = entry(MessageType::Party, "Someone", "hello");
+    assert!(matches("channel = say or channel = party and sender = nobody", &say));
+    assert!(!matches("channel = say or channel = party and sender = nobody", &party));
+    assert!(!matches("(channel = say or channel = party) and sender = nobody", &say));
+  }
+
+  #[test]
+  fn not_binds_tighter_than_and() {
+    let say = entry(MessageType::Say, "Someone", "hello");
+    let party = entry(MessageType::Party, "Someone", "hello");
+    assert!(matches("not channel = say and sender = someone", &party));
+    assert!(!matches("not channel = say and sender = someone", &say));
+    assert!(matches("not (channel = say and sender = nobody)", &say));
+    assert!(matches("not not channel = say", &say));
+  }
+
+  #[test]
+  fn in_lists() {
+    let party = entry(MessageType::Party, "Some One", "hello");
+    assert!(matches("channel in (say, party)", &party));
+    assert!(!matches("channel not in (say, party)", &party));
+    assert!(matches("sender in (\"some one\", other)", &party));
+    assert!(matches("sender not in (other)", &party));
+    match parse("channel in (say, party") {
+      Err(ExpressionError::UnexpectedEnd) => {},
+      other => panic!("expected the end to be unexpected, got {:?}", other)
+    }
+    match parse("channel in (say party)") {
+      Err(ExpressionError::Unexpected(16, _)) => {},
+      other => panic!("expected party to be unexpected, got {:?}", other)
+    }
+    match parse("channel in (say, nowhere)") {
+      Err(ExpressionError::UnknownChannel(ref c)) if c == "nowhere" => {},
+      other => panic!("expected an unknown channel, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn quoted_strings() {
+    let say = entry(MessageType::Say, "Someone", "say \"hi\" \\ bye");
+    assert!(matches(r#"text contains "\"hi\" \\""#, &say));
+    match parse("text contains \"raid") {
+      Err(ExpressionError::UnterminatedString(14)) => {},
+      other => panic!("expected an unterminated string, got {:?}", other)
+    }
+    match parse("text contains \"raid\\") {
+      Err(ExpressionError::UnterminatedString(14)) => {},
+      other => panic!("expected an unterminated string, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn invalid_patterns() {
+    match parse("text ~ \"(unclosed\"") {
+      Err(ExpressionError::InvalidPattern(ref p, _)) if p == "(unclosed" => {},
+      other => panic!("expected an invalid pattern, got {:?}", other)
+    }
+    match parse("sender !~ \"[a-\"") {
+      Err(ExpressionError::InvalidPattern(ref p, _)) if p == "[a-" => {},
+      other => panic!("expected an invalid pattern, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn other_errors() {
+    match parse("colour = red") {
+      Err(ExpressionError::UnknownField(ref f)) if f == "colour" => {},
+      other => panic!("expected an unknown field, got {:?}", other)
+    }
+    match parse("channel = say sender = x") {
+      Err(ExpressionError::Unexpected(14, _)) => {},
+      other => panic!("expected sender to be unexpected, got {:?}", other)
+    }
+    match parse("channel ~ say") {
+      Err(ExpressionError::Unexpected(8, _)) => {},
+      other => panic!("expected ~ to be unexpected, got {:?}", other)
+    }
+    match parse("") {
+      Err(ExpressionError::UnexpectedEnd) => {},
+      other => panic!("expected the end to be unexpected, got {:?}", other)
+    }
+  }
+}
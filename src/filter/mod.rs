@@ -0,0 +1,167 @@
+//! Filters for entries
+//!
+//! Filters can also be written as [expressions](expression/index.html), like
+//! `channel in (say, party) and text contains "raid"`, for use on the command line and in config
+//! files.
+
+pub mod expression;
+
+use messages::{MessageType, HasDisplayText};
+use messages::entries::Entry;
+
+use self::expression::ExpressionError;
+
+use regex::Regex;
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A filter that decides whether an entry should be kept.
+///
+/// Filters can be combined with `And`, `Or`, and `Not` to build up more complicated rules.
+///
+/// # Examples
+/// This filter keeps party chat from anyone except `Some Name`.
+///
+/// ```rust,no_run
+/// let filter = EntryFilter::And(vec![
+///   EntryFilter::MessageTypes(vec![MessageType::Party]),
+///   EntryFilter::Not(Box::new(EntryFilter::Senders(vec![String::from("Some Name")])))
+/// ]);
+/// ```
+///
+/// The same filter as an [expression](expression/index.html):
+///
+/// ```rust,no_run
+/// let filter: EntryFilter = "channel = party and sender != \"Some Name\"".parse().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub enum EntryFilter {
+  /// Matches every entry.
+  All,
+
+  /// Matches entries with any of the given message types.
+  MessageTypes(Vec<MessageType>),
+
+  /// Matches entries sent by any of the given names.
+  ///
+  /// Names are compared against the real name of the sender, ignoring case.
+  Senders(Vec<String>),
+
+  /// Matches entries whose sender's real name matches the pattern anywhere.
+  SenderMatches(Regex),
+
+  /// Matches entries whose message contains the given text, ignoring case.
+  TextContains(String),
+
+  /// Matches entries whose message matches the pattern anywhere.
+  TextMatches(Regex),
+
+  /// Matches entries with the given tag, whatever its value.
+  HasTag(String),
+
+  /// Matches entries that match every filter.
+  And(Vec<EntryFilter>),
+
+  /// Matches entries that match any filter.
+  Or(Vec<EntryFilter>),
+
+  /// Matches entries that do not match the filter.
+  Not(Box<EntryFilter>)
+}
+
+impl EntryFilter {
+  /// Checks if the entry matches this filter.
+  pub fn matches(&self, entry: &Entry) -> bool {
+    match *self {
+      EntryFilter::All => true,
+      EntryFilter::MessageTypes(ref types) => types.contains(&entry.message_type),
+      EntryFilter::Senders(ref names) => {
+        let sender = opt_or!(entry.sender_name(), return false).to_lowercase();
+        names.iter().any(|x| x.to_lowercase() == sender)
+      },
+      EntryFilter::SenderMatches(ref pattern) => pattern.is_match(&opt_or!(entry.sender_name(), return false)),
+      EntryFilter::TextContains(ref text) => {
+        entry.message.display_text().to_lowercase().contains(&text.to_lowercase())
+      },
+      EntryFilter::TextMatches(ref pattern) => pattern.is_match(&entry.message.display_text()),
+      EntryFilter::HasTag(ref tag) => entry.has_tag(tag),
+      EntryFilter::And(ref filters) => filters.iter().all(|x| x.matches(entry)),
+      EntryFilter::Or(ref filters) => filters.iter().any(|x| x.matches(entry)),
+      EntryFilter::Not(ref filter) => !filter.matches(entry)
+    }
+  }
+}
+
+impl FromStr for EntryFilter {
+  type Err = ExpressionError;
+
+  /// Parses a filter [expression](expression/index.html).
+  fn from_str(s: &str) -> Result<EntryFilter, ExpressionError> {
+    expression::parse(s)
+  }
+}
+
+impl Default for EntryFilter {
+  fn default() -> Self {
+    EntryFilter::All
+  }
+}
+
+/// A list of characters whose entries should never be shown.
+///
+/// Names are compared ignoring case.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+  names: BTreeSet<String>
+}
+
+impl Blocklist {
+  pub fn new() -> Self {
+    Blocklist::default()
+  }
+
+  /// Loads a blocklist from a file with one name per line.
+  ///
+  /// Empty lines and lines starting with `#` are ignored.
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Blocklist> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut blocklist = Blocklist::new();
+    for line in reader.lines() {
+      let line = line?;
+      let name = line.trim();
+      if name.is_empty() || name.starts_with('#') {
+        continue;
+      }
+      blocklist.add(name);
+    }
+    Ok(blocklist)
+  }
+
+  /// Adds a name to the blocklist.
+  pub fn add<S: AsRef<str>>(&mut self, name: S) {
+    self.names.insert(name.as_ref().to_lowercase());
+  }
+
+  /// Removes a name from the blocklist.
+  pub fn remove<S: AsRef<str>>(&mut self, name: S) -> bool {
+    self.names.remove(&name.as_ref().to_lowercase())
+  }
+
+  /// Checks if the entry was sent by someone on the blocklist.
+  pub fn blocks(&self, entry: &Entry) -> bool {
+    let sender = opt_or!(entry.sender_name(), return false);
+    self.names.contains(&sender.to_lowercase())
+  }
+
+  /// Converts the blocklist into a filter that matches every entry not sent by a blocked name.
+  pub fn to_filter(&self) -> EntryFilter {
+    if self.names.is_empty() {
+      return EntryFilter::All;
+    }
+    EntryFilter::Not(Box::new(EntryFilter::Senders(self.names.iter().cloned().collect())))
+  }
+}
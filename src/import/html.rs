@@ -0,0 +1,166 @@
+//! HTML exports
+//!
+//! HTML is turned back into lines of text, breaking at block elements, and then read the same way
+//! as [plain text](../text/index.html). Pages written by the
+//! [`HtmlRenderer`](../../render/struct.HtmlRenderer.html) keep their full timestamps and channels.
+
+use import::{ImportFormat, ImportOptions};
+use import::text::parse_lines;
+use messages::MessageType;
+use messages::entries::Entry;
+
+/// Elements that start a new line.
+static BLOCK_ELEMENTS: &'static [&'static str] = &["br", "div", "p", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Parses an HTML export.
+pub fn parse(data: &str, options: &ImportOptions) -> Vec<Entry> {
+  let text = to_text(data);
+  parse_lines(text.lines(), ImportFormat::Html, options)
+}
+
+/// Converts HTML into plain text, with a line for each block element.
+pub fn to_text(html: &str) -> String {
+  let mut text = String::with_capacity(html.len());
+  // The channel of a rendered entry, written out after its timestamp.
+  let mut channel = None;
+  let mut skip_until = None;
+  let mut rest = html;
+  while !rest.is_empty() {
+    let start = match rest.find('<') {
+      Some(s) => s,
+      None => {
+        if skip_until.is_none() {
+          push_decoded(&mut text, rest);
+        }
+        break;
+      }
+    };
+    if skip_until.is_none() {
+      push_decoded(&mut text, &rest[..start]);
+    }
+    let end = opt_or!(rest[start..].find('>'), break) + start;
+    let tag = Tag::parse(&rest[start + 1..end]);
+    rest = &rest[end + 1..];
+
+    if skip_until.is_some() {
+      if tag.closing && skip_until.as_ref() == Some(&tag.name) {
+        skip_until = None;
+      }
+      continue;
+    }
+    match tag.name.as_str() {
+      "script" | "style" | "head" if !tag.closing => skip_until = Some(tag.name.clone()),
+      "time" if !tag.closing => {
+        if let Some(datetime) = tag.attribute("datetime") {
+          text.push('[');
+          text.push_str(&datetime);
+          text.push(']');
+          // Skip the displayed time, since the attribute has all of it.
+          if let Some(close) = rest.find("</time>") {
+            rest = &rest[close + "</time>".len()..];
+          }
+          if let Some(c) = channel.take() {
+            text.push_str(&format!(" [{}]", c));
+          }
+        }
+      },
+      "span" if !tag.closing && tag.has_class("sender") => {
+        // The colon after the sender is only added with CSS, so add it back.
+        let close = opt_or!(rest.find("</span>"), continue);
+        let sender = to_text(&rest[..close]);
+        text.push_str(sender.trim());
+        text.push(':');
+        rest = &rest[close + "</span>".len()..];
+      },
+      name if BLOCK_ELEMENTS.contains(&name) => {
+        text.push('\n');
+        if name == "div" && !tag.closing && tag.has_class("entry") {
+          channel = tag.classes().into_iter()
+            .filter(|x| *x != "entry")
+            .find(|x| x.parse::<MessageType>().is_ok());
+        }
+      },
+      _ => {}
+    }
+  }
+  text
+}
+
+struct Tag {
+  name: String,
+  closing: bool,
+  attributes: String
+}
+
+impl Tag {
+  fn parse(inner: &str) -> Tag {
+    let inner = inner.trim().trim_end_matches('/');
+    let closing = inner.starts_with('/');
+    let inner = inner.trim_start_matches('/');
+    let split = inner.find(char::is_whitespace).unwrap_or_else(|| inner.len());
+    Tag {
+      name: inner[..split].to_lowercase(),
+      closing,
+      attributes: inner[split..].to_owned()
+    }
+  }
+
+  fn attribute(&self, name: &str) -> Option<String> {
+    let key = format!("{}=\"", name);
+    let start = opt!(self.attributes.find(&key)) + key.len();
+    let end = opt!(self.attributes[start..].find('"')) + start;
+    let mut value = String::new();
+    push_decoded(&mut value, &self.attributes[start..end]);
+    Some(value)
+  }
+
+  fn classes(&self) -> Vec<String> {
+    self.attribute("class")
+      .map(|x| x.split_whitespace().map(|c| c.to_owned()).collect())
+      .unwrap_or_default()
+  }
+
+  fn has_class(&self, class: &str) -> bool {
+    self.classes().iter().any(|x| x == class)
+  }
+}
+
+fn push_decoded(text: &mut String, html: &str) {
+  let mut rest = html;
+  while let Some(start) = rest.find('&') {
+    text.push_str(&rest[..start]);
+    rest = &rest[start..];
+    let end = match rest.find(';') {
+      Some(e) if e <= 10 => e,
+      _ => {
+        text.push('&');
+        rest = &rest[1..];
+        continue;
+      }
+    };
+    let decoded = match &rest[1..end] {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      "nbsp" => Some(' '),
+      entity if entity.starts_with("#x") || entity.starts_with("#X") => {
+        u32::from_str_radix(&entity[2..], 16).ok().and_then(::std::char::from_u32)
+      },
+      entity if entity.starts_with('#') => entity[1..].parse().ok().and_then(::std::char::from_u32),
+      _ => None
+    };
+    match decoded {
+      Some(c) => {
+        text.push(c);
+        rest = &rest[end + 1..];
+      },
+      None => {
+        text.push('&');
+        rest = &rest[1..];
+      }
+    }
+  }
+  text.push_str(rest);
+}
@@ -0,0 +1,106 @@
+//! Importing logs exported by other tools
+//!
+//! Older chat history often only exists as plain text or HTML saved by other tools. The importers
+//! here turn those back into [`Entries`](../messages/entries/struct.Entry.html) so they can be
+//! archived alongside logs read by this crate.
+//!
+//! Exports only keep text, so imported entries never have icons, links, or auto-translate parts.
+//! Every imported entry is tagged with `imported`, set to the format it came from.
+
+pub mod text;
+pub mod html;
+
+use messages::{Message, MessageType};
+use messages::entries::Entry;
+use messages::parts::PlainTextPart;
+
+use chrono::NaiveDate;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Options for importing.
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+  /// The type given to entries whose channel isn't shown in the export.
+  pub default_type: MessageType,
+  /// The date of the first entry, for exports that only show the time of day.
+  ///
+  /// Lines with only a time are skipped if this is not set.
+  pub date: Option<NaiveDate>,
+  /// The offset from UTC of the times in the export, in seconds east of UTC.
+  ///
+  /// Times that include an offset ignore this.
+  pub utc_offset: i32
+}
+
+impl Default for ImportOptions {
+  fn default() -> Self {
+    ImportOptions {
+      default_type: MessageType::Say,
+      date: None,
+      utc_offset: 0
+    }
+  }
+}
+
+/// A format that can be imported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+  Text,
+  Html
+}
+
+impl ImportFormat {
+  /// Determines the format of a file from its extension.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImportFormat> {
+    match path.as_ref().extension().and_then(|x| x.to_str()).map(|x| x.to_lowercase()) {
+      Some(ref e) if e == "txt" || e == "log" => Some(ImportFormat::Text),
+      Some(ref e) if e == "html" || e == "htm" => Some(ImportFormat::Html),
+      _ => None
+    }
+  }
+
+  /// The name used in the `imported` tag.
+  pub fn name(&self) -> &'static str {
+    match *self {
+      ImportFormat::Text => "text",
+      ImportFormat::Html => "html"
+    }
+  }
+
+  /// Parses the contents of an export.
+  pub fn parse(&self, data: &str, options: &ImportOptions) -> Vec<Entry> {
+    match *self {
+      ImportFormat::Text => text::parse(data, options),
+      ImportFormat::Html => html::parse(data, options)
+    }
+  }
+}
+
+/// Imports a file, guessing its format from its extension.
+pub fn import_file<P: AsRef<Path>>(path: P, options: &ImportOptions) -> io::Result<Vec<Entry>> {
+  let path = path.as_ref();
+  let format = match ImportFormat::from_path(path) {
+    Some(f) => f,
+    None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "unknown import format"))
+  };
+  let mut data = String::new();
+  File::open(path)?.read_to_string(&mut data)?;
+  Ok(format.parse(&data, options))
+}
+
+fn imported_entry(format: ImportFormat, message_type: MessageType, timestamp: u32, sender: Option<&str>, message: &str) -> Entry {
+  let mut tags = BTreeMap::new();
+  tags.insert(String::from("imported"), format.name().to_owned());
+  Entry {
+    message_type,
+    timestamp,
+    sender: sender.map(PlainTextPart::from_text),
+    message: Message::new(vec![PlainTextPart::from_text(message)]),
+    tags,
+    provenance: None
+  }
+}
@@ -0,0 +1,201 @@
+//! Plain text exports
+//!
+//! Each line should start with a timestamp in brackets, optionally followed by the channel, then the
+//! sender and the message. These are all understood:
+//!
+//! ```text
+//! [27/06/2017 12:34:56], Party, <Some Name> hello
+//! [2017-06-27 12:34:56] [Party] Some Name: hello
+//! [12:34] Some Name: hello
+//! ```
+//!
+//! The first is the format written by `output_text`, so dates with slashes are read as day first.
+//! Lines that don't start with a timestamp are treated as more of the message before them.
+
+use import::{ImportFormat, ImportOptions, imported_entry};
+use messages::{Message, MessageType};
+use messages::entries::Entry;
+use messages::parts::{Part, PlainTextPart};
+
+use chrono::{DateTime, NaiveDateTime};
+
+/// Formats for timestamps with a date, tried in order.
+static DATE_TIME_FORMATS: &'static [&'static str] = &[
+  "%d/%m/%Y %H:%M:%S",
+  "%d/%m/%Y %H:%M",
+  "%Y-%m-%d %H:%M:%S",
+  "%Y-%m-%d %H:%M",
+  "%Y-%m-%dT%H:%M:%S",
+  "%Y/%m/%d %H:%M:%S"
+];
+
+/// Formats for timestamps with only a time of day, tried in order.
+static TIME_FORMATS: &'static [&'static str] = &["%H:%M:%S", "%H:%M"];
+
+/// The longest sender name that will be recognized before a colon.
+const MAX_SENDER_LENGTH: usize = 40;
+
+/// Parses a plain text export.
+pub fn parse(data: &str, options: &ImportOptions) -> Vec<Entry> {
+  parse_lines(data.lines(), ImportFormat::Text, options)
+}
+
+/// Parses lines of text, tagging the entries as coming from `format`.
+pub fn parse_lines<'a, I>(lines: I, format: ImportFormat, options: &ImportOptions) -> Vec<Entry>
+  where I: IntoIterator<Item = &'a str>
+{
+  let mut entries: Vec<Entry> = Vec::new();
+  let mut day_offset = 0;
+  let mut last_timestamp = None;
+  for line in lines {
+    let line = line.trim_end_matches('\r');
+    if line.trim().is_empty() {
+      continue;
+    }
+    let (stamp, rest) = match split_timestamp(line) {
+      Some(s) => s,
+      None => {
+        // Not the start of an entry, so it must be another line of the last message.
+        if let Some(last) = entries.last_mut() {
+          append_line(&mut last.message, line);
+        }
+        continue;
+      }
+    };
+    let mut timestamp = match stamp {
+      Stamp::Utc(t) => t,
+      Stamp::Local(t) => t - options.utc_offset as i64,
+      Stamp::TimeOfDay(t) => {
+        let date = opt_or!(options.date, continue);
+        let text = format!("{} {}", date.format("%Y-%m-%d"), t);
+        let time = opt_or!(parse_with(&text, TIME_FORMATS, "%Y-%m-%d "), continue);
+        time - options.utc_offset as i64 + day_offset
+      }
+    };
+    // Exports with only times roll over at midnight, so move to the next day when time goes back.
+    if let (Stamp::TimeOfDay(_), Some(last)) = (stamp, last_timestamp) {
+      while timestamp < last - 12 * 3600 {
+        day_offset += 86400;
+        timestamp += 86400;
+      }
+    }
+    last_timestamp = Some(timestamp);
+    if timestamp < 0 || timestamp > ::std::u32::MAX as i64 {
+      continue;
+    }
+    let (channel, rest) = split_channel(rest);
+    let (sender, message) = split_sender(rest);
+    entries.push(imported_entry(format, channel.unwrap_or(options.default_type), timestamp as u32, sender, message));
+  }
+  entries
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Stamp<'a> {
+  /// Seconds since the epoch, from a timestamp that included its offset.
+  Utc(i64),
+  /// Seconds since the epoch, before applying the UTC offset.
+  Local(i64),
+  /// A time of day, before applying the date and the UTC offset.
+  TimeOfDay(&'a str)
+}
+
+fn split_timestamp(line: &str) -> Option<(Stamp<'_>, &str)> {
+  if !line.starts_with('[') {
+    return None;
+  }
+  let end = opt!(line.find(']'));
+  let inner = line[1..end].trim();
+  let rest = line[end + 1..].trim_start_matches(',').trim_start();
+  if let Ok(dt) = DateTime::parse_from_rfc3339(inner) {
+    return Some((Stamp::Utc(dt.timestamp()), rest));
+  }
+  if let Some(t) = parse_with(inner, DATE_TIME_FORMATS, "") {
+    return Some((Stamp::Local(t), rest));
+  }
+  if parse_with(&format!("2000-01-01 {}", inner), TIME_FORMATS, "%Y-%m-%d ").is_some() {
+    return Some((Stamp::TimeOfDay(inner), rest));
+  }
+  None
+}
+
+fn parse_with(text: &str, formats: &[&str], prefix: &str) -> Option<i64> {
+  formats.iter()
+    .filter_map(|f| NaiveDateTime::parse_from_str(text, &format!("{}{}", prefix, f)).ok())
+    .map(|x| x.timestamp())
+    .next()
+}
+
+fn split_channel(rest: &str) -> (Option<MessageType>, &str) {
+  for &(open, close) in &[('[', ']'), ('(', ')')] {
+    if rest.starts_with(open) {
+      if let Some(end) = rest.find(close) {
+        if let Some(channel) = parse_channel(&rest[1..end]) {
+          return (Some(channel), rest[end + 1..].trim_start());
+        }
+      }
+    }
+  }
+  // The output_text format writes the type followed by a comma.
+  if let Some(end) = rest.find(',') {
+    if let Some(channel) = parse_channel(&rest[..end]) {
+      return (Some(channel), rest[end + 1..].trim_start());
+    }
+  }
+  (None, rest)
+}
+
+fn parse_channel(name: &str) -> Option<MessageType> {
+  let name = name.trim();
+  if let Ok(t) = name.parse::<MessageType>() {
+    if name.parse::<u8>().is_err() {
+      return Some(t);
+    }
+  }
+  let lower = name.to_lowercase().replace(' ', "");
+  let channel = match lower.as_str() {
+    "say" | "s" => MessageType::Say,
+    "shout" | "sh" => MessageType::Shout,
+    "yell" | "y" => MessageType::Yell,
+    "party" | "p" => MessageType::Party,
+    "alliance" | "a" => MessageType::Alliance,
+    "freecompany" | "fc" => MessageType::FreeCompany,
+    "tell" | "t" => MessageType::TellReceive,
+    "novicenetwork" | "nn" => MessageType::NoviceNetwork,
+    "emote" | "em" => MessageType::CustomEmotes,
+    "ls1" => MessageType::Linkshell1,
+    "ls2" => MessageType::Linkshell2,
+    "ls3" => MessageType::Linkshell3,
+    "ls4" => MessageType::Linkshell4,
+    "ls5" => MessageType::Linkshell5,
+    "ls6" => MessageType::Linkshell6,
+    "ls7" => MessageType::Linkshell7,
+    "ls8" => MessageType::Linkshell8,
+    _ => return None
+  };
+  Some(channel)
+}
+
+fn split_sender(rest: &str) -> (Option<&str>, &str) {
+  if rest.starts_with('<') {
+    if let Some(end) = rest.find('>') {
+      return (Some(&rest[1..end]), rest[end + 1..].trim_start());
+    }
+  }
+  if let Some(end) = rest.find(": ") {
+    let sender = &rest[..end];
+    if !sender.is_empty() && sender.len() <= MAX_SENDER_LENGTH && !sender.contains(|c| c == '[' || c == ']') {
+      return (Some(sender), &rest[end + 2..]);
+    }
+  }
+  (None, rest)
+}
+
+fn append_line(message: &mut Message, line: &str) {
+  // The game separates lines in a message with carriage returns.
+  let text = format!("\r{}", line);
+  match message.parts.last_mut() {
+    Some(&mut Part::PlainText(ref mut t)) => t.push_str(&text),
+    _ => message.parts.push(PlainTextPart::from_text(text))
+  }
+}
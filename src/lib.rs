@@ -6,6 +6,10 @@ extern crate serde_json;
 #[macro_use]
 extern crate lazy_static;
 extern crate chrono;
+extern crate regex;
+extern crate toml;
+extern crate xz2;
+extern crate zstd;
 
 use byteorder::{LittleEndian, ByteOrder};
 use std::sync::mpsc::Receiver;
@@ -50,10 +54,38 @@ macro_rules! read {
 
 pub mod messages;
 pub mod act;
+pub mod pipeline;
+pub mod filter;
+pub mod config;
+pub mod localization;
+pub mod archive;
+pub mod events;
+pub mod names;
+pub mod render;
+pub mod sound;
+pub mod extract;
+pub mod routing;
+pub mod annotate;
+pub mod import;
+pub mod daemon;
+pub mod schema;
+pub mod character;
+pub mod diagnostics;
+pub mod redact;
+pub mod similar;
+pub mod storage;
+pub mod rate;
+pub mod diff;
+pub mod tokenize;
+pub mod telemetry;
+pub mod watchdog;
+pub mod sample;
+#[cfg(feature = "search")]
+pub mod search;
 
 pub use act::ActReader;
 
-use messages::entries::{Entry, RawEntry};
+use messages::entries::{Entry, Provenance, RawEntry};
 
 fn get_base_address(reader: Option<&MemReader>) -> Option<usize> {
   let reader = opt!(reader);
@@ -143,29 +175,62 @@ impl MemoryEntryReader {
   ///
   /// This will return `None` if `start` has already been called or if the memory can't be read.
   pub fn start(&self) -> Option<Receiver<Vec<u8>>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    if self.spawn(move |bytes, _| tx.send(bytes).is_ok()) {
+      Some(rx)
+    } else {
+      None
+    }
+  }
+
+  /// Starts the memory reading loop, sending the raw bytes for each entry along with where in the
+  /// memory they were read from.
+  ///
+  /// This will return `None` if `start` has already been called or if the memory can't be read.
+  pub fn start_traced(&self) -> Option<Receiver<(Vec<u8>, Provenance)>> {
+    let (tx, rx) = std::sync::mpsc::channel();
+    if self.spawn(move |bytes, provenance| tx.send((bytes, provenance)).is_ok()) {
+      Some(rx)
+    } else {
+      None
+    }
+  }
+
+  /// Spawns the memory reading loop, which calls `send` for each entry until it returns `false`.
+  fn spawn<F>(&self, mut send: F) -> bool
+    where F: FnMut(Vec<u8>, Provenance) -> bool + Send + 'static
+  {
     if self.run.load(Ordering::Relaxed) {
-      return None;
+      return false;
     }
     // Create a reader around the PID of the game.
     let reader = match MemReader::new(self.pid) {
       Ok(r) => r,
       Err(e) => {
         println!("Encountered error {} when trying to access memory.", e);
-        return None;
+        return false;
+      }
+    };
+    let lines_address = match get_lines_address(Some(&reader)) {
+      Some(a) => a,
+      None => {
+        println!("Could not find the chat log in memory. The game may have been patched, changing where it is.");
+        return false;
       }
     };
-    let lines_address = opt!(get_lines_address(Some(&reader)));
     let chat_pointer = get_chat_pointer(lines_address);
     let index_pointer = get_index_pointer(lines_address);
-    let raw_chat_pointer = opt!(read!(reader.address_slice_len(chat_pointer, 4), 4).ok());
+    let raw_chat_pointer = opt_or!(read!(reader.address_slice_len(chat_pointer, 4), 4).ok(), return false);
     let chat_address = LittleEndian::read_u32(&raw_chat_pointer) as usize;
     let stop = self.stop;
-    let (tx, rx) = std::sync::mpsc::channel();
+    let pid = self.pid;
     self.run.store(true, Ordering::Relaxed);
     let run = self.run.clone();
     std::thread::spawn(move || {
       // Index of last read index
       let mut index_index = 0;
+      // Number of times new messages were found
+      let mut snapshot = 0;
       'main_loop: while run.load(Ordering::Relaxed) {
         // Get raw bytes for current index pointer
         let raw_pointer = try_or!(read!(reader.address_slice_len(index_pointer, 4), 4), break);
@@ -218,12 +283,20 @@ impl MemoryEntryReader {
         for index in new_indices {
           let read_size = *index as usize - last_index as usize;
           let message = try_or!(read!(reader.address_slice_len(chat_address + last_index as usize, read_size), read_size), break);
+          let provenance = Provenance::Memory {
+            pid,
+            snapshot,
+            offset: last_index
+          };
           last_index = *index;
-          try_or!(tx.send(message), break 'main_loop);
+          if !send(message, provenance) {
+            break 'main_loop;
+          }
         }
+        snapshot += 1;
       }
     });
-    Some(rx)
+    true
   }
 
   /// Stops the memory loop.
@@ -240,7 +313,7 @@ impl MemoryEntryReader {
   /// If `start` has been called but `stop` has not been called, the iterator returned will always
   /// return `None`.
   pub fn iter(&self) -> MemoryEntryReaderIterator {
-    MemoryEntryReaderIterator { rx: self.start() }
+    MemoryEntryReaderIterator { rx: self.start_traced() }
   }
 }
 
@@ -254,7 +327,7 @@ impl Drop for MemoryEntryReader {
 ///
 /// See [`MemoryEntryReader`](struct.MemoryEntryReader.html) for more information.
 pub struct MemoryEntryReaderIterator {
-  rx: Option<Receiver<Vec<u8>>>
+  rx: Option<Receiver<(Vec<u8>, Provenance)>>
 }
 
 impl Iterator for MemoryEntryReaderIterator {
@@ -265,13 +338,12 @@ impl Iterator for MemoryEntryReaderIterator {
       Some(ref r) => r,
       None => return None
     };
-    let bytes = match rx.recv() {
+    let (bytes, provenance) = match rx.recv() {
       Ok(b) => b,
       Err(_) => return None
     };
-    let raw = RawEntry::new(bytes);
-    let parts = opt!(raw.as_parts());
-    let entry = parts.as_entry();
+    let mut entry = opt!(RawEntry::new(bytes).as_entry());
+    entry.provenance = Some(provenance);
     Some(entry)
   }
 }
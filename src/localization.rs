@@ -0,0 +1,714 @@
+//! Localized system message templates
+//!
+//! The game words its system messages differently depending on the client language. Each
+//! [`Template`](struct.Template.html) describes one kind of message, with `{placeholders}` where the
+//! message varies. Matching a message against the templates gives back which template it was, which
+//! language it was in, and what filled the placeholders, so parsers built on top of this don't need
+//! to care about the client language.
+//!
+//! Only the English wording of messages is built in. Patterns for other languages are added to a
+//! [`Localizer`](struct.Localizer.html) with `add_pattern`, usually from the `[localization]`
+//! section of the [configuration](../config/index.html), and
+//! [`set_localizer`](fn.set_localizer.html) makes the rest of the crate use them.
+
+use messages::entries::Entry;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+
+/// A client language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+  English,
+  German,
+  French,
+  Japanese
+}
+
+impl Language {
+  /// Every language the game supports.
+  pub fn all() -> &'static [Language] {
+    static ALL: &'static [Language] = &[Language::English, Language::German, Language::French, Language::Japanese];
+    ALL
+  }
+
+  /// The two letter code for this language, as used by the auto-translate database.
+  pub fn code(&self) -> &'static str {
+    match *self {
+      Language::English => "en",
+      Language::German => "de",
+      Language::French => "fr",
+      Language::Japanese => "ja"
+    }
+  }
+
+  /// The language with the given two letter code.
+  pub fn from_code(code: &str) -> Option<Language> {
+    Language::all().iter().cloned().find(|x| x.code() == code)
+  }
+}
+
+/// A system message, as the English client words it.
+///
+/// Placeholders are written as `{name}`.
+#[derive(Debug, Clone)]
+pub struct Template {
+  /// The key identifying this template, such as `obtain_item`.
+  pub key: &'static str,
+  pub en: &'static str
+}
+
+impl Template {
+  /// Matches English text against this template.
+  ///
+  /// This parses the pattern every time. A [`Localizer`](struct.Localizer.html) parses its
+  /// templates once, so use one when matching many messages.
+  pub fn match_text(&self, text: &str) -> Option<TemplateMatch> {
+    match_parsed(self.key, Language::English, &Segment::parse(self.en), text)
+  }
+
+  /// Fills in the template in English.
+  ///
+  /// Placeholders without a value are left as they are.
+  pub fn render(&self, captures: &BTreeMap<String, String>) -> String {
+    render(&Segment::parse(self.en), captures)
+  }
+}
+
+/// The result of matching text against a template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateMatch {
+  /// The key of the template that matched.
+  pub key: &'static str,
+  /// The language the text was in.
+  pub language: Language,
+  /// The text that filled each placeholder.
+  pub captures: BTreeMap<String, String>
+}
+
+impl TemplateMatch {
+  /// Gets the text that filled a placeholder.
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.captures.get(name).map(|x| x.as_str())
+  }
+}
+
+/// A template with its patterns already parsed.
+#[derive(Debug, Clone)]
+struct ParsedTemplate {
+  template: Template,
+  /// The segments of the pattern for each language the template has one for.
+  patterns: BTreeMap<Language, Vec<Segment>>
+}
+
+impl ParsedTemplate {
+  fn new(template: Template) -> Self {
+    let mut patterns = BTreeMap::new();
+    patterns.insert(Language::English, Segment::parse(template.en));
+    ParsedTemplate {
+      template,
+      patterns
+    }
+  }
+
+  fn match_text(&self, text: &str) -> Option<TemplateMatch> {
+    self.patterns.iter()
+      .filter_map(|(l, segments)| match_parsed(self.template.key, *l, segments, text))
+      .next()
+  }
+}
+
+/// A set of templates that messages can be matched against.
+///
+/// Each pattern is parsed once, when it is added.
+#[derive(Debug, Clone)]
+pub struct Localizer {
+  templates: Vec<ParsedTemplate>
+}
+
+impl Localizer {
+  /// Create a localizer with the built-in templates.
+  pub fn new() -> Self {
+    Localizer {
+      templates: TEMPLATES.iter().cloned().map(ParsedTemplate::new).collect()
+    }
+  }
+
+  /// Create a localizer without any templates.
+  pub fn empty() -> Self {
+    Localizer {
+      templates: Vec::new()
+    }
+  }
+
+  /// Adds a template.
+  ///
+  /// Templates are tried in the order they were added, so more specific templates should be added
+  /// before more general ones.
+  pub fn add(&mut self, template: Template) {
+    self.templates.push(ParsedTemplate::new(template));
+  }
+
+  /// Adds the pattern for a language to the template with the given key, replacing any pattern it
+  /// already had for that language.
+  ///
+  /// The pattern must have the same placeholders as the English one, but they don't need to be in
+  /// the same order. Returns false without adding anything if there is no template with the key or
+  /// the placeholders differ.
+  pub fn add_pattern(&mut self, key: &str, language: Language, pattern: &str) -> bool {
+    let template = opt_or!(self.templates.iter_mut().find(|x| x.template.key == key), return false);
+    let segments = Segment::parse(pattern);
+    if placeholders(&segments) != placeholders(&Segment::parse(template.template.en)) {
+      return false;
+    }
+    template.patterns.insert(language, segments);
+    true
+  }
+
+  /// Gets a template by its key.
+  pub fn template(&self, key: &str) -> Option<&Template> {
+    self.find(key).map(|x| &x.template)
+  }
+
+  /// Matches text against every template, returning the first match.
+  pub fn match_text(&self, text: &str) -> Option<TemplateMatch> {
+    self.templates.iter().filter_map(|x| x.match_text(text)).next()
+  }
+
+  /// Matches text against the template with the given key.
+  pub fn match_key(&self, key: &str, text: &str) -> Option<TemplateMatch> {
+    opt!(self.find(key)).match_text(text)
+  }
+
+  /// Matches the plain text of an entry's message against every template.
+  pub fn match_entry(&self, entry: &Entry) -> Option<TemplateMatch> {
+    self.match_text(entry.message.plain_text().trim())
+  }
+
+  /// Renders a match in another language, if its template has a pattern for that language.
+  pub fn translate(&self, matched: &TemplateMatch, language: Language) -> Option<String> {
+    let template = opt!(self.find(matched.key));
+    Some(render(opt!(template.patterns.get(&language)), &matched.captures))
+  }
+
+  fn find(&self, key: &str) -> Option<&ParsedTemplate> {
+    self.templates.iter().find(|x| x.template.key == key)
+  }
+}
+
+impl Default for Localizer {
+  fn default() -> Self {
+    Localizer::new()
+  }
+}
+
+lazy_static! {
+  static ref LOCALIZER: RwLock<Localizer> = RwLock::new(Localizer::new());
+}
+
+/// Sets the localizer used everywhere in the crate, such as by the [events](../events/index.html)
+/// parsers.
+///
+/// Until this is called, only the built-in templates are used.
+pub fn set_localizer(localizer: Localizer) {
+  *LOCALIZER.write().unwrap() = localizer;
+}
+
+/// Matches text against the template with the given key, using the crate-wide localizer.
+pub fn match_key(key: &str, text: &str) -> Option<TemplateMatch> {
+  LOCALIZER.read().unwrap().match_key(key, text)
+}
+
+/// Matches the plain text of an entry's message against every template, using the crate-wide
+/// localizer.
+pub fn match_entry(entry: &Entry) -> Option<TemplateMatch> {
+  LOCALIZER.read().unwrap().match_entry(entry)
+}
+
+/// The built-in templates.
+///
+/// Templates sharing a prefix are ordered from most to least specific.
+pub static TEMPLATES: &'static [Template] = &[
+  Template {
+    key: "obtain_gil",
+    en: "You obtain {amount} gil."
+  },
+  Template {
+    key: "obtain_mgp",
+    en: "You obtain {amount} MGP."
+  },
+  Template {
+    key: "obtain_tomestones",
+    en: "You obtain {amount} Allagan tomestones of {kind}."
+  },
+  Template {
+    key: "obtain_item",
+    en: "You obtain {item}."
+  },
+  Template {
+    key: "gain_experience",
+    en: "You gain {amount} experience points."
+  },
+  Template {
+    key: "venture_assigned",
+    en: "{retainer} sets out on {venture}."
+  },
+  Template {
+    key: "venture_complete",
+    en: "{retainer} has completed the venture."
+  },
+  Template {
+    key: "fc_credits",
+    en: "You receive {amount} company credits."
+  },
+  Template {
+    key: "fc_rank_up",
+    en: "Your free company has reached rank {rank}!"
+  },
+  Template {
+    key: "fc_member_rank",
+    en: "{member} has been appointed {rank}."
+  },
+  Template {
+    key: "fc_member_joined",
+    en: "{name} has joined the free company."
+  },
+  Template {
+    key: "fc_member_left",
+    en: "{name} has left the free company."
+  },
+  Template {
+    key: "achievement_earned_self",
+    en: "You earn the achievement {achievement}."
+  },
+  Template {
+    key: "achievement_earned",
+    en: "{player} has earned the achievement {achievement}."
+  },
+  Template {
+    key: "level_up_self",
+    en: "You attain level {level}!"
+  },
+  Template {
+    key: "level_up_other",
+    en: "{player} attains level {level}!"
+  },
+  Template {
+    key: "job_change",
+    en: "You change to {job}."
+  },
+  Template {
+    key: "teleport",
+    en: "You teleport to {destination}."
+  },
+  Template {
+    key: "zone_entered",
+    en: "Now entering {zone}."
+  },
+  Template {
+    key: "sanctuary_entered",
+    en: "You have entered a sanctuary."
+  },
+  Template {
+    key: "sanctuary_left",
+    en: "You have left the sanctuary."
+  },
+  Template {
+    key: "jumbo_cactpot_purchased",
+    en: "You purchase a Jumbo Cactpot ticket with the number {number}."
+  },
+  Template {
+    key: "jumbo_cactpot_winning_number",
+    en: "The winning number for this week's Jumbo Cactpot is {number}."
+  },
+  Template {
+    key: "jumbo_cactpot_won",
+    en: "Your Jumbo Cactpot ticket {number} wins {amount} MGP!"
+  },
+  Template {
+    key: "gate_announced",
+    en: "The GATE “{gate}” will begin in {minutes} minutes."
+  },
+  Template {
+    key: "gate_started",
+    en: "The GATE “{gate}” has begun."
+  },
+  Template {
+    key: "party_invite_sent",
+    en: "You invite {player} to a party."
+  },
+  Template {
+    key: "party_invite_received",
+    en: "{player} invites you to a party."
+  },
+  Template {
+    key: "party_joined_self",
+    en: "You join {leader}'s party."
+  },
+  Template {
+    key: "party_joined",
+    en: "{player} joins the party."
+  },
+  Template {
+    key: "party_left_self",
+    en: "You leave the party."
+  },
+  Template {
+    key: "party_left",
+    en: "{player} has left the party."
+  },
+  Template {
+    key: "party_disbanded",
+    en: "The party has been disbanded."
+  },
+  Template {
+    key: "party_member_offline",
+    en: "{player} has gone offline."
+  },
+  Template {
+    key: "trade_request_sent",
+    en: "Trade request sent to {player}."
+  },
+  Template {
+    key: "trade_request_received",
+    en: "{player} wishes to trade with you."
+  },
+  Template {
+    key: "trade_canceled",
+    en: "Trade canceled."
+  },
+  Template {
+    key: "trade_complete",
+    en: "Trade complete."
+  },
+  Template {
+    key: "trade_gave_gil",
+    en: "You hand over {amount} gil."
+  },
+  Template {
+    key: "trade_gave_item",
+    en: "You hand over {item}."
+  },
+  Template {
+    key: "duty_commenced",
+    en: "{duty} has begun."
+  },
+  Template {
+    key: "duty_ended",
+    en: "{duty} has ended."
+  },
+  Template {
+    key: "roulette_bonus_tomestones",
+    en: "You receive a duty roulette bonus of {amount} Allagan tomestones of {kind}."
+  },
+  Template {
+    key: "roulette_bonus_exp",
+    en: "You receive a duty roulette bonus of {amount} experience points."
+  },
+  Template {
+    key: "roulette_bonus_gil",
+    en: "You receive a duty roulette bonus of {amount} gil."
+  },
+  Template {
+    key: "hit_critical_direct",
+    en: "Critical direct hit! {line}"
+  },
+  Template {
+    key: "hit_critical",
+    en: "Critical! {line}"
+  },
+  Template {
+    key: "hit_direct",
+    en: "Direct hit! {line}"
+  },
+  Template {
+    key: "battle_use_self",
+    en: "You use {action}."
+  },
+  Template {
+    key: "battle_use",
+    en: "{actor} uses {action}."
+  },
+  Template {
+    key: "battle_cast_self",
+    en: "You cast {action}."
+  },
+  Template {
+    key: "battle_cast",
+    en: "{actor} casts {action}."
+  },
+  Template {
+    key: "battle_damage_self",
+    en: "You take {amount} damage."
+  },
+  Template {
+    key: "battle_damage",
+    en: "{target} takes {amount} damage."
+  },
+  Template {
+    key: "battle_defeated_self",
+    en: "You are defeated by {actor}."
+  },
+  Template {
+    key: "battle_defeat_self",
+    en: "You defeat {target}."
+  },
+  Template {
+    key: "battle_defeated",
+    en: "{target} is defeated by {actor}."
+  },
+  Template {
+    key: "battle_defeat",
+    en: "{actor} defeats {target}."
+  }
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+  Literal(String),
+  Placeholder(String)
+}
+
+impl Segment {
+  fn parse(pattern: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rest = pattern;
+    while !rest.is_empty() {
+      let start = match rest.find('{') {
+        Some(s) => s,
+        None => {
+          segments.push(Segment::Literal(rest.to_owned()));
+          break;
+        }
+      };
+      let end = match rest[start..].find('}') {
+        Some(e) => start + e,
+        None => {
+          segments.push(Segment::Literal(rest.to_owned()));
+          break;
+        }
+      };
+      if start > 0 {
+        segments.push(Segment::Literal(rest[..start].to_owned()));
+      }
+      segments.push(Segment::Placeholder(rest[start + 1..end].to_owned()));
+      rest = &rest[end + 1..];
+    }
+    segments
+  }
+}
+
+fn placeholders(segments: &[Segment]) -> BTreeSet<&str> {
+  segments.iter()
+    .filter_map(|x| match *x {
+      Segment::Placeholder(ref p) => Some(p.as_str()),
+      Segment::Literal(_) => None
+    })
+    .collect()
+}
+
+fn render(segments: &[Segment], captures: &BTreeMap<String, String>) -> String {
+  segments.iter()
+    .map(|x| match *x {
+      Segment::Literal(ref l) => l.clone(),
+      Segment::Placeholder(ref p) => match captures.get(p) {
+        Some(v) => v.clone(),
+        None => format!("{{{}}}", p)
+      }
+    })
+    .collect()
+}
+
+fn match_parsed(key: &'static str, language: Language, segments: &[Segment], text: &str) -> Option<TemplateMatch> {
+  let mut captures = Vec::new();
+  if !match_segments(segments, text, &mut captures) {
+    return None;
+  }
+  Some(TemplateMatch {
+    key,
+    language,
+    captures: captures.into_iter().map(|(k, v)| (k.to_owned(), v.to_owned())).collect()
+  })
+}
+
+fn match_segments<'p, 't>(segments: &'p [Segment], text: &'t str, captures: &mut Vec<(&'p str, &'t str)>) -> bool {
+  let (first, rest) = match segments.split_first() {
+    Some(s) => s,
+    None => return text.is_empty()
+  };
+  match *first {
+    Segment::Literal(ref lit) => text.starts_with(lit.as_str()) && match_segments(rest, &text[lit.len()..], captures),
+    Segment::Placeholder(ref name) => {
+      // Placeholders can't be empty, and take as little text as they can.
+      let ends = text.char_indices().map(|(i, _)| i).skip(1).chain(Some(text.len())).filter(|e| *e > 0);
+      for end in ends {
+        captures.push((name, &text[..end]));
+        if match_segments(rest, &text[end..], captures) {
+          return true;
+        }
+        captures.pop();
+      }
+      false
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn template(pattern: &'static str) -> Template {
+    Template {
+      key: "test",
+      en: pattern
+    }
+  }
+
+  #[test]
+  fn parses_segments() {
+    assert_eq!(Segment::parse("You obtain {item}."), vec![
+      Segment::Literal(String::from("You obtain ")),
+      Segment::Placeholder(String::from("item")),
+      Segment::Literal(String::from("."))
+    ]);
+    assert_eq!(Segment::parse("{a}{b}"), vec![
+      Segment::Placeholder(String::from("a")),
+      Segment::Placeholder(String::from("b"))
+    ]);
+    // An unclosed brace is just text.
+    assert_eq!(Segment::parse("{a} {b"), vec![
+      Segment::Placeholder(String::from("a")),
+      Segment::Literal(String::from(" {b"))
+    ]);
+  }
+
+  #[test]
+  fn every_template_matches_its_sample() {
+    // One English line per built-in template, in the order of the templates. Each has to match its
+    // own template before any other, so this also checks that specific templates come first.
+    let samples = [
+      ("obtain_gil", "You obtain 1,500 gil."),
+      ("obtain_mgp", "You obtain 10 MGP."),
+      ("obtain_tomestones", "You obtain 20 Allagan tomestones of poetics."),
+      ("obtain_item", "You obtain a Hi-Potion."),
+      ("gain_experience", "You gain 3,482 experience points."),
+      ("venture_assigned", "Ann sets out on Quick Exploration."),
+      ("venture_complete", "Ann has completed the venture."),
+      ("fc_credits", "You receive 120 company credits."),
+      ("fc_rank_up", "Your free company has reached rank 8!"),
+      ("fc_member_rank", "Ann Example has been appointed Officer."),
+      ("fc_member_joined", "Ann Example has joined the free company."),
+      ("fc_member_left", "Ann Example has left the free company."),
+      ("achievement_earned_self", "You earn the achievement Going Green."),
+      ("achievement_earned", "Ann Example has earned the achievement Going Green."),
+      ("level_up_self", "You attain level 50!"),
+      ("level_up_other", "Ann Example attains level 50!"),
+      ("job_change", "You change to paladin."),
+      ("teleport", "You teleport to Ul'dah - Steps of Nald."),
+      ("zone_entered", "Now entering Western Thanalan."),
+      ("sanctuary_entered", "You have entered a sanctuary."),
+      ("sanctuary_left", "You have left the sanctuary."),
+      ("jumbo_cactpot_purchased", "You purchase a Jumbo Cactpot ticket with the number 1234."),
+      ("jumbo_cactpot_winning_number", "The winning number for this week's Jumbo Cactpot is 1234."),
+      ("jumbo_cactpot_won", "Your Jumbo Cactpot ticket 1234 wins 10,000 MGP!"),
+      ("gate_announced", "The GATE “Cliffhanger” will begin in 5 minutes."),
+      ("gate_started", "The GATE “Cliffhanger” has begun."),
+      ("party_invite_sent", "You invite Ann Example to a party."),
+      ("party_invite_received", "Ann Example invites you to a party."),
+      ("party_joined_self", "You join Ann Example's party."),
+      ("party_joined", "Ann Example joins the party."),
+      ("party_left_self", "You leave the party."),
+      ("party_left", "Ann Example has left the party."),
+      ("party_disbanded", "The party has been disbanded."),
+      ("party_member_offline", "Ann Example has gone offline."),
+      ("trade_request_sent", "Trade request sent to Ann Example."),
+      ("trade_request_received", "Ann Example wishes to trade with you."),
+      ("trade_canceled", "Trade canceled."),
+      ("trade_complete", "Trade complete."),
+      ("trade_gave_gil", "You hand over 5,000 gil."),
+      ("trade_gave_item", "You hand over a Hi-Potion."),
+      ("duty_commenced", "Sastasha has begun."),
+      ("duty_ended", "Sastasha has ended."),
+      ("roulette_bonus_tomestones", "You receive a duty roulette bonus of 100 Allagan tomestones of poetics."),
+      ("roulette_bonus_exp", "You receive a duty roulette bonus of 30,000 experience points."),
+      ("roulette_bonus_gil", "You receive a duty roulette bonus of 500 gil."),
+      ("hit_critical_direct", "Critical direct hit! The striking dummy takes 612 damage."),
+      ("hit_critical", "Critical! The striking dummy takes 450 damage."),
+      ("hit_direct", "Direct hit! The striking dummy takes 390 damage."),
+      ("battle_use_self", "You use Fast Blade."),
+      ("battle_use", "Ann Example uses Fast Blade."),
+      ("battle_cast_self", "You cast Cure."),
+      ("battle_cast", "Ann Example casts Cure."),
+      ("battle_damage_self", "You take 120 damage."),
+      ("battle_damage", "The striking dummy takes 312 damage."),
+      ("battle_defeated_self", "You are defeated by the goblin thug."),
+      ("battle_defeat_self", "You defeat the goblin thug."),
+      ("battle_defeated", "The goblin thug is defeated by Ann Example."),
+      ("battle_defeat", "Ann Example defeats the goblin thug.")
+    ];
+    let keys: Vec<&str> = TEMPLATES.iter().map(|x| x.key).collect();
+    let sampled: Vec<&str> = samples.iter().map(|x| x.0).collect();
+    assert_eq!(keys, sampled);
+    let localizer = Localizer::new();
+    for &(key, sample) in &samples {
+      let m = localizer.match_text(sample);
+      assert_eq!(m.as_ref().map(|x| x.key), Some(key), "{}", sample);
+      assert_eq!(m.map(|x| x.language), Some(Language::English));
+    }
+  }
+
+  #[test]
+  fn placeholders_backtrack_past_literals_they_contain() {
+    let m = Localizer::new().match_key("obtain_item", "You obtain a potion. Or two.").unwrap();
+    assert_eq!(m.get("item"), Some("a potion. Or two"));
+    assert_eq!(m.language, Language::English);
+  }
+
+  #[test]
+  fn placeholders_take_as_little_as_they_can() {
+    let text = "Ann sets out on Bob sets out on Quick Exploration.";
+    let m = Localizer::new().match_key("venture_assigned", text).unwrap();
+    assert_eq!(m.get("retainer"), Some("Ann"));
+    assert_eq!(m.get("venture"), Some("Bob sets out on Quick Exploration"));
+    let m = template("{a}{b}!").match_text("xyz!").unwrap();
+    assert_eq!(m.get("a"), Some("x"));
+    assert_eq!(m.get("b"), Some("yz"));
+  }
+
+  #[test]
+  fn placeholders_are_never_empty() {
+    assert!(Localizer::new().match_key("obtain_item", "You obtain .").is_none());
+    assert!(template("{a}{b}!").match_text("x!").is_none());
+    // Placeholders step over whole characters, not bytes.
+    let m = template("{amount}ギル").match_text("1,000ギル").unwrap();
+    assert_eq!(m.get("amount"), Some("1,000"));
+  }
+
+  #[test]
+  fn literals_must_match_the_whole_text() {
+    let localizer = Localizer::new();
+    assert!(localizer.match_key("obtain_item", "You obtain a potion").is_none());
+    assert!(localizer.match_key("obtain_item", "You obtain a potion. ").is_none());
+  }
+
+  #[test]
+  fn added_patterns_need_a_template_and_the_same_placeholders() {
+    let mut localizer = Localizer::new();
+    assert!(!localizer.add_pattern("no_such_template", Language::German, "{amount}"));
+    assert!(!localizer.add_pattern("obtain_gil", Language::German, "{count} Gil"));
+    assert!(!localizer.add_pattern("obtain_gil", Language::German, "{amount} {item}"));
+    assert!(localizer.add_pattern("obtain_gil", Language::German, "Gil: {amount}"));
+  }
+
+  #[test]
+  fn translates_matches() {
+    let mut localizer = Localizer::new();
+    assert!(localizer.add_pattern("obtain_gil", Language::German, "Gil: {amount}"));
+    let m = localizer.match_key("obtain_gil", "Gil: 500").unwrap();
+    assert_eq!(m.language, Language::German);
+    assert_eq!(localizer.translate(&m, Language::English), Some(String::from("You obtain 500 gil.")));
+    // There's no French pattern to render it in.
+    assert_eq!(localizer.translate(&m, Language::French), None);
+  }
+}
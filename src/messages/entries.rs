@@ -2,12 +2,17 @@
 
 use messages::Message;
 use messages::types::MessageType;
-use messages::parts::{Part, NamePart};
+use messages::parts::{Part, NamePart, PlainTextPart};
 use messages::parser::MessageParser;
-use messages::Parses;
+use messages::{Parses, HasDisplayText};
 
 use byteorder::{ByteOrder, LittleEndian};
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::mem;
+use std::path::PathBuf;
+
 /// A wrapper around the raw bytes of an entry.
 #[derive(Debug)]
 pub struct RawEntry {
@@ -24,39 +29,66 @@ impl RawEntry {
 
   /// Converts the bytes in their raw parts.
   ///
+  /// The parts returned own copies of their bytes. Use `as_parts_ref` to avoid the copies.
+  ///
+  /// If the bytes are invalid, this will return `None`.
+  pub fn as_parts(&self) -> Option<RawEntryParts<'static>> {
+    self.as_parts_ref().map(RawEntryParts::into_owned)
+  }
+
+  /// Converts the bytes in their raw parts, borrowing from this entry.
+  ///
   /// If the bytes are invalid, this will return `None`.
-  pub fn as_parts(&self) -> Option<RawEntryParts> {
+  pub fn as_parts_ref(&self) -> Option<RawEntryParts<'_>> {
     let header = opt!(self.get_header());
     let second_colon = opt!(self.bytes[9..].iter().position(|b| b == &0x3a));
-    let sender = self.bytes[9..second_colon + 9].to_vec();
-    let message = self.bytes[second_colon + 9 + 1..].to_vec();
+    let sender = &self.bytes[9..second_colon + 9];
+    let message = &self.bytes[second_colon + 9 + 1..];
     Some(RawEntryParts {
-      header,
-      sender,
-      message
+      header: Cow::Borrowed(header),
+      sender: Cow::Borrowed(sender),
+      message: Cow::Borrowed(message)
     })
   }
 
-  fn get_header(&self) -> Option<Vec<u8>> {
-    if self.bytes.len() < 8 {
+  /// Converts the bytes into a processed entry.
+  ///
+  /// If the bytes are invalid, this will return `None`.
+  pub fn as_entry(&self) -> Option<Entry> {
+    self.as_parts_ref().map(|parts| parts.as_entry())
+  }
+
+  fn get_header(&self) -> Option<&[u8]> {
+    if self.bytes.len() < 9 {
       return None;
     }
-    Some(self.bytes[..8].to_vec())
+    Some(&self.bytes[..8])
   }
 }
 
 /// The raw parts of an entry.
+///
+/// The parts may either borrow from a [`RawEntry`](struct.RawEntry.html) or own their bytes.
 #[derive(Debug)]
-pub struct RawEntryParts {
+pub struct RawEntryParts<'a> {
   /// The bytes for the header of the entry.
-  pub header: Vec<u8>,
+  pub header: Cow<'a, [u8]>,
   /// The bytes for the sender of the entry.
-  pub sender: Vec<u8>,
+  pub sender: Cow<'a, [u8]>,
   /// The bytes for the message of the entry.
-  pub message: Vec<u8>
+  pub message: Cow<'a, [u8]>
 }
 
-impl RawEntryParts {
+impl<'a> RawEntryParts<'a> {
+  /// Converts these parts into parts that own their bytes.
+  pub fn into_owned(self) -> RawEntryParts<'static> {
+    RawEntryParts {
+      header: Cow::Owned(self.header.into_owned()),
+      sender: Cow::Owned(self.sender.into_owned()),
+      message: Cow::Owned(self.message.into_owned())
+    }
+  }
+
   /// Converts the raw parts into a processed entry.
   pub fn as_entry(&self) -> Entry {
     let message_type = self.header[4];
@@ -65,25 +97,25 @@ impl RawEntryParts {
       None
     } else if let Some(part) = NamePart::parse(&self.sender) {
       Some(part)
-    } else if let Ok(name) = String::from_utf8(self.sender.clone()) {
-      Some(Part::PlainText(name))
-    } else if !self.sender.is_empty() {
-      Some(Part::Bytes(self.sender.clone()))
+    } else if let Ok(name) = ::std::str::from_utf8(&self.sender) {
+      Some(PlainTextPart::from_text(name))
     } else {
-      None
+      Some(Part::Bytes(self.sender.to_vec()))
     };
     let message = Message::new(MessageParser::parse(&self.message));
     Entry {
       message_type: message_type.into(),
       timestamp,
       sender,
-      message
+      message,
+      tags: BTreeMap::new(),
+      provenance: None
     }
   }
 }
 
 /// An entry from FFXIV's chat log.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
   /// The type of message this entry contains.
   pub message_type: MessageType,
@@ -92,5 +124,199 @@ pub struct Entry {
   /// The sender of the message, if any.
   pub sender: Option<Part>,
   /// The message of the entry.
-  pub message: Message
+  pub message: Message,
+  /// Tags attached to the entry after it was read, such as `spam` or `duty`.
+  ///
+  /// Tags without a value have an empty string as their value. Nothing is serialized when there are
+  /// no tags, so untagged entries look the same as they always have.
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub tags: BTreeMap<String, String>,
+  /// Where the entry was read from, if the reader recorded it.
+  ///
+  /// Readers only fill this in for entries that don't already have it, so entries copied between
+  /// files keep their original source. Nothing is serialized when it's missing.
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub provenance: Option<Provenance>
+}
+
+/// Where an entry was read from.
+///
+/// This is for auditing and debugging, like finding out why two sources disagree about an entry or
+/// where a duplicate came from. It isn't part of an entry's
+/// [`dedup_key`](struct.Entry.html#method.dedup_key).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Provenance {
+  /// Read from a file.
+  File {
+    path: PathBuf,
+    /// The byte offset of the entry in the file. For compressed files, this is the offset in the
+    /// decompressed data.
+    offset: u64
+  },
+  /// Read from the game's memory.
+  Memory {
+    pid: u32,
+    /// Counts each time the reader found new entries, starting at 0. Entries read at the same time
+    /// share a snapshot.
+    snapshot: u64,
+    /// The byte offset of the entry in the game's chat buffer.
+    offset: u32
+  },
+  /// Read from a packet capture log, like ACT's network log.
+  Packet {
+    path: PathBuf,
+    /// The line of the log the entry was on, starting at 1.
+    line: u64,
+    /// When the packet was captured, in milliseconds since the epoch. Entry timestamps are only
+    /// precise to the second.
+    captured: i64
+  }
+}
+
+/// Which way a tell went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TellDirection {
+  /// A tell someone sent to the player, including tells from GMs.
+  Incoming,
+  /// A tell the player sent.
+  Outgoing
+}
+
+impl TellDirection {
+  /// The direction of tells of a message type, if it's a tell.
+  pub fn of(message_type: MessageType) -> Option<TellDirection> {
+    match message_type {
+      MessageType::TellReceive | MessageType::GmTell => Some(TellDirection::Incoming),
+      MessageType::Tell => Some(TellDirection::Outgoing),
+      _ => None
+    }
+  }
+}
+
+impl Entry {
+  /// The real name of the sender, if there is a sender.
+  ///
+  /// Party number glyphs that the game puts in front of names in party chat are removed.
+  pub fn sender_name(&self) -> Option<String> {
+    let sender = opt!(self.sender.as_ref());
+    let name = match *sender {
+      Part::Name { ref real_name, .. } => real_name.display_text(),
+      ref other => other.display_text()
+    };
+    Some(name.trim_start_matches(|c| c >= '\u{e090}' && c <= '\u{e097}').to_owned())
+  }
+
+  /// Which way the tell in this entry went, if it's a tell.
+  pub fn tell_direction(&self) -> Option<TellDirection> {
+    TellDirection::of(self.message_type)
+  }
+
+  /// The real name of the other person in a tell, if this entry is one.
+  ///
+  /// The game puts the other person in the sender of both incoming and outgoing tells, so for an
+  /// outgoing tell this is the recipient, not the player. If the sender is missing, the first name in
+  /// the message is used instead.
+  pub fn tell_counterpart(&self) -> Option<String> {
+    self.tell_direction()?;
+    if let Some(name) = self.sender_name().filter(|x| !x.is_empty()) {
+      return Some(name);
+    }
+    self.message.parts.iter()
+      .filter_map(|x| match *x {
+        Part::Name { ref real_name, .. } => Some(real_name.display_text()),
+        _ => None
+      })
+      .next()
+  }
+
+  /// A key identifying this entry, for finding duplicates.
+  ///
+  /// Entries with the same timestamp, type, sender, and message text will have the same key. The key
+  /// is stable, so it can be stored and compared across runs.
+  pub fn dedup_key(&self) -> u64 {
+    // FNV-1a, since it's simple and doesn't change between Rust versions like the std hasher can.
+    fn hash(mut hash: u64, bytes: &[u8]) -> u64 {
+      for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+      }
+      hash
+    }
+    let mut timestamp = [0; 4];
+    LittleEndian::write_u32(&mut timestamp, self.timestamp);
+    let mut key = hash(0xcbf29ce484222325, &timestamp);
+    key = hash(key, &[self.message_type.id()]);
+    key = hash(key, self.sender.as_ref().map(|x| x.display_text()).unwrap_or_default().as_bytes());
+    key = hash(key, &[0]);
+    hash(key, self.message.display_text().as_bytes())
+  }
+
+  /// Attaches a tag, replacing any value it already had.
+  pub fn tag<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+    self.tags.insert(key.into(), value.into());
+  }
+
+  /// Gets the value of a tag.
+  pub fn get_tag(&self, key: &str) -> Option<&str> {
+    self.tags.get(key).map(|x| x.as_str())
+  }
+
+  /// Checks if the entry has a tag.
+  pub fn has_tag(&self, key: &str) -> bool {
+    self.tags.contains_key(key)
+  }
+
+  /// An estimate of the bytes this entry has allocated on the heap.
+  ///
+  /// This does not include the size of the entry itself, which is `mem::size_of::<Entry>()`.
+  pub fn estimated_heap_size(&self) -> usize {
+    let sender_size = self.sender.as_ref().map(|x| x.estimated_heap_size()).unwrap_or(0);
+    // B-tree nodes are rarely full, so each tag is counted as taking up about twice the space of its
+    // key and value in a node, plus a child pointer.
+    let tag_node_size = 2 * mem::size_of::<(String, String)>() + mem::size_of::<usize>();
+    let tags_size: usize = self.tags.iter()
+      .map(|(k, v)| tag_node_size + k.capacity() + v.capacity())
+      .sum();
+    let provenance_size = self.provenance.as_ref().map(|x| x.estimated_heap_size()).unwrap_or(0);
+    sender_size + self.message.estimated_heap_size() + tags_size + provenance_size
+  }
+}
+
+impl Provenance {
+  /// An estimate of the bytes this provenance has allocated on the heap.
+  pub fn estimated_heap_size(&self) -> usize {
+    match *self {
+      Provenance::File { ref path, .. } | Provenance::Packet { ref path, .. } => path.capacity(),
+      Provenance::Memory { .. } => 0
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry() -> Entry {
+    Entry {
+      message_type: MessageType::Say,
+      timestamp: 0,
+      sender: None,
+      message: Message::new(vec![PlainTextPart::from_text("hi")]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  #[test]
+  fn heap_size_counts_tags_and_provenance() {
+    let mut entry = entry();
+    let base = entry.estimated_heap_size();
+    entry.tag("", "");
+    assert!(entry.estimated_heap_size() > base);
+    let tagged = entry.estimated_heap_size();
+    entry.provenance = Some(Provenance::File { path: PathBuf::from("/logs/chat.jsonl"), offset: 0 });
+    assert!(entry.estimated_heap_size() >= tagged + "/logs/chat.jsonl".len());
+  }
 }
@@ -9,7 +9,9 @@ pub mod entries;
 pub use self::types::MessageType;
 use messages::parts::Part;
 
-#[derive(Debug, Serialize, Deserialize)]
+use std::mem;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
   pub parts: Vec<Part>
 }
@@ -20,6 +22,22 @@ impl Message {
       parts
     }
   }
+
+  /// Create an empty message with room for `capacity` parts.
+  pub fn with_capacity(capacity: usize) -> Self {
+    Message::new(Vec::with_capacity(capacity))
+  }
+
+  /// The text of this message without any icons or unknown bytes.
+  pub fn plain_text(&self) -> String {
+    self.parts.iter().map(|x| x.plain_text()).collect::<Vec<_>>().join("")
+  }
+
+  /// An estimate of the bytes this message has allocated on the heap.
+  pub fn estimated_heap_size(&self) -> usize {
+    let parts_size = self.parts.capacity() * mem::size_of::<Part>();
+    parts_size + self.parts.iter().map(|x| x.estimated_heap_size()).sum::<usize>()
+  }
 }
 
 impl HasDisplayText for Message {
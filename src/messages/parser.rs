@@ -35,7 +35,8 @@ pub struct MessageParser;
 impl MessageParser {
   pub fn parse(message: &[u8]) -> Vec<Part> {
     let mut parts: Vec<Part> = Vec::new();
-    let mut buf: Vec<u8> = Vec::new();
+    // Most messages are mostly plain text, so size the buffer for the whole message up front.
+    let mut buf: Vec<u8> = Vec::with_capacity(message.len());
     // FIXME: enumerate
     let mut i = 0;
     while i < message.len() {
@@ -1,6 +1,4 @@
-extern crate xz2;
-
-use self::xz2::read::XzDecoder;
+use xz2::read::XzDecoder;
 
 use messages::parts::Part;
 use messages::{Parses, DeterminesLength, VerifiesData, HasMarkerBytes};
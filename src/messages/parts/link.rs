@@ -0,0 +1,113 @@
+use messages::parts::{Part, MultiPart, NamePart};
+use messages::{Parses, HasMarkerBytes};
+use messages::parser::MessageParser;
+
+/// Links to things other than players and items.
+///
+/// These use the same marker as [`NamePart`](struct.NamePart.html), which hands them off to this
+/// part when it sees a kind of link it knows.
+pub struct LinkPart;
+
+impl LinkPart {
+  /// The kind of link for achievements.
+  pub const ACHIEVEMENT: u8 = 0x06;
+
+  pub fn from_parts(kind: u8, id: u32, display: Part) -> Part {
+    Part::Link {
+      kind,
+      id,
+      display: Box::new(display)
+    }
+  }
+
+  /// Checks if the bytes are a link that should be parsed by this part.
+  pub fn is_link(bytes: &[u8]) -> bool {
+    let (two, marker) = NamePart::marker_bytes();
+    bytes.len() > 4
+      && bytes[0] == two
+      && bytes[1] == marker
+      && bytes[3] == LinkPart::ACHIEVEMENT
+      && bytes[2] as usize + 4 < bytes.len()
+  }
+
+  /// Reads one of the game's packed integers, returning it and the number of bytes it took up.
+  ///
+  /// Small values are stored in one byte, plus one. Larger values have a marker byte saying which of
+  /// the following bytes are present, from most to least significant.
+  fn read_packed_int(bytes: &[u8]) -> Option<(u32, usize)> {
+    let marker = *opt!(bytes.first());
+    if marker < 0xf0 {
+      return Some(((marker as u32).saturating_sub(1), 1));
+    }
+    let flags = marker.wrapping_add(1) & 0x0f;
+    let mut value = 0;
+    let mut used = 1;
+    for i in (0..4).rev() {
+      if flags & (1 << i) == 0 {
+        continue;
+      }
+      value |= (*opt!(bytes.get(used)) as u32) << (8 * i);
+      used += 1;
+    }
+    Some((value, used))
+  }
+}
+
+impl Parses for LinkPart {
+  fn parse(bytes: &[u8]) -> Option<Part> {
+    if !LinkPart::is_link(bytes) {
+      return None;
+    }
+    let marker = NamePart::marker_bytes();
+    let kind = bytes[3];
+    let (id, _) = opt!(LinkPart::read_packed_int(&bytes[4..]));
+    let header_length = bytes[2] as usize + 4;
+    let display_end = opt!(bytes[header_length..].windows(2).position(|w| w == [marker.0, marker.1])) + header_length;
+    if display_end <= header_length {
+      return None;
+    }
+    let display_bytes = &bytes[header_length + 1 .. display_end];
+    let mut parts = MessageParser::parse(display_bytes);
+    let display = if parts.len() == 1 {
+      parts.remove(0)
+    } else if parts.len() > 1 {
+      MultiPart::from_parts(parts)
+    } else {
+      Part::PlainText(String::new())
+    };
+    Some(LinkPart::from_parts(kind, id, display))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_achievement_links() {
+    let bytes = [0x02, 0x27, 0x02, 0x06, 0x0b, 0x03, 0x01, b'H', b'i', 0x02, 0x27, 0x07, 0x03];
+    match LinkPart::parse(&bytes) {
+      Some(Part::Link { kind, id, display }) => {
+        assert_eq!(kind, LinkPart::ACHIEVEMENT);
+        assert_eq!(id, 10);
+        assert_eq!(display.plain_text(), "Hi");
+      },
+      other => panic!("expected a link, got {:?}", other)
+    }
+  }
+
+  #[test]
+  fn rejects_malformed_links() {
+    // The display text ends where the header does.
+    assert!(LinkPart::parse(&[0x02, 0x27, 0x02, 0x06, 0x0b, 0x03, 0x02, 0x27, 0x03]).is_none());
+    // The header is longer than the bytes.
+    assert!(!LinkPart::is_link(&[0x02, 0x27, 0xff, 0x06, 0x0b, 0x03]));
+    // The marker is wrong.
+    assert!(!LinkPart::is_link(&[0x02, 0x28, 0x02, 0x06, 0x0b, 0x03, 0x01, b'H', 0x02, 0x27]));
+    // Every prefix of a valid link either parses or is rejected without panicking.
+    let bytes = [0x02, 0x27, 0x02, 0x06, 0x0b, 0x03, 0x01, b'H', b'i', 0x02, 0x27, 0x07, 0x03];
+    for end in 0..bytes.len() {
+      LinkPart::parse(&bytes[..end]);
+    }
+  }
+}
@@ -6,6 +6,7 @@ mod colored;
 mod formatted;
 mod percentage;
 mod icon;
+mod link;
 
 pub use self::name::NamePart;
 pub use self::autotranslate::AutoTranslatePart;
@@ -15,11 +16,14 @@ pub use self::colored::ColoredPart;
 pub use self::formatted::FormattedPart;
 pub use self::percentage::PercentagePart;
 pub use self::icon::IconPart;
+pub use self::link::LinkPart;
 
 use messages::HasDisplayText;
 
+use std::mem;
+
 /// Parts of a message.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Part {
   /// A name, which is composed of a real name and a display name.
   #[serde(rename = "name")]
@@ -93,7 +97,60 @@ pub enum Part {
   ///
   /// Some icons use this structure, some are UTF-8 glyphs.
   #[serde(rename = "icon")]
-  Icon(u64)
+  Icon(u64),
+
+  /// A link to something in the game, such as an achievement.
+  ///
+  /// Links to players are `Name` parts instead, for now.
+  #[serde(rename = "link")]
+  Link {
+    /// The kind of link. See the constants on [`LinkPart`](struct.LinkPart.html).
+    kind: u8,
+    /// The ID of the thing being linked to.
+    id: u32,
+    /// The part displayed for the link.
+    display: Box<Part>
+  }
+}
+
+impl Part {
+  /// The text of this part without any icons or unknown bytes.
+  ///
+  /// This is the display text with everything that isn't actually text left out, which makes it
+  /// better for matching against known messages.
+  pub fn plain_text(&self) -> String {
+    match *self {
+      Part::Name { ref display_name, .. } => display_name.plain_text(),
+      Part::Colored { ref display, .. }
+        | Part::Formatted { ref display, .. }
+        | Part::Link { ref display, .. } => display.plain_text(),
+      Part::Multi(ref parts) => parts.iter().map(|x| x.plain_text()).collect::<Vec<_>>().join(""),
+      Part::Bytes(_) | Part::Icon(_) => String::new(),
+      _ => self.display_text()
+    }
+  }
+
+  /// An estimate of the bytes this part has allocated on the heap.
+  pub fn estimated_heap_size(&self) -> usize {
+    let boxed = mem::size_of::<Part>();
+    match *self {
+      Part::Name { ref real_name, ref display_name } => {
+        2 * boxed + real_name.estimated_heap_size() + display_name.estimated_heap_size()
+      },
+      Part::Colored { ref info, ref display }
+        | Part::Formatted { ref info, ref display } => info.capacity() + boxed + display.estimated_heap_size(),
+      Part::Multi(ref parts) => {
+        let parts_size = parts.capacity() * mem::size_of::<Box<Part>>();
+        parts_size + parts.iter().map(|x| boxed + x.estimated_heap_size()).sum::<usize>()
+      },
+      Part::Link { ref display, .. } => boxed + display.estimated_heap_size(),
+      Part::PlainText(ref text) => text.capacity(),
+      Part::Bytes(ref bytes) => bytes.capacity(),
+      Part::AutoTranslate { .. }
+        | Part::Percentage(_)
+        | Part::Icon(_) => 0
+    }
+  }
 }
 
 impl HasDisplayText for Part {
@@ -109,7 +166,8 @@ impl HasDisplayText for Part {
       },
       Part::Bytes(ref bytes) => bytes.iter().map(|x| format!("{:02X}", x)).collect::<Vec<_>>().join(" "),
       Part::Colored { ref display, .. }
-        | Part::Formatted { ref display, .. } => display.display_text(),
+        | Part::Formatted { ref display, .. }
+        | Part::Link { ref display, .. } => display.display_text(),
       Part::Multi(ref parts) => parts.iter().map(|x| x.display_text()).collect::<Vec<_>>().join(""),
       Part::Percentage(_) => String::from(" "),
       Part::Icon(id) => format!("<Icon: {}>", id)
@@ -1,4 +1,4 @@
-use messages::parts::{Part, MultiPart};
+use messages::parts::{Part, MultiPart, LinkPart};
 use messages::{Parses, DeterminesLength, VerifiesData, HasMarkerBytes};
 use messages::parser::MessageParser;
 
@@ -56,6 +56,9 @@ impl DeterminesLength for NamePart {
 
 impl Parses for NamePart {
   fn parse(bytes: &[u8]) -> Option<Part> {
+    if LinkPart::is_link(bytes) {
+      return LinkPart::parse(bytes);
+    }
     if !NamePart::verify_data(bytes) {
       return None;
     }
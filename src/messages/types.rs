@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::fmt::Result as FmtResult;
+use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum MessageType {
   None,
@@ -90,6 +92,138 @@ impl Display for MessageType {
   }
 }
 
+impl MessageType {
+  /// The numeric ID of this type, as used by the game.
+  pub fn id(&self) -> u8 {
+    // This mirrors From<u8>.
+    match *self {
+      MessageType::None => 0,
+      MessageType::Debug => 1,
+      MessageType::UrgentInformation => 2,
+      MessageType::GeneralInformation => 3,
+      MessageType::Say => 10,
+      MessageType::Shout => 11,
+      MessageType::Tell => 12,
+      MessageType::TellReceive => 13,
+      MessageType::Party => 14,
+      MessageType::Alliance => 15,
+      MessageType::Linkshell1 => 16,
+      MessageType::Linkshell2 => 17,
+      MessageType::Linkshell3 => 18,
+      MessageType::Linkshell4 => 19,
+      MessageType::Linkshell5 => 20,
+      MessageType::Linkshell6 => 21,
+      MessageType::Linkshell7 => 22,
+      MessageType::Linkshell8 => 23,
+      MessageType::FreeCompany => 24,
+      MessageType::NoviceNetwork => 27,
+      MessageType::CustomEmotes => 28,
+      MessageType::StandardEmotes => 29,
+      MessageType::Yell => 30,
+      MessageType::Party2 => 32,
+      MessageType::Damage => 41,
+      MessageType::FailedAttacks => 42,
+      MessageType::Actions => 43,
+      MessageType::Items => 44,
+      MessageType::HealingMagic => 45,
+      MessageType::BeneficialEffects => 46,
+      MessageType::DetrimentalEffects => 47,
+      MessageType::Echo => 56,
+      MessageType::SystemMessages => 57,
+      MessageType::BattleSystemMessages => 58,
+      MessageType::GatheringSystemMessages => 59,
+      MessageType::SystemErrorMessages => 60,
+      MessageType::NpcSay => 61,
+      MessageType::LootNotices => 62,
+      MessageType::CharacterProgress => 64,
+      MessageType::LootMessages => 65,
+      MessageType::CraftingMessages => 66,
+      MessageType::GatheringMessages => 67,
+      MessageType::NpcAnnouncements => 68,
+      MessageType::FcAnnouncements => 69,
+      MessageType::FcLoginMessages => 70,
+      MessageType::RetainerSaleReports => 71,
+      MessageType::PartySearchInfo => 72,
+      MessageType::SignSettings => 73,
+      MessageType::DiceRolls => 74,
+      MessageType::NoviceNetworkNotifications => 75,
+      MessageType::MusicChange => 76,
+      MessageType::GmTell => 80,
+      MessageType::GmSay => 81,
+      MessageType::GmShout => 82,
+      MessageType::GmYell => 83,
+      MessageType::GmParty => 84,
+      MessageType::GmFreeCompany => 85,
+      MessageType::GmLinkshell1 => 86,
+      MessageType::GmLinkshell2 => 87,
+      MessageType::GmLinkshell3 => 88,
+      MessageType::GmLinkshell4 => 89,
+      MessageType::GmLinkshell5 => 90,
+      MessageType::GmLinkshell6 => 91,
+      MessageType::GmLinkshell7 => 92,
+      MessageType::GmLinkshell8 => 93,
+      MessageType::BattleReceiveDamage => 169,
+      MessageType::BattleResistDebuff => 170,
+      MessageType::BattleCast => 171,
+      MessageType::ReadyItem => 172,
+      MessageType::BattleGainBuff => 174,
+      MessageType::BattleSelfAbsorb => 173,
+      MessageType::BattleSufferDebuff => 175,
+      MessageType::BattleLoseBuff => 176,
+      MessageType::BattleRecoverDebuff => 177,
+      MessageType::TrialUpdate => 185,
+      MessageType::BattleDeath => 186,
+      MessageType::GainMgp => 190,
+      MessageType::Unknown(id) => id
+    }
+  }
+
+  /// The snake case name of this type, as used when serializing.
+  pub fn snake_case_name(&self) -> String {
+    let name = self.to_string();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+      if c.is_uppercase() {
+        if i != 0 {
+          snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+      } else {
+        snake.push(c);
+      }
+    }
+    snake
+  }
+}
+
+impl FromStr for MessageType {
+  type Err = ();
+
+  /// Parses a message type from its name (`FreeCompany` or `free_company`) or its numeric ID.
+  fn from_str(s: &str) -> Result<MessageType, ()> {
+    if let Ok(id) = s.parse::<u8>() {
+      return Ok(MessageType::from(id));
+    }
+    NAMES.get(s).cloned().ok_or(())
+  }
+}
+
+lazy_static! {
+  /// Every known message type by its name and its snake case name.
+  static ref NAMES: HashMap<String, MessageType> = {
+    let mut names = HashMap::new();
+    for id in 0..256u16 {
+      let message_type = MessageType::from(id as u8);
+      if let MessageType::Unknown(_) = message_type {
+        continue;
+      }
+      names.insert(message_type.to_string(), message_type);
+      names.insert(message_type.snake_case_name(), message_type);
+    }
+    names
+  };
+}
+
 impl From<u8> for MessageType {
   fn from(u: u8) -> MessageType {
     match u {
@@ -174,3 +308,26 @@ impl From<u8> for MessageType {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ids_round_trip() {
+    for id in 0..256u16 {
+      assert_eq!(MessageType::from(id as u8).id(), id as u8);
+    }
+  }
+
+  #[test]
+  fn parses_names_and_ids() {
+    assert_eq!("FreeCompany".parse(), Ok(MessageType::FreeCompany));
+    assert_eq!("free_company".parse(), Ok(MessageType::FreeCompany));
+    assert_eq!("linkshell1".parse(), Ok(MessageType::Linkshell1));
+    assert_eq!("24".parse(), Ok(MessageType::FreeCompany));
+    assert_eq!("200".parse(), Ok(MessageType::Unknown(200)));
+    assert_eq!("unknown".parse::<MessageType>(), Err(()));
+    assert_eq!("Unknown(200)".parse::<MessageType>(), Err(()));
+  }
+}
@@ -0,0 +1,368 @@
+//! Character names
+//!
+//! # Display policy
+//! A [`NameDisplayPolicy`](struct.NameDisplayPolicy.html) controls how names are shown by the
+//! [renderers](../render/index.html) and the text outputs, so names can be hidden everywhere at
+//! once, such as while streaming. The policy is crate-wide and set with
+//! [`set_display_policy`](fn.set_display_policy.html). It only changes how names are shown:
+//! `display_text`, dedup keys, and serialized entries always use the names as logged. Use
+//! [`message_display_text`](fn.message_display_text.html) to show a message with the policy, and a
+//! [`Redactor`](../redact/struct.Redactor.html) to remove names from files.
+//!
+//! # Renames
+//! The game doesn't log renames, so the only way to tell that a new name belongs to someone already
+//! seen is by who they talk with. [`RenameLinker`](struct.RenameLinker.html) watches who speaks
+//! near whom in group channels (Free Company, linkshells, and party) and links an old name to a
+//! new one when the old name stops being seen, the new one shows up soon after, and both spoke with
+//! mostly the same people.
+
+use messages::{Message, MessageType, HasDisplayText};
+use messages::entries::Entry;
+use messages::parts::Part;
+use redact;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::RwLock;
+
+/// The glyph the game puts between a name and the world of someone from another world.
+pub const CROSS_WORLD_GLYPH: char = '\u{e05d}';
+
+lazy_static! {
+  static ref DISPLAY_POLICY: RwLock<NameDisplayPolicy> = RwLock::new(NameDisplayPolicy::default());
+}
+
+/// Sets how names are shown everywhere in the crate.
+pub fn set_display_policy(policy: NameDisplayPolicy) {
+  *DISPLAY_POLICY.write().unwrap() = policy;
+}
+
+/// The current crate-wide display policy.
+pub fn display_policy() -> NameDisplayPolicy {
+  DISPLAY_POLICY.read().unwrap().clone()
+}
+
+/// How a name is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NameStyle {
+  /// The name as the game displays it. This is the default.
+  Display,
+  /// The real name of the character, even if the game displays something else.
+  Real,
+  /// The initials of the real name, like `S. N.`.
+  Initials,
+  /// A pseudonym made from the real name, which is the same every time, like `Player 1a2b3c`.
+  Pseudonym
+}
+
+impl Default for NameStyle {
+  fn default() -> Self {
+    NameStyle::Display
+  }
+}
+
+/// How names should be shown.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NameDisplayPolicy {
+  #[serde(default)]
+  pub style: NameStyle,
+  /// Whether to leave off the worlds of people from other worlds.
+  #[serde(default)]
+  pub strip_world: bool
+}
+
+impl NameDisplayPolicy {
+  /// Checks if names are shown as the game shows them.
+  pub fn is_default(&self) -> bool {
+    self.style == NameStyle::Display && !self.strip_world
+  }
+
+  /// Formats a name from its real and displayed text.
+  ///
+  /// Party number glyphs in front of the displayed name are kept.
+  pub fn format(&self, real_name: &str, display_name: &str) -> String {
+    let is_party_glyph = |c| c >= '\u{e090}' && c <= '\u{e097}';
+    let name = display_name.trim_start_matches(is_party_glyph);
+    let prefix = &display_name[..display_name.len() - name.len()];
+    let name = match self.style {
+      NameStyle::Display => name,
+      _ => real_name.trim_start_matches(is_party_glyph)
+    };
+    let name = if self.strip_world {
+      name.split(|c| c == CROSS_WORLD_GLYPH || c == '@').next().unwrap_or(name)
+    } else {
+      name
+    };
+    let name = match self.style {
+      NameStyle::Display | NameStyle::Real => name.to_owned(),
+      NameStyle::Initials => name.split_whitespace()
+        .filter_map(|x| x.chars().next())
+        .map(|c| format!("{}.", c))
+        .collect::<Vec<_>>()
+        .join(" "),
+      NameStyle::Pseudonym => redact::stable_pseudonym(name)
+    };
+    format!("{}{}", prefix, name)
+  }
+
+  /// Formats a name part.
+  pub fn format_part(&self, real_name: &Part, display_name: &Part) -> String {
+    if self.is_default() {
+      return display_name.display_text();
+    }
+    self.format(&real_name.display_text(), &display_name.display_text())
+  }
+
+  /// Formats the sender of an entry.
+  ///
+  /// The game doesn't link the player's own name, so plain text senders are formatted as names
+  /// too.
+  pub fn format_sender(&self, sender: &Part) -> String {
+    match *sender {
+      Part::Name { ref real_name, ref display_name } => self.format_part(real_name, display_name),
+      Part::PlainText(ref name) if !self.is_default() => self.format(name, name),
+      ref other => other.display_text()
+    }
+  }
+
+  /// Formats a real name, such as one from `Entry::sender_name`.
+  pub fn format_name(&self, name: &str) -> String {
+    if self.is_default() {
+      return name.to_owned();
+    }
+    self.format(name, name)
+  }
+
+  /// The plain text of a part, with any names in it formatted.
+  pub fn format_plain_text(&self, part: &Part) -> String {
+    if self.is_default() {
+      return part.plain_text();
+    }
+    match *part {
+      Part::Name { ref real_name, ref display_name } => self.format(&real_name.plain_text(), &display_name.plain_text()),
+      Part::Colored { ref display, .. }
+        | Part::Formatted { ref display, .. }
+        | Part::Link { ref display, .. } => self.format_plain_text(display),
+      Part::Multi(ref parts) => parts.iter().map(|x| self.format_plain_text(x)).collect(),
+      ref other => other.plain_text()
+    }
+  }
+
+  /// The display text of a part, with any names in it formatted.
+  pub fn format_text(&self, part: &Part) -> String {
+    if self.is_default() {
+      return part.display_text();
+    }
+    match *part {
+      Part::Name { ref real_name, ref display_name } => self.format_part(real_name, display_name),
+      Part::Colored { ref display, .. }
+        | Part::Formatted { ref display, .. }
+        | Part::Link { ref display, .. } => self.format_text(display),
+      Part::Multi(ref parts) => parts.iter().map(|x| self.format_text(x)).collect(),
+      ref other => other.display_text()
+    }
+  }
+}
+
+/// Formats the sender of an entry with the crate-wide policy.
+pub fn sender_display_text(sender: &Part) -> String {
+  DISPLAY_POLICY.read().unwrap().format_sender(sender)
+}
+
+/// Formats a real name with the crate-wide policy.
+pub fn name_display_text(name: &str) -> String {
+  DISPLAY_POLICY.read().unwrap().format_name(name)
+}
+
+/// The plain text of a message, with names formatted by the crate-wide policy.
+pub fn message_plain_text(message: &Message) -> String {
+  let policy = DISPLAY_POLICY.read().unwrap();
+  message.parts.iter().map(|x| policy.format_plain_text(x)).collect()
+}
+
+/// The display text of a message, with names formatted by the crate-wide policy.
+pub fn message_display_text(message: &Message) -> String {
+  let policy = DISPLAY_POLICY.read().unwrap();
+  message.parts.iter().map(|x| policy.format_text(x)).collect()
+}
+
+/// How close together two messages in a channel must be for their senders to count as talking with
+/// each other, in seconds.
+const CONVERSATION_WINDOW: u32 = 5 * 60;
+
+/// A link from an old name to a new one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameLink {
+  /// The name that stopped being seen.
+  pub old_name: String,
+  /// The name that showed up in its place.
+  pub new_name: String,
+  /// How alike the two names' conversation partners were, from `0.0` to `1.0`.
+  pub score: f64
+}
+
+#[derive(Debug, Default)]
+struct Activity {
+  first_seen: u32,
+  last_seen: u32,
+  messages: usize,
+  partners: HashMap<String, usize>
+}
+
+/// Links character names that probably belong to the same character.
+#[derive(Debug)]
+pub struct RenameLinker {
+  /// The longest gap allowed between the last sighting of an old name and the first of a new one.
+  pub max_gap: u32,
+  /// The fewest messages a name needs before it can be linked.
+  pub min_messages: usize,
+  names: HashMap<String, Activity>,
+  recent: HashMap<MessageType, VecDeque<(u32, String)>>
+}
+
+impl RenameLinker {
+  /// Create a new linker allowing a gap of up to 30 days and needing 20 messages per name.
+  pub fn new() -> Self {
+    RenameLinker {
+      max_gap: 30 * 86400,
+      min_messages: 20,
+      names: HashMap::new(),
+      recent: HashMap::new()
+    }
+  }
+
+  /// Looks at the next entry. Entries should be given in order.
+  pub fn observe(&mut self, entry: &Entry) {
+    if !is_group_channel(entry.message_type) {
+      return;
+    }
+    let name = opt_or!(entry.sender_name(), return);
+    if name.is_empty() {
+      return;
+    }
+
+    let recent = self.recent.entry(entry.message_type).or_insert_with(VecDeque::new);
+    while recent.front().map(|x| x.0.saturating_add(CONVERSATION_WINDOW) < entry.timestamp).unwrap_or(false) {
+      recent.pop_front();
+    }
+    let partners: HashSet<String> = recent.iter()
+      .map(|x| x.1.clone())
+      .filter(|x| *x != name)
+      .collect();
+    recent.push_back((entry.timestamp, name.clone()));
+
+    for partner in &partners {
+      if let Some(activity) = self.names.get_mut(partner) {
+        *activity.partners.entry(name.clone()).or_insert(0) += 1;
+      }
+    }
+    let activity = self.names.entry(name).or_insert_with(|| Activity {
+      first_seen: entry.timestamp,
+      ..Activity::default()
+    });
+    activity.last_seen = entry.timestamp;
+    activity.messages += 1;
+    for partner in partners {
+      *activity.partners.entry(partner).or_insert(0) += 1;
+    }
+  }
+
+  /// Finds links scoring at least `threshold`, best first.
+  ///
+  /// Each name is linked at most once in each direction.
+  pub fn links(&self, threshold: f64) -> Vec<NameLink> {
+    let candidates: Vec<(&String, &Activity)> = self.names.iter()
+      .filter(|x| x.1.messages >= self.min_messages)
+      .collect();
+    let mut links = Vec::new();
+    for &(old_name, old) in &candidates {
+      for &(new_name, new) in &candidates {
+        if new.first_seen <= old.last_seen || new.first_seen - old.last_seen > self.max_gap {
+          continue;
+        }
+        let score = similarity(&old.partners, &new.partners, old_name, new_name);
+        if score >= threshold {
+          links.push(NameLink {
+            old_name: old_name.clone(),
+            new_name: new_name.clone(),
+            score
+          });
+        }
+      }
+    }
+    links.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(::std::cmp::Ordering::Equal));
+    let mut old_used = HashSet::new();
+    let mut new_used = HashSet::new();
+    links.retain(|x| {
+      if old_used.contains(&x.old_name) || new_used.contains(&x.new_name) {
+        return false;
+      }
+      old_used.insert(x.old_name.clone());
+      new_used.insert(x.new_name.clone());
+      true
+    });
+    links
+  }
+
+  /// Maps every linked old name to the latest name it was linked to.
+  pub fn canonical_names(&self, threshold: f64) -> HashMap<String, String> {
+    let links: HashMap<String, String> = self.links(threshold).into_iter()
+      .map(|x| (x.old_name, x.new_name))
+      .collect();
+    let mut canonical = HashMap::new();
+    for old in links.keys() {
+      let mut current = old;
+      let mut seen = HashSet::new();
+      while let Some(next) = links.get(current) {
+        if !seen.insert(next) {
+          break;
+        }
+        current = next;
+      }
+      canonical.insert(old.clone(), current.clone());
+    }
+    canonical
+  }
+}
+
+impl Default for RenameLinker {
+  fn default() -> Self {
+    RenameLinker::new()
+  }
+}
+
+fn is_group_channel(message_type: MessageType) -> bool {
+  match message_type {
+    MessageType::FreeCompany
+      | MessageType::Party
+      | MessageType::Linkshell1
+      | MessageType::Linkshell2
+      | MessageType::Linkshell3
+      | MessageType::Linkshell4
+      | MessageType::Linkshell5
+      | MessageType::Linkshell6
+      | MessageType::Linkshell7
+      | MessageType::Linkshell8 => true,
+    _ => false
+  }
+}
+
+/// The cosine similarity of two partner counts, ignoring the two names themselves.
+fn similarity(a: &HashMap<String, usize>, b: &HashMap<String, usize>, a_name: &str, b_name: &str) -> f64 {
+  let ignored = |x: &String| x == a_name || x == b_name;
+  let dot: f64 = a.iter()
+    .filter(|x| !ignored(x.0))
+    .filter_map(|(k, v)| b.get(k).map(|w| (*v * *w) as f64))
+    .sum();
+  let norm = |m: &HashMap<String, usize>| m.iter()
+    .filter(|x| !ignored(x.0))
+    .map(|x| (*x.1 * *x.1) as f64)
+    .sum::<f64>()
+    .sqrt();
+  let denominator = norm(a) * norm(b);
+  if denominator == 0.0 {
+    0.0
+  } else {
+    dot / denominator
+  }
+}
@@ -0,0 +1,393 @@
+//! A multi-threaded pipeline from a reader to sinks.
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use filter::EntryFilter;
+use messages::entries::{Entry, Provenance, RawEntry};
+
+/// A destination for entries leaving a [`Pipeline`](struct.Pipeline.html).
+///
+/// Each sink runs on its own thread, so a slow sink only holds up itself.
+pub trait Sink: Send {
+  /// Handles one entry.
+  fn handle(&mut self, entry: &Entry);
+
+  /// Flushes any buffered output.
+  ///
+  /// This is called once the pipeline has no more entries for the sink.
+  fn flush(&mut self) {}
+
+  /// Writes out any buffered output without ending the stream.
+  ///
+  /// The pipeline calls this every [sync interval](struct.Pipeline.html#method.set_sync_interval),
+  /// and entries the sink has handled only count as [synced](struct.Pipeline.html#method.synced)
+  /// once it returns. Sinks that buffer entries should write them out here.
+  fn sync(&mut self) {}
+}
+
+impl<S: Sink + ?Sized> Sink for Box<S> {
+  fn handle(&mut self, entry: &Entry) {
+    (**self).handle(entry)
+  }
+
+  fn flush(&mut self) {
+    (**self).flush()
+  }
+
+  fn sync(&mut self) {
+    (**self).sync()
+  }
+}
+
+/// What to do when a sink's channel is full.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backpressure {
+  /// Wait until the sink has room. A slow sink will eventually slow every other sink down.
+  Block,
+  /// Drop the entry for the full sink only. Dropped entries are counted in
+  /// [`Pipeline::dropped`](struct.Pipeline.html#method.dropped), and still count as
+  /// [synced](struct.Pipeline.html#method.synced) once a later entry is.
+  DropNewest
+}
+
+/// A pipeline that parses entries on a pool of workers and fans them out to sinks.
+///
+/// The stages are connected with bounded channels of `capacity` entries. Entries reach every sink
+/// in the order they were captured, no matter which worker parsed them.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let reader = MemoryEntryReader::new(my_pid, false);
+/// let mut pipeline = Pipeline::new(4, 256, Backpressure::DropNewest);
+/// pipeline.add_sink(my_sink);
+/// pipeline.start(reader.start_traced().unwrap());
+/// pipeline.join();
+/// ```
+pub struct Pipeline {
+  workers: usize,
+  capacity: usize,
+  backpressure: Backpressure,
+  sinks: Vec<Box<dyn Sink>>,
+  filter: EntryFilter,
+  sync_interval: Option<Duration>,
+  handles: Vec<JoinHandle<()>>,
+  dropped: Arc<AtomicUsize>,
+  collected: Arc<AtomicUsize>,
+  synced: Vec<Arc<AtomicUsize>>,
+  started: bool,
+  run: Arc<AtomicBool>
+}
+
+impl Pipeline {
+  /// Create a new pipeline.
+  ///
+  /// `workers` is the number of parsing threads (at least one is always used), `capacity` is the
+  /// size of each channel between stages (also at least one), and `backpressure` decides what
+  /// happens when a sink falls behind.
+  pub fn new(workers: usize, capacity: usize, backpressure: Backpressure) -> Self {
+    Pipeline {
+      workers: ::std::cmp::max(workers, 1),
+      // A channel with no room only takes an entry when a sink is already waiting for one, so
+      // DropNewest would drop nearly everything.
+      capacity: ::std::cmp::max(capacity, 1),
+      backpressure,
+      sinks: Vec::new(),
+      filter: EntryFilter::All,
+      sync_interval: None,
+      handles: Vec::new(),
+      dropped: Arc::new(AtomicUsize::new(0)),
+      collected: Arc::new(AtomicUsize::new(0)),
+      synced: Vec::new(),
+      started: false,
+      run: Arc::new(AtomicBool::new(false))
+    }
+  }
+
+  /// Adds a sink to the pipeline.
+  ///
+  /// Sinks added after `start` has been called will not receive any entries.
+  pub fn add_sink<S>(&mut self, sink: S)
+    where S: Sink + 'static
+  {
+    self.sinks.push(Box::new(sink));
+  }
+
+  /// Sets the filter entries must match to reach any sink, such as a
+  /// [`Blocklist`](../filter/struct.Blocklist.html) filter.
+  ///
+  /// Changing the filter after `start` has been called has no effect.
+  pub fn set_filter(&mut self, filter: EntryFilter) {
+    self.filter = filter;
+  }
+
+  /// Makes every sink [`sync`](trait.Sink.html#method.sync) at least this often while entries are
+  /// arriving, and once more when they stop. Sinks are only flushed at the end without this.
+  ///
+  /// Changing the interval after `start` has been called has no effect.
+  pub fn set_sync_interval(&mut self, interval: Duration) {
+    self.sync_interval = Some(interval);
+  }
+
+  /// Starts the pipeline over the raw bytes from
+  /// [`MemoryEntryReader::start_traced`](../struct.MemoryEntryReader.html#method.start_traced),
+  /// recording where each entry was read from in its provenance.
+  ///
+  /// Returns `false` if the pipeline has already been started, even if it has since been stopped.
+  pub fn start(&mut self, source: Receiver<(Vec<u8>, Provenance)>) -> bool {
+    self.start_with(source, |(bytes, provenance)| {
+      let mut entry = opt!(RawEntry::new(bytes).as_entry());
+      entry.provenance = Some(provenance);
+      Some(entry)
+    })
+  }
+
+  /// Starts the pipeline over entries that have already been parsed, such as those from
+  /// [`ActReader::start`](../act/struct.ActReader.html#method.start).
+  ///
+  /// Returns `false` if the pipeline has already been started, even if it has since been stopped.
+  pub fn start_parsed(&mut self, source: Receiver<Entry>) -> bool {
+    self.start_with(source, Some)
+  }
+
+  fn start_with<T, F>(&mut self, source: Receiver<T>, parse: F) -> bool
+    where T: Send + 'static,
+          F: Fn(T) -> Option<Entry> + Send + Sync + 'static
+  {
+    // The sinks are moved to their threads when starting, so a stopped pipeline can't be started
+    // again.
+    if self.started {
+      return false;
+    }
+    self.started = true;
+    self.run.store(true, Ordering::Relaxed);
+
+    let sink_txs = self.spawn_sinks();
+
+    // Tag everything with a sequence number so the collector can restore the capture order.
+    let (job_tx, job_rx) = sync_channel::<(u64, T)>(self.capacity);
+    let run = self.run.clone();
+    self.handles.push(thread::spawn(move || {
+      let mut seq = 0;
+      while run.load(Ordering::Relaxed) {
+        let item = match source.recv_timeout(Duration::from_millis(100)) {
+          Ok(i) => i,
+          Err(RecvTimeoutError::Timeout) => continue,
+          Err(RecvTimeoutError::Disconnected) => break
+        };
+        try_or!(job_tx.send((seq, item)), break);
+        seq += 1;
+      }
+    }));
+
+    let (result_tx, result_rx) = sync_channel::<(u64, Option<Entry>)>(self.capacity);
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let parse = Arc::new(parse);
+    for _ in 0..self.workers {
+      let job_rx = job_rx.clone();
+      let result_tx = result_tx.clone();
+      let parse = parse.clone();
+      self.handles.push(thread::spawn(move || {
+        loop {
+          let job = match job_rx.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => break
+          };
+          let (seq, item) = try_or!(job, break);
+          try_or!(result_tx.send((seq, parse(item))), break);
+        }
+      }));
+    }
+    drop(result_tx);
+
+    let backpressure = self.backpressure;
+    let dropped = self.dropped.clone();
+    let collected = self.collected.clone();
+    let filter = self.filter.clone();
+    self.handles.push(thread::spawn(move || {
+      let mut next = 0;
+      let mut pending = BTreeMap::new();
+      for (seq, entry) in result_rx {
+        pending.insert(seq, entry);
+        while let Some(entry) = pending.remove(&next) {
+          let seq = next;
+          next += 1;
+          collected.store(next as usize, Ordering::Relaxed);
+          let entry = match entry {
+            Some(e) => e,
+            None => continue
+          };
+          if !filter.matches(&entry) {
+            continue;
+          }
+          let entry = Arc::new(entry);
+          for tx in &sink_txs {
+            match backpressure {
+              Backpressure::Block => { let _ = tx.send((seq, entry.clone())); },
+              Backpressure::DropNewest => if let Err(TrySendError::Full(_)) = tx.try_send((seq, entry.clone())) {
+                dropped.fetch_add(1, Ordering::Relaxed);
+              }
+            }
+          }
+        }
+      }
+    }));
+
+    true
+  }
+
+  fn spawn_sinks(&mut self) -> Vec<SyncSender<(u64, Arc<Entry>)>> {
+    let sinks = mem::replace(&mut self.sinks, Vec::new());
+    let mut txs = Vec::with_capacity(sinks.len());
+    for mut sink in sinks {
+      let (tx, rx) = sync_channel::<(u64, Arc<Entry>)>(self.capacity);
+      txs.push(tx);
+      let synced = Arc::new(AtomicUsize::new(0));
+      self.synced.push(synced.clone());
+      let interval = self.sync_interval;
+      let collected = self.collected.clone();
+      self.handles.push(thread::spawn(move || {
+        // The number of entries from the source that this sink has seen, in the pipeline's order.
+        let mut handled = 0;
+        let mut last_sync = Instant::now();
+        loop {
+          let received = match interval {
+            Some(i) => rx.recv_timeout(i),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+          };
+          match received {
+            Ok((seq, entry)) => {
+              sink.handle(&entry);
+              handled = seq as usize + 1;
+            },
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => break
+          }
+          let due = interval.map(|i| last_sync.elapsed() >= i).unwrap_or(false);
+          if due && handled > synced.load(Ordering::Relaxed) {
+            sink.sync();
+            synced.store(handled, Ordering::Relaxed);
+            last_sync = Instant::now();
+          }
+        }
+        sink.flush();
+        // The collector has finished, so everything it passed over, like filtered entries after the
+        // last one this sink saw, is done with too.
+        synced.store(handled.max(collected.load(Ordering::Relaxed)), Ordering::Relaxed);
+      }));
+    }
+    txs
+  }
+
+  /// The number of entries from the source that every sink has handled and synced or flushed.
+  ///
+  /// Entries up to this point are safely written by every sink that implements
+  /// [`sync`](trait.Sink.html#method.sync), so a checkpoint can be moved past them. While running,
+  /// entries that were filtered out or couldn't be parsed only count once a later entry reaches
+  /// every sink. Once the source is exhausted or the pipeline is stopped, and every sink has been
+  /// flushed, they all count.
+  pub fn synced(&self) -> usize {
+    let collected = self.collected.load(Ordering::Relaxed);
+    self.synced.iter()
+      .map(|x| x.load(Ordering::Relaxed))
+      .min()
+      .unwrap_or(collected)
+  }
+
+  /// The number of entries dropped because a sink was full.
+  ///
+  /// This is always `0` when using `Backpressure::Block`.
+  pub fn dropped(&self) -> usize {
+    self.dropped.load(Ordering::Relaxed)
+  }
+
+  /// Stops the pipeline.
+  ///
+  /// Entries already read from the source still make it to the sinks. This is called automatically
+  /// when the pipeline is dropped.
+  pub fn stop(&self) {
+    self.run.store(false, Ordering::Relaxed);
+  }
+
+  /// Waits for every stage of the pipeline to finish.
+  ///
+  /// This returns once the source has been exhausted (or `stop` has been called) and every sink has
+  /// been flushed.
+  pub fn join(&mut self) {
+    for handle in self.handles.drain(..) {
+      let _ = handle.join();
+    }
+  }
+}
+
+impl Drop for Pipeline {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use messages::{Message, MessageType};
+  use messages::parts::Part;
+
+  use std::collections::BTreeMap;
+  use std::sync::mpsc::channel;
+
+  fn entry(message_type: MessageType, text: &str) -> Entry {
+    Entry {
+      message_type,
+      timestamp: 0,
+      sender: None,
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  struct Counter(Arc<AtomicUsize>);
+
+  impl Sink for Counter {
+    fn handle(&mut self, _: &Entry) {
+      self.0.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  #[test]
+  fn pipelines_only_start_once() {
+    let mut pipeline = Pipeline::new(1, 0, Backpressure::Block);
+    let (tx, rx) = channel::<Entry>();
+    assert!(pipeline.start_parsed(rx));
+    pipeline.stop();
+    drop(tx);
+    pipeline.join();
+    let (_tx, rx) = channel::<Entry>();
+    assert!(!pipeline.start_parsed(rx));
+  }
+
+  #[test]
+  fn trailing_filtered_entries_count_as_synced() {
+    let count = Arc::new(AtomicUsize::new(0));
+    let mut pipeline = Pipeline::new(2, 4, Backpressure::Block);
+    pipeline.set_filter(EntryFilter::MessageTypes(vec![MessageType::Say]));
+    pipeline.add_sink(Counter(count.clone()));
+    let (tx, rx) = channel();
+    assert!(pipeline.start_parsed(rx));
+    tx.send(entry(MessageType::Say, "kept")).unwrap();
+    for _ in 0..5 {
+      tx.send(entry(MessageType::Shout, "filtered")).unwrap();
+    }
+    drop(tx);
+    pipeline.join();
+    assert_eq!(count.load(Ordering::Relaxed), 1);
+    assert_eq!(pipeline.synced(), 6);
+  }
+}
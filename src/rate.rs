@@ -0,0 +1,199 @@
+//! Entry rates and bursts
+//!
+//! A [`RateMonitor`](struct.RateMonitor.html) counts entries per channel over a sliding window and
+//! notices bursts, when a channel suddenly gets much busier than usual. Hunt trains and shout spam
+//! can send hundreds of entries a minute, which is enough to get a forwarding sink rate limited or
+//! banned, so the [`ThrottledSink`](struct.ThrottledSink.html) uses a monitor to slow down only the
+//! channels that are bursting.
+//!
+//! Rates are measured with entry timestamps rather than the clock, so they work the same on live
+//! streams and on logs being replayed.
+
+use messages::MessageType;
+use messages::entries::Entry;
+use pipeline::Sink;
+
+use std::collections::{HashMap, VecDeque};
+
+/// How busy a channel is.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelRate {
+  pub message_type: MessageType,
+  /// Entries per second over the short window.
+  pub per_second: f64,
+  /// Entries per second over the long window.
+  pub baseline: f64,
+  /// Whether the channel is bursting.
+  pub burst: bool
+}
+
+/// Counts entries per channel and detects bursts.
+///
+/// A channel is bursting when its rate over the last `short_window` seconds is at least
+/// `burst_factor` times its rate over the last `long_window` seconds, and at least `min_burst_rate`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut monitor = RateMonitor::new();
+/// for entry in reader.iter() {
+///   if monitor.observe(&entry) {
+///     println!("{} is bursting at {:.1}/s", entry.message_type, monitor.rate(entry.message_type));
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateMonitor {
+  /// The length of the window rates are measured over, in seconds.
+  pub short_window: u32,
+  /// The length of the window baselines are measured over, in seconds.
+  pub long_window: u32,
+  /// How many times the baseline the rate must be for a burst.
+  pub burst_factor: f64,
+  /// The lowest rate that counts as a burst, in entries per second.
+  pub min_burst_rate: f64,
+  timestamps: HashMap<MessageType, VecDeque<u32>>,
+  latest: u32
+}
+
+impl RateMonitor {
+  /// Create a monitor with a 10 second window and a 5 minute baseline, where bursts are three times
+  /// the baseline and at least one entry a second.
+  pub fn new() -> Self {
+    RateMonitor {
+      short_window: 10,
+      long_window: 300,
+      burst_factor: 3.0,
+      min_burst_rate: 1.0,
+      timestamps: HashMap::new(),
+      latest: 0
+    }
+  }
+
+  /// Counts an entry, returning whether its channel is now bursting.
+  pub fn observe(&mut self, entry: &Entry) -> bool {
+    self.latest = self.latest.max(entry.timestamp);
+    let cutoff = self.latest.saturating_sub(self.long_window);
+    {
+      let timestamps = self.timestamps.entry(entry.message_type).or_insert_with(VecDeque::new);
+      timestamps.push_back(entry.timestamp);
+      while timestamps.front().map(|x| *x < cutoff).unwrap_or(false) {
+        timestamps.pop_front();
+      }
+    }
+    self.is_bursting(entry.message_type)
+  }
+
+  /// The rate of a channel over the short window, in entries per second.
+  pub fn rate(&self, message_type: MessageType) -> f64 {
+    self.rate_over(message_type, self.short_window)
+  }
+
+  /// The rate of a channel over the long window, in entries per second.
+  pub fn baseline(&self, message_type: MessageType) -> f64 {
+    self.rate_over(message_type, self.long_window)
+  }
+
+  /// The rate of every channel together over the short window, in entries per second.
+  pub fn total_rate(&self) -> f64 {
+    self.timestamps.keys().map(|x| self.rate(*x)).sum()
+  }
+
+  /// Checks if a channel is bursting.
+  pub fn is_bursting(&self, message_type: MessageType) -> bool {
+    let rate = self.rate(message_type);
+    rate >= self.min_burst_rate && rate >= self.burst_factor * self.baseline(message_type)
+  }
+
+  /// The rates of every channel seen in the long window, busiest first.
+  pub fn rates(&self) -> Vec<ChannelRate> {
+    let mut rates: Vec<ChannelRate> = self.timestamps.iter()
+      .filter(|&(_, t)| !t.is_empty())
+      .map(|(t, _)| ChannelRate {
+        message_type: *t,
+        per_second: self.rate(*t),
+        baseline: self.baseline(*t),
+        burst: self.is_bursting(*t)
+      })
+      .collect();
+    rates.sort_by(|a, b| b.per_second.partial_cmp(&a.per_second).unwrap_or(::std::cmp::Ordering::Equal));
+    rates
+  }
+
+  fn rate_over(&self, message_type: MessageType, window: u32) -> f64 {
+    let timestamps = opt_or!(self.timestamps.get(&message_type), return 0.0);
+    let cutoff = self.latest.saturating_sub(window);
+    let count = timestamps.iter().rev().take_while(|x| **x >= cutoff).count();
+    count as f64 / window.max(1) as f64
+  }
+}
+
+impl Default for RateMonitor {
+  fn default() -> Self {
+    RateMonitor::new()
+  }
+}
+
+/// A sink that limits how many entries reach another sink while a channel is bursting.
+///
+/// Outside of bursts, every entry is passed on. During a burst, entries from the bursting channel
+/// are passed on at most `limit` per second, and the rest are dropped.
+pub struct ThrottledSink<S> {
+  sink: S,
+  /// The most entries per second passed on from a bursting channel.
+  pub limit: f64,
+  pub monitor: RateMonitor,
+  tokens: HashMap<MessageType, (f64, u32)>,
+  dropped: usize
+}
+
+impl<S: Sink> ThrottledSink<S> {
+  pub fn new(limit: f64, sink: S) -> Self {
+    ThrottledSink {
+      sink,
+      limit,
+      monitor: RateMonitor::new(),
+      tokens: HashMap::new(),
+      dropped: 0
+    }
+  }
+
+  /// The number of entries dropped so far.
+  pub fn dropped(&self) -> usize {
+    self.dropped
+  }
+
+  fn take_token(&mut self, entry: &Entry) -> bool {
+    let limit = self.limit;
+    // Limits below one a second still need room for a whole entry.
+    let capacity = limit.max(1.0);
+    let &mut (ref mut tokens, ref mut last) = self.tokens.entry(entry.message_type).or_insert((capacity, entry.timestamp));
+    let elapsed = entry.timestamp.saturating_sub(*last);
+    *tokens = (*tokens + elapsed as f64 * limit).min(capacity);
+    *last = (*last).max(entry.timestamp);
+    if *tokens >= 1.0 {
+      *tokens -= 1.0;
+      true
+    } else {
+      false
+    }
+  }
+}
+
+impl<S: Sink> Sink for ThrottledSink<S> {
+  fn handle(&mut self, entry: &Entry) {
+    if self.monitor.observe(entry) && !self.take_token(entry) {
+      self.dropped += 1;
+      return;
+    }
+    self.sink.handle(entry);
+  }
+
+  fn flush(&mut self) {
+    self.sink.flush();
+  }
+
+  fn sync(&mut self) {
+    self.sink.sync();
+  }
+}
@@ -0,0 +1,319 @@
+//! Redacting entries for sharing
+//!
+//! A [`Redactor`](struct.Redactor.html) removes what shouldn't be shared from entries while keeping
+//! what makes them useful, so combat logs or system messages can be published without private
+//! conversations. It can:
+//!
+//! - drop whole channels, like tells and linkshells
+//! - replace the names of specific senders, or every sender, with pseudonyms
+//! - strip the text of messages in some channels, keeping their length and structure
+//!
+//! Pseudonyms are handed out in the order names are first seen and stay the same for the life of
+//! the redactor, so per-sender statistics still work on redacted logs.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let mut redactor = Redactor::new();
+//! redactor.drop_channels = vec![MessageType::Tell, MessageType::TellReceive];
+//! redactor.strip_channels = vec![MessageType::Party];
+//! redactor.mask_all_senders = true;
+//! let stats = redactor.redact_archive(&Archive::open("logs").unwrap(), "shared").unwrap();
+//! println!("Dropped {} entries.", stats.dropped);
+//! ```
+
+use archive::{Archive, Format, binary, jsonl};
+use messages::{Message, MessageType, HasDisplayText};
+use messages::entries::Entry;
+use messages::parts::{Part, PlainTextPart};
+use schema::Metadata;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The character that stripped text is replaced with.
+pub const MASK_CHAR: char = '*';
+
+/// Removes private information from entries.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+  /// Entries in these channels are dropped.
+  pub drop_channels: Vec<MessageType>,
+  /// The real names of senders to replace with pseudonyms, compared ignoring case.
+  ///
+  /// Their names are replaced wherever they appear: as the sender, in name parts, and as whole words
+  /// in plain text, ignoring case. When every name is masked, names in plain text are only replaced
+  /// once they've been seen as a sender or name part, since the redactor can't otherwise tell them
+  /// apart from other words.
+  pub masked_senders: Vec<String>,
+  /// Whether to replace every name with a pseudonym.
+  pub mask_all_senders: bool,
+  /// Messages in these channels have their text replaced with
+  /// [`MASK_CHAR`](constant.MASK_CHAR.html), keeping whitespace.
+  ///
+  /// The parts of the message are kept, so the length and shape of messages can still be studied.
+  /// Tags are removed from these entries, since they often describe the text.
+  pub strip_channels: Vec<MessageType>,
+  pseudonyms: HashMap<String, String>
+}
+
+/// What happened while redacting.
+#[derive(Debug, Clone, Default)]
+pub struct RedactStats {
+  /// The number of entries read.
+  pub read: usize,
+  /// The number of entries dropped.
+  pub dropped: usize,
+  /// The number of entries that had a name replaced.
+  pub masked: usize,
+  /// The number of entries that had their text stripped.
+  pub stripped: usize,
+  /// The number of entries written.
+  pub written: usize,
+  /// The number of files written.
+  pub files: usize
+}
+
+impl Redactor {
+  pub fn new() -> Self {
+    Redactor::default()
+  }
+
+  /// The pseudonyms handed out so far, by the lowercased real name.
+  pub fn pseudonyms(&self) -> &HashMap<String, String> {
+    &self.pseudonyms
+  }
+
+  /// Redacts an entry, returning `None` if it should be dropped entirely.
+  pub fn redact(&mut self, entry: Entry) -> Option<Entry> {
+    self.redact_counted(entry, &mut RedactStats::default())
+  }
+
+  /// Redacts every entry in an archive into the directory `output`.
+  ///
+  /// Each file in the archive becomes a JSON lines file at the same path in the output, so the
+  /// output can be opened as an [`Archive`](../archive/struct.Archive.html) too. Compressed and
+  /// binary files stay that way.
+  ///
+  /// Returns an error without writing anything if `output` overlaps the archive, since the redacted
+  /// files would replace the originals. See
+  /// [`Archive::check_output`](../archive/struct.Archive.html#method.check_output).
+  pub fn redact_archive<P: AsRef<Path>>(&mut self, archive: &Archive, output: P) -> io::Result<RedactStats> {
+    let output = output.as_ref();
+    archive.check_output(output)?;
+    let metadata = Metadata::new(Some("redacted"));
+    let mut stats = RedactStats::default();
+    for file in archive.files() {
+      let mut entries = Vec::new();
+      for entry in file.format.read_entries(archive.root().join(&file.path))? {
+        stats.read += 1;
+        if let Some(e) = self.redact_counted(entry, &mut stats) {
+          entries.push(e);
+        }
+      }
+      let (path, compress) = match file.format {
+        Format::Native => (file.path.with_extension("jsonl"), false),
+        Format::JsonLines | Format::Binary => (file.path.clone(), false),
+        Format::CompressedJsonLines => (file.path.clone(), true)
+      };
+      let path = output.join(path);
+      if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+      }
+      if file.format == Format::Binary {
+        binary::write_file(path, &metadata, &entries)?;
+      } else {
+        jsonl::write_file(path, &metadata, &entries, compress)?;
+      }
+      stats.written += entries.len();
+      stats.files += 1;
+    }
+    Ok(stats)
+  }
+
+  fn redact_counted(&mut self, mut entry: Entry, stats: &mut RedactStats) -> Option<Entry> {
+    if self.drop_channels.contains(&entry.message_type) {
+      stats.dropped += 1;
+      return None;
+    }
+    // Provenance holds file paths, which often include the name of the user's account.
+    entry.provenance = None;
+    let strip = self.strip_channels.contains(&entry.message_type);
+    let mut masked = false;
+    if let Some(name) = entry.sender_name() {
+      if let Some(pseudonym) = self.pseudonym(&name) {
+        entry.sender = Some(PlainTextPart::from_text(pseudonym));
+        masked = true;
+      }
+    }
+    let parts = ::std::mem::replace(&mut entry.message.parts, Vec::new());
+    let parts = parts.into_iter().map(|x| self.redact_part(x, strip, &mut masked)).collect();
+    entry.message = Message::new(parts);
+    if strip {
+      entry.tags.clear();
+      stats.stripped += 1;
+    }
+    if masked {
+      stats.masked += 1;
+    }
+    Some(entry)
+  }
+
+  fn redact_part(&mut self, part: Part, strip: bool, masked: &mut bool) -> Part {
+    match part {
+      Part::Name { real_name, display_name } => {
+        if let Some(pseudonym) = self.pseudonym(&real_name.display_text()) {
+          *masked = true;
+          return PlainTextPart::from_text(pseudonym);
+        }
+        Part::Name {
+          real_name,
+          display_name
+        }
+      },
+      Part::PlainText(ref text) if strip => Part::PlainText(mask(text)),
+      Part::PlainText(text) => Part::PlainText(self.mask_names(text, masked)),
+      Part::Bytes(ref bytes) if strip => Part::Bytes(vec![0; bytes.len()]),
+      Part::Multi(parts) => Part::Multi(parts.into_iter().map(|x| Box::new(self.redact_part(*x, strip, masked))).collect()),
+      Part::Colored { info, display } => Part::Colored {
+        info,
+        display: Box::new(self.redact_part(*display, strip, masked))
+      },
+      Part::Formatted { info, display } => Part::Formatted {
+        info,
+        display: Box::new(self.redact_part(*display, strip, masked))
+      },
+      Part::Link { kind, id, display } => Part::Link {
+        kind,
+        id,
+        display: Box::new(self.redact_part(*display, strip, masked))
+      },
+      other => other
+    }
+  }
+
+  /// Replaces whole words in the text that are masked names with their pseudonyms.
+  fn mask_names(&mut self, text: String, masked: &mut bool) -> String {
+    let mut names: Vec<String> = self.masked_senders.iter()
+      .map(|x| x.trim().to_lowercase())
+      .chain(self.pseudonyms.keys().cloned())
+      .filter(|x| !x.is_empty())
+      .collect();
+    if names.is_empty() {
+      return text;
+    }
+    names.sort();
+    names.dedup();
+    // Longer names first, so a full name wins over a masked first name.
+    names.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()));
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    let mut previous: Option<char> = None;
+    while let Some(c) = rest.chars().next() {
+      if !previous.map(char::is_alphanumeric).unwrap_or(false) {
+        let found = names.iter().filter_map(|x| match_word(rest, x).map(|len| (x, len))).next();
+        if let Some((name, len)) = found {
+          if let Some(pseudonym) = self.pseudonym(name) {
+            *masked = true;
+            result.push_str(&pseudonym);
+            previous = rest[..len].chars().last();
+            rest = &rest[len..];
+            continue;
+          }
+        }
+      }
+      result.push(c);
+      previous = Some(c);
+      rest = &rest[c.len_utf8()..];
+    }
+    result
+  }
+
+  fn pseudonym(&mut self, name: &str) -> Option<String> {
+    // Party number glyphs aren't part of the name.
+    let name = name.trim_start_matches(|c| c >= '\u{e090}' && c <= '\u{e097}').to_lowercase();
+    if name.is_empty() {
+      return None;
+    }
+    if !self.mask_all_senders && !self.masked_senders.iter().any(|x| x.to_lowercase() == name) {
+      return None;
+    }
+    let next = self.pseudonyms.len() + 1;
+    Some(self.pseudonyms.entry(name).or_insert_with(|| format!("Player {}", next)).clone())
+  }
+}
+
+/// Checks if the text starts with the lowercased name as a whole word, ignoring case. Returns the
+/// length of the name in the text.
+fn match_word(text: &str, name: &str) -> Option<usize> {
+  let mut name = name.chars().peekable();
+  for (i, c) in text.char_indices() {
+    if name.peek().is_none() {
+      return if c.is_alphanumeric() { None } else { Some(i) };
+    }
+    for lower in c.to_lowercase() {
+      if name.next() != Some(lower) {
+        return None;
+      }
+    }
+  }
+  if name.peek().is_none() { Some(text.len()) } else { None }
+}
+
+fn mask(text: &str) -> String {
+  text.chars().map(|c| if c.is_whitespace() { c } else { MASK_CHAR }).collect()
+}
+
+/// A pseudonym for a name that is the same every time, like `Player 1a2b3c`.
+///
+/// Names are compared ignoring case. Unlike the pseudonyms a [`Redactor`](struct.Redactor.html)
+/// hands out, these don't depend on which names were seen first, so they can be used anywhere.
+pub fn stable_pseudonym(name: &str) -> String {
+  // FNV-1a, like Entry::dedup_key.
+  let mut hash: u64 = 0xcbf29ce484222325;
+  for byte in name.trim().to_lowercase().bytes() {
+    hash ^= byte as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  format!("Player {:06x}", hash & 0xff_ffff)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::collections::BTreeMap;
+
+  fn entry(sender: &str, text: &str) -> Entry {
+    Entry {
+      message_type: MessageType::Say,
+      timestamp: 0,
+      sender: Some(Part::PlainText(sender.to_owned())),
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  #[test]
+  fn masks_names_in_plain_text() {
+    let mut redactor = Redactor::new();
+    redactor.masked_senders = vec!["Luna Kamaria".to_owned()];
+    let entry = redactor.redact(entry("Someone Else", "luna kamaria, LUNA KAMARIA's sword and Luna Kamarian")).unwrap();
+    assert_eq!(entry.message.plain_text(), "Player 1, Player 1's sword and Luna Kamarian");
+    assert_eq!(entry.sender_name().unwrap(), "Someone Else");
+  }
+
+  #[test]
+  fn masks_seen_names_when_masking_everyone() {
+    let mut redactor = Redactor::new();
+    redactor.mask_all_senders = true;
+    let first = redactor.redact(entry("Luna Kamaria", "hello Rin Tohsaka")).unwrap();
+    assert_eq!(first.message.plain_text(), "hello Rin Tohsaka");
+    let second = redactor.redact(entry("Rin Tohsaka", "hi Luna Kamaria")).unwrap();
+    assert_eq!(second.sender_name().unwrap(), "Player 2");
+    assert_eq!(second.message.plain_text(), "hi Player 1");
+  }
+}
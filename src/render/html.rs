@@ -0,0 +1,202 @@
+use archive::bookmarks::Bookmarks;
+use messages::HasDisplayText;
+use messages::entries::{Entry, TellDirection};
+use messages::parts::Part;
+use names::display_policy;
+use render::{IconMap, IconImage, Renderer, format_timestamp};
+
+/// Renders entries as HTML.
+///
+/// Each entry becomes a `div` with the class `entry` and the snake case name of its message type, so
+/// channels can be styled with CSS. Tells also get `tell-incoming` or `tell-outgoing`.
+#[derive(Debug, Clone)]
+pub struct HtmlRenderer {
+  /// The icons to show in place of `Icon` parts and glyphs.
+  pub icons: IconMap,
+  /// Whether `render_log` should produce a whole page instead of just the entries.
+  pub standalone: bool,
+  /// Bookmarks to mark entries with.
+  ///
+  /// Bookmarked entries get the class `bookmarked` and an `id` of `entry-` and their `dedup_key` in
+  /// hex, and their notes are shown after the message. Whole pages start with a list of links to
+  /// them.
+  pub bookmarks: Option<Bookmarks>
+}
+
+impl HtmlRenderer {
+  /// Create a renderer using the [built-in icons](struct.IconMap.html#method.builtin) that
+  /// renders whole pages.
+  pub fn new() -> Self {
+    HtmlRenderer {
+      icons: IconMap::builtin(),
+      standalone: true,
+      bookmarks: None
+    }
+  }
+
+  /// Renders a part of a message.
+  pub fn render_part(&self, part: &Part) -> String {
+    match *part {
+      Part::PlainText(ref text) => self.render_text(text),
+      Part::Name { ref real_name, ref display_name } => {
+        let policy = display_policy();
+        if !policy.is_default() {
+          // The real name can't be in the title if it's being hidden.
+          return format!("<span class=\"name\">{}</span>", self.render_text(&policy.format_part(real_name, display_name)));
+        }
+        format!(
+          "<span class=\"name\" title=\"{}\">{}</span>",
+          escape(&real_name.plain_text()),
+          self.render_part(display_name)
+        )
+      },
+      Part::AutoTranslate { .. } => format!("<span class=\"auto-translate\">{}</span>", escape(&part.display_text())),
+      Part::Colored { ref display, .. } => format!("<span class=\"colored\">{}</span>", self.render_part(display)),
+      Part::Formatted { ref display, .. } => format!("<em>{}</em>", self.render_part(display)),
+      Part::Multi(ref parts) => parts.iter().map(|x| self.render_part(x)).collect(),
+      Part::Bytes(_) => String::new(),
+      Part::Percentage(_) => String::from(" "),
+      Part::Icon(id) => match self.icons.icon(id) {
+        Some(image) => render_image(image),
+        None => format!("<span class=\"icon\" data-icon=\"{}\"></span>", id)
+      },
+      Part::Link { kind, id, ref display } => format!(
+        "<span class=\"link\" data-kind=\"{}\" data-id=\"{}\">{}</span>",
+        kind,
+        id,
+        self.render_part(display)
+      )
+    }
+  }
+
+  fn render_bookmarks(&self, bookmarks: &Bookmarks, entries: &[&Entry]) -> String {
+    let links: Vec<String> = entries.iter()
+      .filter_map(|e| bookmarks.get(e).map(|b| (e, b)))
+      .map(|(e, b)| format!(
+        "<li><a href=\"#entry-{:x}\">{}</a> {}</li>",
+        b.key,
+        format_timestamp(e, "%Y-%m-%d %H:%M:%S"),
+        escape(b.note.as_ref().map(|x| x.as_str()).unwrap_or(""))
+      ))
+      .collect();
+    if links.is_empty() {
+      return String::new();
+    }
+    format!("<nav class=\"bookmarks\"><ul>\n{}\n</ul></nav>\n", links.join("\n"))
+  }
+
+  fn render_text(&self, text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for c in text.chars() {
+      if let Some(image) = self.icons.glyph(c) {
+        html.push_str(&render_image(image));
+        continue;
+      }
+      match c {
+        '\r' | '\n' => html.push_str("<br>"),
+        _ => push_escaped(&mut html, c)
+      }
+    }
+    html
+  }
+}
+
+impl Default for HtmlRenderer {
+  fn default() -> Self {
+    HtmlRenderer::new()
+  }
+}
+
+impl Renderer for HtmlRenderer {
+  fn render_entry(&self, entry: &Entry) -> String {
+    let sender = match entry.sender {
+      Some(Part::PlainText(ref name)) if !display_policy().is_default() => format!(
+        " <span class=\"sender\">{}</span>",
+        self.render_text(&display_policy().format(name, name))
+      ),
+      Some(ref s) => format!(" <span class=\"sender\">{}</span>", self.render_part(s)),
+      None => String::new()
+    };
+    let message: String = entry.message.parts.iter().map(|x| self.render_part(x)).collect();
+    let bookmark = self.bookmarks.as_ref().and_then(|x| x.get(entry));
+    let (class, id, note) = match bookmark {
+      Some(b) => (
+        " bookmarked",
+        format!(" id=\"entry-{:x}\"", b.key),
+        b.note.as_ref().map(|x| format!(" <span class=\"note\">{}</span>", escape(x))).unwrap_or_default()
+      ),
+      None => ("", String::new(), String::new())
+    };
+    let direction = match entry.tell_direction() {
+      Some(TellDirection::Incoming) => " tell-incoming",
+      Some(TellDirection::Outgoing) => " tell-outgoing",
+      None => ""
+    };
+    format!(
+      "<div class=\"entry {}{}{}\"{}><time datetime=\"{}\">{}</time>{} <span class=\"message\">{}</span>{}</div>",
+      entry.message_type.snake_case_name(),
+      direction,
+      class,
+      id,
+      format_timestamp(entry, "%Y-%m-%dT%H:%M:%S%:z"),
+      format_timestamp(entry, "%H:%M:%S"),
+      sender,
+      message,
+      note
+    )
+  }
+
+  fn render_log<'a, I>(&self, entries: I) -> String
+    where I: IntoIterator<Item = &'a Entry>,
+          Self: Sized
+  {
+    let entries: Vec<&Entry> = entries.into_iter().collect();
+    let body = entries.iter().map(|x| self.render_entry(x)).collect::<Vec<_>>().join("\n");
+    if !self.standalone {
+      return body;
+    }
+    let nav = match self.bookmarks {
+      Some(ref bookmarks) => self.render_bookmarks(bookmarks, &entries),
+      None => String::new()
+    };
+    format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Chat log</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}{}\n</body>\n</html>\n", STYLE, nav, body)
+  }
+}
+
+static STYLE: &'static str = "body { background: #1e1e1e; color: #eee; font-family: sans-serif; }
+.entry time { color: #888; margin-right: 0.5em; }
+.entry .sender::after { content: \":\"; }
+.tell-outgoing .sender::before { content: \">> \"; }
+.tell-incoming .sender::after { content: \" >>\"; }
+.name { font-weight: bold; }
+.link { color: #f0c674; }
+img.icon { height: 1em; vertical-align: middle; }
+.bookmarked { background: #2d2a1e; border-left: 3px solid #f0c674; }
+.note { color: #f0c674; font-style: italic; margin-left: 0.5em; }";
+
+fn render_image(image: &IconImage) -> String {
+  match *image {
+    IconImage::Emoji(ref text) => format!("<span class=\"icon\">{}</span>", escape(text)),
+    IconImage::Image(ref url) => format!("<img class=\"icon\" src=\"{}\" alt=\"\">", escape(url))
+  }
+}
+
+fn push_escaped(html: &mut String, c: char) {
+  match c {
+    '&' => html.push_str("&amp;"),
+    '<' => html.push_str("&lt;"),
+    '>' => html.push_str("&gt;"),
+    '"' => html.push_str("&quot;"),
+    '\'' => html.push_str("&#39;"),
+    _ => html.push(c)
+  }
+}
+
+/// Escapes text for use in HTML.
+pub fn escape(text: &str) -> String {
+  let mut html = String::with_capacity(text.len());
+  for c in text.chars() {
+    push_escaped(&mut html, c);
+  }
+  html
+}
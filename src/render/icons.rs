@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+
+/// What to show in place of an icon.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IconImage {
+  /// Text to show instead, usually an emoji.
+  Emoji(String),
+  /// The URL of an image to show instead.
+  Image(String)
+}
+
+/// A mapping from icons to what renderers should show in their place.
+///
+/// Icons show up in two ways: as `Icon` parts with an ID, and as glyphs from the private use area
+/// inside plain text (such as the high quality glyph). Both can be mapped.
+///
+/// In the config file, IDs are given as strings, since TOML keys always are.
+///
+/// ```toml
+/// [icons.ids]
+/// "62119" = { image = "https://example.com/icons/pld.png" }
+///
+/// [icons.glyphs]
+/// "\uE03C" = { emoji = "★" }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IconMap {
+  /// Images for `Icon` parts, keyed by icon ID.
+  #[serde(default)]
+  pub ids: BTreeMap<String, IconImage>,
+  /// Images for glyphs, keyed by the glyph itself.
+  #[serde(default)]
+  pub glyphs: BTreeMap<String, IconImage>
+}
+
+impl IconMap {
+  /// Create an empty icon map.
+  pub fn new() -> Self {
+    IconMap::default()
+  }
+
+  /// Create an icon map with the glyphs whose meaning is known.
+  ///
+  /// This covers the high quality glyph and the party position numbers. Icon IDs aren't included,
+  /// since what they point to depends on the client's icon files.
+  pub fn builtin() -> Self {
+    let mut map = IconMap::new();
+    map.set_glyph('\u{e03c}', IconImage::Emoji(String::from("★")));
+    let numbers = ['①', '②', '③', '④', '⑤', '⑥', '⑦', '⑧'];
+    for (i, number) in numbers.iter().enumerate() {
+      let glyph = opt_or!(::std::char::from_u32(0xe090 + i as u32), continue);
+      map.set_glyph(glyph, IconImage::Emoji(number.to_string()));
+    }
+    map
+  }
+
+  /// Sets the image for an `Icon` part.
+  pub fn set_icon(&mut self, id: u64, image: IconImage) {
+    self.ids.insert(id.to_string(), image);
+  }
+
+  /// Sets the image for a glyph.
+  pub fn set_glyph(&mut self, glyph: char, image: IconImage) {
+    self.glyphs.insert(glyph.to_string(), image);
+  }
+
+  /// Gets the image for an `Icon` part.
+  pub fn icon(&self, id: u64) -> Option<&IconImage> {
+    self.ids.get(&id.to_string())
+  }
+
+  /// Gets the image for a glyph.
+  ///
+  /// Only glyphs in the private use area are looked up, since everything else is ordinary text.
+  pub fn glyph(&self, glyph: char) -> Option<&IconImage> {
+    if glyph < '\u{e000}' || glyph > '\u{f8ff}' {
+      return None;
+    }
+    self.glyphs.get(&glyph.to_string())
+  }
+
+  /// Adds every mapping from `other`, replacing any that are already set.
+  pub fn extend(&mut self, other: IconMap) {
+    self.ids.extend(other.ids);
+    self.glyphs.extend(other.glyphs);
+  }
+}
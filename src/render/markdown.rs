@@ -0,0 +1,130 @@
+use archive::bookmarks::Bookmarks;
+use messages::HasDisplayText;
+use messages::entries::{Entry, TellDirection};
+use messages::parts::Part;
+use names::display_policy;
+use render::{IconMap, IconImage, Renderer, format_timestamp};
+
+/// Renders entries as Markdown, one line per entry.
+///
+/// Names are shown in bold and formatted text in italics. Colors and unmapped icons are dropped,
+/// since Markdown has no way to show them. Tells are marked with `>>` the way the game shows them,
+/// before the name for outgoing tells and after it for incoming ones.
+#[derive(Debug, Clone)]
+pub struct MarkdownRenderer {
+  /// The icons to show in place of `Icon` parts and glyphs.
+  pub icons: IconMap,
+  /// Bookmarks to mark entries with. Bookmarked entries start with a bookmark emoji and end with
+  /// their note.
+  pub bookmarks: Option<Bookmarks>
+}
+
+impl MarkdownRenderer {
+  /// Create a renderer using the [built-in icons](struct.IconMap.html#method.builtin).
+  pub fn new() -> Self {
+    MarkdownRenderer {
+      icons: IconMap::builtin(),
+      bookmarks: None
+    }
+  }
+
+  /// Renders a part of a message.
+  pub fn render_part(&self, part: &Part) -> String {
+    match *part {
+      Part::PlainText(ref text) => self.render_text(text),
+      Part::Name { ref real_name, ref display_name } => {
+        let policy = display_policy();
+        if !policy.is_default() {
+          return format!("**{}**", self.render_text(&policy.format_part(real_name, display_name)));
+        }
+        format!("**{}**", self.render_part(display_name))
+      },
+      Part::AutoTranslate { .. } => escape(&part.display_text()),
+      Part::Colored { ref display, .. }
+        | Part::Link { ref display, .. } => self.render_part(display),
+      Part::Formatted { ref display, .. } => format!("*{}*", self.render_part(display)),
+      Part::Multi(ref parts) => parts.iter().map(|x| self.render_part(x)).collect(),
+      Part::Bytes(_) => String::new(),
+      Part::Percentage(_) => String::from(" "),
+      Part::Icon(id) => match self.icons.icon(id) {
+        Some(image) => render_image(image),
+        None => String::new()
+      }
+    }
+  }
+
+  fn render_text(&self, text: &str) -> String {
+    let mut markdown = String::with_capacity(text.len());
+    for c in text.chars() {
+      if let Some(image) = self.icons.glyph(c) {
+        markdown.push_str(&render_image(image));
+        continue;
+      }
+      match c {
+        // Line breaks would end the entry's line, so use a hard break instead.
+        '\r' | '\n' => markdown.push_str("<br>"),
+        _ => push_escaped(&mut markdown, c)
+      }
+    }
+    markdown
+  }
+}
+
+impl Default for MarkdownRenderer {
+  fn default() -> Self {
+    MarkdownRenderer::new()
+  }
+}
+
+impl Renderer for MarkdownRenderer {
+  fn render_entry(&self, entry: &Entry) -> String {
+    let sender = match entry.sender {
+      Some(Part::PlainText(ref name)) if !display_policy().is_default() => {
+        self.render_text(&display_policy().format(name, name))
+      },
+      Some(ref s) => self.render_part(s),
+      None => String::new()
+    };
+    let sender = match (entry.sender.is_some(), entry.tell_direction()) {
+      (false, _) => sender,
+      (true, Some(TellDirection::Incoming)) => format!(" {} >>", sender),
+      (true, Some(TellDirection::Outgoing)) => format!(" >> {}:", sender),
+      (true, None) => format!(" {}:", sender)
+    };
+    let message: String = entry.message.parts.iter().map(|x| self.render_part(x)).collect();
+    let line = format!("`{}` *{}*{} {}", format_timestamp(entry, "%Y-%m-%d %H:%M:%S"), entry.message_type, sender, message);
+    match self.bookmarks.as_ref().and_then(|x| x.get(entry)) {
+      Some(b) => match b.note {
+        Some(ref note) => format!("\u{1f516} {} \u{2014} *{}*  ", line, escape(note)),
+        None => format!("\u{1f516} {}  ", line)
+      },
+      None => format!("{}  ", line)
+    }
+  }
+}
+
+fn render_image(image: &IconImage) -> String {
+  match *image {
+    IconImage::Emoji(ref text) => escape(text),
+    IconImage::Image(ref url) => format!("![]({})", url)
+  }
+}
+
+fn push_escaped(markdown: &mut String, c: char) {
+  match c {
+    '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' => {
+      markdown.push('\\');
+      markdown.push(c);
+    },
+    _ => markdown.push(c)
+  }
+}
+
+/// Escapes text for use in Markdown.
+pub fn escape(text: &str) -> String {
+  let mut markdown = String::with_capacity(text.len());
+  for c in text.chars() {
+    push_escaped(&mut markdown, c);
+  }
+  markdown
+}
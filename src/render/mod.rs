@@ -0,0 +1,43 @@
+//! Rendering entries as HTML or Markdown
+//!
+//! Renderers turn entries into something readable outside of the game, keeping names, links,
+//! formatting, and icons instead of flattening everything into display text. Icons are shown using
+//! an [`IconMap`](struct.IconMap.html), so rendered logs can look like the client does.
+//!
+//! For any other format, a [`TemplateRenderer`](struct.TemplateRenderer.html) fills in a
+//! user-supplied [`Template`](struct.Template.html) for each entry.
+
+pub mod html;
+pub mod markdown;
+pub mod template;
+mod icons;
+
+pub use self::icons::{IconMap, IconImage};
+pub use self::html::HtmlRenderer;
+pub use self::markdown::MarkdownRenderer;
+pub use self::template::{Template, TemplateError, TemplateRenderer};
+
+use messages::entries::Entry;
+
+use chrono::{Local, TimeZone};
+
+/// Something that can render entries.
+pub trait Renderer {
+  /// Renders a single entry.
+  fn render_entry(&self, entry: &Entry) -> String;
+
+  /// Renders a whole log.
+  ///
+  /// By default, this is every entry rendered and joined with newlines.
+  fn render_log<'a, I>(&self, entries: I) -> String
+    where I: IntoIterator<Item = &'a Entry>,
+          Self: Sized
+  {
+    entries.into_iter().map(|x| self.render_entry(x)).collect::<Vec<_>>().join("\n")
+  }
+}
+
+/// Formats an entry's timestamp in local time.
+fn format_timestamp(entry: &Entry, format: &str) -> String {
+  Local.timestamp(entry.timestamp as i64, 0).format(format).to_string()
+}
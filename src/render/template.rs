@@ -0,0 +1,452 @@
+use messages::entries::{Entry, TellDirection};
+use names;
+use render::{Renderer, HtmlRenderer, MarkdownRenderer, format_timestamp, html, markdown};
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+
+/// A template for one entry.
+///
+/// Templates are text with fields in double braces, which are replaced with parts of the entry:
+///
+/// - `{{timestamp}}`: the timestamp, in seconds since the epoch
+/// - `{{time}}` or `{{time:FORMAT}}`: the local time, formatted with `strftime` codes, defaulting to
+///   `%Y-%m-%d %H:%M:%S`
+/// - `{{type}}`: the name of the message type as it is displayed elsewhere, like `FreeCompany`
+/// - `{{channel}}`: the snake case name of the message type, like `free_company`
+/// - `{{sender}}`: the sender as displayed, following the
+///   [name display policy](../../names/index.html#display-policy)
+/// - `{{sender_name}}`: the real name of the sender
+/// - `{{direction}}`: `incoming` or `outgoing` for tells, and empty otherwise
+/// - `{{counterpart}}`: the real name of the other person in a tell
+/// - `{{message}}`: the display text of the message
+/// - `{{message_plain}}`: the plain text of the message, without auto-translate brackets
+/// - `{{message_html}}` and `{{message_markdown}}`: the message as the HTML and Markdown renderers
+///   render it
+/// - `{{tag:NAME}}`: the value of a tag, or `yes` if it has no value
+/// - `{{key}}`: the `dedup_key` of the entry, in hex
+///
+/// Every field that shows a name, including `{{sender_name}}`, `{{counterpart}}`, and
+/// `{{message_plain}}`, follows the name display policy.
+///
+/// Fields can be passed through filters, like `{{sender|upper}}`. The filters are `upper`, `lower`,
+/// `trim`, `html`, `markdown`, `latex`, and `json`, where the last four escape text for those
+/// formats.
+///
+/// Sections show text only if a field isn't empty, as in `{{#sender}}{{sender}}: {{/sender}}`, or
+/// only if it is empty, as in `{{^sender}}* {{/sender}}`.
+///
+/// # Examples
+/// A screenplay-style transcript.
+///
+/// ```rust,no_run
+/// let template = Template::parse("{{#sender}}{{sender|upper}}\n  {{/sender}}{{message}}\n").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+  nodes: Vec<Node>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+  Text(String),
+  Field(Field, Vec<Filter>),
+  Section {
+    field: Field,
+    inverted: bool,
+    body: Vec<Node>
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+  Timestamp,
+  Time(String),
+  Type,
+  Channel,
+  Sender,
+  SenderName,
+  Direction,
+  Counterpart,
+  Message,
+  MessagePlain,
+  MessageHtml,
+  MessageMarkdown,
+  Tag(String),
+  Key
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Filter {
+  Upper,
+  Lower,
+  Trim,
+  Html,
+  Markdown,
+  Latex,
+  Json
+}
+
+/// An error in a template.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplateError {
+  /// A `{{` without a matching `}}`, at this byte offset.
+  Unclosed(usize),
+  /// A field that doesn't exist.
+  UnknownField(String),
+  /// A filter that doesn't exist.
+  UnknownFilter(String),
+  /// A section that was closed without being opened, or closed with the wrong name.
+  UnexpectedClose(String),
+  /// A section that was never closed.
+  UnclosedSection(String)
+}
+
+impl Display for TemplateError {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match *self {
+      TemplateError::Unclosed(i) => write!(f, "unclosed {{{{ at byte {}", i),
+      TemplateError::UnknownField(ref x) => write!(f, "unknown field {}", x),
+      TemplateError::UnknownFilter(ref x) => write!(f, "unknown filter {}", x),
+      TemplateError::UnexpectedClose(ref x) => write!(f, "unexpected end of section {}", x),
+      TemplateError::UnclosedSection(ref x) => write!(f, "section {} was never closed", x)
+    }
+  }
+}
+
+impl Error for TemplateError {}
+
+impl Template {
+  /// Parses a template.
+  pub fn parse(source: &str) -> Result<Template, TemplateError> {
+    // Each open section, with its name, whether it's inverted, and the nodes before it.
+    let mut stack: Vec<(String, bool, Vec<Node>)> = Vec::new();
+    let mut nodes = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find("{{") {
+      if start > 0 {
+        nodes.push(Node::Text(rest[..start].to_owned()));
+      }
+      let offset = source.len() - rest.len() + start;
+      let end = opt_or!(rest[start..].find("}}"), return Err(TemplateError::Unclosed(offset)));
+      let tag = rest[start + 2..start + end].trim();
+      rest = &rest[start + end + 2..];
+      if tag.starts_with('#') || tag.starts_with('^') {
+        let name = tag[1..].trim().to_owned();
+        Template::parse_field(&name)?;
+        let before = ::std::mem::replace(&mut nodes, Vec::new());
+        stack.push((name, tag.starts_with('^'), before));
+      } else if tag.starts_with('/') {
+        let name = tag[1..].trim();
+        let (open, inverted, before) = opt_or!(stack.pop(), return Err(TemplateError::UnexpectedClose(name.to_owned())));
+        if open != name {
+          return Err(TemplateError::UnexpectedClose(name.to_owned()));
+        }
+        let body = ::std::mem::replace(&mut nodes, before);
+        nodes.push(Node::Section {
+          field: Template::parse_field(&open)?,
+          inverted,
+          body
+        });
+      } else {
+        let mut pieces = tag.split('|');
+        let field = Template::parse_field(pieces.next().unwrap_or("").trim())?;
+        let filters = pieces.map(|x| Template::parse_filter(x.trim())).collect::<Result<_, _>>()?;
+        nodes.push(Node::Field(field, filters));
+      }
+    }
+    if let Some((name, _, _)) = stack.pop() {
+      return Err(TemplateError::UnclosedSection(name));
+    }
+    if !rest.is_empty() {
+      nodes.push(Node::Text(rest.to_owned()));
+    }
+    Ok(Template {
+      nodes
+    })
+  }
+
+  fn parse_field(name: &str) -> Result<Field, TemplateError> {
+    let (name, arg) = match name.find(':') {
+      Some(i) => (&name[..i], Some(&name[i + 1..])),
+      None => (name, None)
+    };
+    let field = match (name, arg) {
+      ("timestamp", None) => Field::Timestamp,
+      ("time", None) => Field::Time(String::from("%Y-%m-%d %H:%M:%S")),
+      ("time", Some(format)) => Field::Time(format.to_owned()),
+      ("type", None) => Field::Type,
+      ("channel", None) => Field::Channel,
+      ("sender", None) => Field::Sender,
+      ("sender_name", None) => Field::SenderName,
+      ("direction", None) => Field::Direction,
+      ("counterpart", None) => Field::Counterpart,
+      ("message", None) => Field::Message,
+      ("message_plain", None) => Field::MessagePlain,
+      ("message_html", None) => Field::MessageHtml,
+      ("message_markdown", None) => Field::MessageMarkdown,
+      ("tag", Some(tag)) => Field::Tag(tag.to_owned()),
+      ("key", None) => Field::Key,
+      _ => return Err(TemplateError::UnknownField(name.to_owned()))
+    };
+    Ok(field)
+  }
+
+  fn parse_filter(name: &str) -> Result<Filter, TemplateError> {
+    let filter = match name {
+      "upper" => Filter::Upper,
+      "lower" => Filter::Lower,
+      "trim" => Filter::Trim,
+      "html" => Filter::Html,
+      "markdown" => Filter::Markdown,
+      "latex" => Filter::Latex,
+      "json" => Filter::Json,
+      _ => return Err(TemplateError::UnknownFilter(name.to_owned()))
+    };
+    Ok(filter)
+  }
+
+  /// Renders the template for an entry, using a template renderer's HTML and Markdown renderers for
+  /// the fields that need them.
+  pub fn render(&self, entry: &Entry, renderer: &TemplateRenderer) -> String {
+    let mut output = String::new();
+    render_nodes(&self.nodes, entry, renderer, &mut output);
+    output
+  }
+}
+
+fn render_nodes(nodes: &[Node], entry: &Entry, renderer: &TemplateRenderer, output: &mut String) {
+  for node in nodes {
+    match *node {
+      Node::Text(ref text) => output.push_str(text),
+      Node::Field(ref field, ref filters) => {
+        let value = filters.iter().fold(field_value(field, entry, renderer), |x, f| apply_filter(*f, &x));
+        output.push_str(&value);
+      },
+      Node::Section { ref field, inverted, ref body } => {
+        if field_value(field, entry, renderer).is_empty() == inverted {
+          render_nodes(body, entry, renderer, output);
+        }
+      }
+    }
+  }
+}
+
+fn field_value(field: &Field, entry: &Entry, renderer: &TemplateRenderer) -> String {
+  match *field {
+    Field::Timestamp => entry.timestamp.to_string(),
+    Field::Time(ref format) => format_timestamp(entry, format),
+    Field::Type => entry.message_type.to_string(),
+    Field::Channel => entry.message_type.snake_case_name(),
+    Field::Sender => entry.sender.as_ref().map(names::sender_display_text).unwrap_or_default(),
+    Field::SenderName => entry.sender_name().map(|x| names::name_display_text(&x)).unwrap_or_default(),
+    Field::Direction => match entry.tell_direction() {
+      Some(TellDirection::Incoming) => String::from("incoming"),
+      Some(TellDirection::Outgoing) => String::from("outgoing"),
+      None => String::new()
+    },
+    Field::Counterpart => entry.tell_counterpart().map(|x| names::name_display_text(&x)).unwrap_or_default(),
+    Field::Message => names::message_display_text(&entry.message),
+    Field::MessagePlain => names::message_plain_text(&entry.message),
+    Field::MessageHtml => entry.message.parts.iter().map(|x| renderer.html.render_part(x)).collect(),
+    Field::MessageMarkdown => entry.message.parts.iter().map(|x| renderer.markdown.render_part(x)).collect(),
+    Field::Tag(ref tag) => match entry.get_tag(tag) {
+      Some("") => String::from("yes"),
+      Some(value) => value.to_owned(),
+      None => String::new()
+    },
+    Field::Key => format!("{:x}", entry.dedup_key())
+  }
+}
+
+fn apply_filter(filter: Filter, text: &str) -> String {
+  match filter {
+    Filter::Upper => text.to_uppercase(),
+    Filter::Lower => text.to_lowercase(),
+    Filter::Trim => text.trim().to_owned(),
+    Filter::Html => html::escape(text),
+    Filter::Markdown => markdown::escape(text),
+    Filter::Latex => escape_latex(text),
+    Filter::Json => {
+      let quoted = ::serde_json::to_string(text).unwrap_or_default();
+      quoted[1..quoted.len() - 1].to_owned()
+    }
+  }
+}
+
+fn escape_latex(text: &str) -> String {
+  let mut latex = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+        latex.push('\\');
+        latex.push(c);
+      },
+      '~' => latex.push_str("\\textasciitilde{}"),
+      '^' => latex.push_str("\\textasciicircum{}"),
+      '\\' => latex.push_str("\\textbackslash{}"),
+      '\r' | '\n' => latex.push_str("\\\\\n"),
+      _ => latex.push(c)
+    }
+  }
+  latex
+}
+
+/// Renders entries with user-supplied templates, for formats there's no built-in renderer for,
+/// like forum posts, screenplay-style transcripts, or LaTeX.
+#[derive(Debug, Clone)]
+pub struct TemplateRenderer {
+  /// The template for each entry.
+  pub entry: Template,
+  /// Text at the start of a whole log.
+  pub header: String,
+  /// Text at the end of a whole log.
+  pub footer: String,
+  /// Text between entries in a whole log.
+  pub separator: String,
+  /// The renderer used for `{{message_html}}`.
+  pub html: HtmlRenderer,
+  /// The renderer used for `{{message_markdown}}`.
+  pub markdown: MarkdownRenderer
+}
+
+impl TemplateRenderer {
+  /// Create a renderer for an entry template, with no header or footer, that puts entries on their
+  /// own lines.
+  pub fn new(entry: Template) -> Self {
+    TemplateRenderer {
+      entry,
+      header: String::new(),
+      footer: String::new(),
+      separator: String::from("\n"),
+      html: HtmlRenderer::new(),
+      markdown: MarkdownRenderer::new()
+    }
+  }
+
+  /// Create a renderer from the source of an entry template.
+  pub fn parse(entry: &str) -> Result<Self, TemplateError> {
+    Template::parse(entry).map(TemplateRenderer::new)
+  }
+}
+
+impl Renderer for TemplateRenderer {
+  fn render_entry(&self, entry: &Entry) -> String {
+    self.entry.render(entry, self)
+  }
+
+  fn render_log<'a, I>(&self, entries: I) -> String
+    where I: IntoIterator<Item = &'a Entry>,
+          Self: Sized
+  {
+    let body = entries.into_iter().map(|x| self.render_entry(x)).collect::<Vec<_>>().join(&self.separator);
+    format!("{}{}{}", self.header, body, self.footer)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use messages::{Message, MessageType};
+  use messages::parts::{Part, NamePart};
+  use names::{NameDisplayPolicy, NameStyle, set_display_policy};
+
+  use std::collections::BTreeMap;
+  use std::sync::{Mutex, MutexGuard};
+
+  lazy_static! {
+    /// Held by tests that render, since the display policy is shared by every test.
+    static ref POLICY: Mutex<()> = Mutex::new(());
+  }
+
+  fn lock_policy() -> MutexGuard<'static, ()> {
+    POLICY.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  fn entry(sender: Option<&str>, text: &str) -> Entry {
+    Entry {
+      message_type: MessageType::Say,
+      timestamp: 0,
+      sender: sender.map(|x| Part::PlainText(x.to_owned())),
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  fn render(template: &str, entry: &Entry) -> String {
+    let _lock = lock_policy();
+    TemplateRenderer::parse(template).unwrap().render_entry(entry)
+  }
+
+  #[test]
+  fn names_follow_the_display_policy() {
+    let mut tell = entry(Some("Luna Kamaria"), "");
+    tell.message_type = MessageType::TellReceive;
+    tell.message = Message::new(vec![
+      Part::PlainText(String::from("ask ")),
+      NamePart::from_names("Rin Tohsaka", "Rin Tohsaka"),
+      Part::PlainText(String::from(" about it"))
+    ]);
+    let template = "{{sender}} {{sender_name}} {{counterpart}} {{message}} {{message_plain}} {{message_html}} {{message_markdown}}";
+    let renderer = TemplateRenderer::parse(template).unwrap();
+    let output = {
+      let _lock = lock_policy();
+      set_display_policy(NameDisplayPolicy { style: NameStyle::Pseudonym, strip_world: false });
+      let output = renderer.render_entry(&tell);
+      set_display_policy(NameDisplayPolicy::default());
+      output
+    };
+    for name in &["Luna", "Kamaria", "Rin", "Tohsaka"] {
+      assert!(!output.contains(name), "{} is in {}", name, output);
+    }
+    assert!(output.contains("Player "));
+  }
+
+  #[test]
+  fn sections_show_text_if_a_field_is_not_empty() {
+    let template = "{{#sender}}{{sender}}: {{/sender}}{{^sender}}* {{/sender}}{{message}}";
+    assert_eq!(render(template, &entry(Some("Luna"), "hello")), "Luna: hello");
+    assert_eq!(render(template, &entry(None, "waves")), "* waves");
+  }
+
+  #[test]
+  fn sections_nest() {
+    let template = "{{#sender}}<{{#tag:spam}}spam from {{/tag:spam}}{{sender|lower}}>{{/sender}}";
+    let mut spam = entry(Some("Luna"), "buy gil");
+    spam.tag("spam", "");
+    assert_eq!(render(template, &spam), "<spam from luna>");
+    assert_eq!(render(template, &entry(Some("Luna"), "hello")), "<luna>");
+    assert_eq!(render(template, &entry(None, "hello")), "");
+  }
+
+  #[test]
+  fn sections_are_parsed_into_nodes() {
+    let template = Template::parse("a{{^sender}}b{{message}}{{/sender}}").unwrap();
+    assert_eq!(template.nodes, vec![
+      Node::Text(String::from("a")),
+      Node::Section {
+        field: Field::Sender,
+        inverted: true,
+        body: vec![
+          Node::Text(String::from("b")),
+          Node::Field(Field::Message, Vec::new())
+        ]
+      }
+    ]);
+  }
+
+  #[test]
+  fn mismatched_sections_are_errors() {
+    assert_eq!(Template::parse("{{#sender}}{{message}}"), Err(TemplateError::UnclosedSection(String::from("sender"))));
+    assert_eq!(Template::parse("{{message}}{{/sender}}"), Err(TemplateError::UnexpectedClose(String::from("sender"))));
+    assert_eq!(
+      Template::parse("{{#sender}}{{#message}}{{/sender}}{{/message}}"),
+      Err(TemplateError::UnexpectedClose(String::from("sender")))
+    );
+    assert_eq!(Template::parse("{{#nothing}}{{/nothing}}"), Err(TemplateError::UnknownField(String::from("nothing"))));
+    assert_eq!(Template::parse("ab{{#sender"), Err(TemplateError::Unclosed(2)));
+  }
+}
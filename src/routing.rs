@@ -0,0 +1,203 @@
+//! Routing entries to sinks by channel and filter
+//!
+//! Each sink in the `sinks` section of the configuration can be given the channels and filter it
+//! should receive, so one reader can send tells to one place, Free Company chat to another, and
+//! everything to disk. Sinks are built by kind from a [`SinkRegistry`](struct.SinkRegistry.html),
+//! which knows the built-in `file` and `stdout` kinds. Other kinds, such as webhooks, are
+//! registered by the program using the library.
+
+use archive::jsonl;
+use config::{Config, SinkConfig};
+use filter::EntryFilter;
+use messages::entries::Entry;
+use pipeline::{Pipeline, Sink};
+use rate::ThrottledSink;
+use schema::Metadata;
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// A sink that only passes on entries matching a filter.
+pub struct FilteredSink<S> {
+  filter: EntryFilter,
+  sink: S
+}
+
+impl<S: Sink> FilteredSink<S> {
+  pub fn new(filter: EntryFilter, sink: S) -> Self {
+    FilteredSink {
+      filter,
+      sink
+    }
+  }
+}
+
+impl<S: Sink> Sink for FilteredSink<S> {
+  fn handle(&mut self, entry: &Entry) {
+    if self.filter.matches(entry) {
+      self.sink.handle(entry);
+    }
+  }
+
+  fn flush(&mut self) {
+    self.sink.flush();
+  }
+
+  fn sync(&mut self) {
+    self.sink.sync();
+  }
+}
+
+/// A sink that appends entries to a JSON lines file.
+pub struct FileSink {
+  writer: BufWriter<File>
+}
+
+impl FileSink {
+  /// Opens a file for appending, creating it if it doesn't exist.
+  ///
+  /// New files start with a metadata header for this version of the crate.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FileSink> {
+    FileSink::with_metadata(path, &Metadata::new(None))
+  }
+
+  /// Opens a file for appending, starting it with `metadata` if it is new.
+  ///
+  /// Existing files are checked to make sure their schema matches the entries that will be added.
+  pub fn with_metadata<P: AsRef<Path>>(path: P, metadata: &Metadata) -> io::Result<FileSink> {
+    let path = path.as_ref();
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    if writer.get_ref().metadata()?.len() == 0 {
+      jsonl::write_header(&mut writer, metadata)?;
+    } else if let Some(existing) = jsonl::read_metadata(path)? {
+      if existing.schema_version != metadata.schema_version {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "file has a different schema version"));
+      }
+    }
+    Ok(FileSink {
+      writer
+    })
+  }
+}
+
+impl Sink for FileSink {
+  fn handle(&mut self, entry: &Entry) {
+    let _ = jsonl::write_entries(&mut self.writer, Some(entry));
+  }
+
+  fn flush(&mut self) {
+    let _ = self.writer.flush();
+  }
+
+  fn sync(&mut self) {
+    let _ = self.writer.flush();
+  }
+}
+
+/// A sink that prints entries to stdout as JSON, one per line.
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+  fn handle(&mut self, entry: &Entry) {
+    let stdout = io::stdout();
+    let _ = jsonl::write_entries(&mut stdout.lock(), Some(entry));
+  }
+}
+
+/// A function building a sink from its configuration.
+pub type SinkFactory = Box<dyn Fn(&SinkConfig) -> Result<Box<dyn Sink>, RoutingError>>;
+
+/// The kinds of sinks that can be built from configuration.
+pub struct SinkRegistry {
+  factories: BTreeMap<String, SinkFactory>
+}
+
+impl SinkRegistry {
+  /// Create a registry with the built-in `file` and `stdout` kinds.
+  pub fn new() -> Self {
+    let mut registry = SinkRegistry::empty();
+    registry.register("file", |config| {
+      let path = opt_or!(config.path.as_ref(), return Err(RoutingError::MissingPath));
+      let sink = FileSink::open(path).map_err(RoutingError::Io)?;
+      Ok(Box::new(sink) as Box<dyn Sink>)
+    });
+    registry.register("stdout", |_| Ok(Box::new(StdoutSink) as Box<dyn Sink>));
+    registry
+  }
+
+  /// Create a registry without any kinds.
+  pub fn empty() -> Self {
+    SinkRegistry {
+      factories: BTreeMap::new()
+    }
+  }
+
+  /// Registers a kind of sink, replacing any registered with the same name.
+  pub fn register<S, F>(&mut self, kind: S, factory: F)
+    where S: Into<String>,
+          F: Fn(&SinkConfig) -> Result<Box<dyn Sink>, RoutingError> + 'static
+  {
+    self.factories.insert(kind.into(), Box::new(factory));
+  }
+
+  /// Builds a sink from its configuration.
+  pub fn build(&self, config: &SinkConfig) -> Result<Box<dyn Sink>, RoutingError> {
+    let factory = opt_or!(self.factories.get(&config.kind), return Err(RoutingError::UnknownKind(config.kind.clone())));
+    factory(config)
+  }
+}
+
+impl Default for SinkRegistry {
+  fn default() -> Self {
+    SinkRegistry::new()
+  }
+}
+
+/// Builds every configured sink and adds it to the pipeline behind its route filter.
+///
+/// The configuration's default filter and blocklist are not applied here; use
+/// [`Pipeline::set_filter`](../pipeline/struct.Pipeline.html#method.set_filter) for those. Returns
+/// the number of sinks added, or the first error along with the name of the sink that caused it.
+pub fn add_routes(pipeline: &mut Pipeline, config: &Config, registry: &SinkRegistry) -> Result<usize, (String, RoutingError)> {
+  for (name, sink_config) in &config.sinks {
+    let mut sink = registry.build(sink_config).map_err(|e| (name.clone(), e))?;
+    if let Some(limit) = sink_config.throttle {
+      sink = Box::new(ThrottledSink::new(limit, sink));
+    }
+    pipeline.add_sink(FilteredSink::new(sink_config.route_filter(), sink));
+  }
+  Ok(config.sinks.len())
+}
+
+/// An error encountered while building a sink.
+#[derive(Debug)]
+pub enum RoutingError {
+  /// No sink of this kind is registered.
+  UnknownKind(String),
+  /// The sink needs a path, but none was configured.
+  MissingPath,
+  /// The sink needs a URL, but none was configured.
+  MissingUrl,
+  /// The sink could not be opened.
+  Io(io::Error)
+}
+
+impl Display for RoutingError {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match *self {
+      RoutingError::UnknownKind(ref k) => write!(f, "unknown sink type: {}", k),
+      RoutingError::MissingPath => write!(f, "sink needs a path"),
+      RoutingError::MissingUrl => write!(f, "sink needs a url"),
+      RoutingError::Io(ref e) => write!(f, "could not open sink: {}", e)
+    }
+  }
+}
+
+impl Error for RoutingError {}
@@ -0,0 +1,201 @@
+//! Samples of entries
+//!
+//! Years of logs are too many entries to look through by hand or to check into a repository as test
+//! data. A [`Sampler`](struct.Sampler.html) takes a sample instead, either at random or by taking
+//! every so many entries, with a quota for each channel, each day, or each channel on each day, so
+//! quiet channels and days aren't drowned out by busy ones.
+//!
+//! Samples can be taken from any entries with [`sample`](struct.Sampler.html#method.sample), or from
+//! an archive with [`sample_archive`](struct.Sampler.html#method.sample_archive), which can read just
+//! some of its files to avoid a full scan.
+//!
+//! Random samples use a seeded generator, so the same seed and entries always give the same sample.
+//!
+//! # Examples
+//! A corpus of 20 entries from each channel on each day of June 2017, from at most 10 files.
+//!
+//! ```rust,no_run
+//! let archive = Archive::open("logs").unwrap();
+//! let mut sampler = Sampler::random(20, 1);
+//! sampler.by_channel = true;
+//! sampler.by_day = true;
+//! sampler.range = 1496275200..1498867200;
+//! sampler.max_files = Some(10);
+//! let corpus = sampler.sample_archive(&archive);
+//! ```
+
+use archive::Archive;
+use filter::EntryFilter;
+use messages::MessageType;
+use messages::entries::Entry;
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// How entries are chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleMethod {
+  /// Entries are chosen at random, each as likely as any other in its group.
+  Random {
+    /// The seed for the random number generator.
+    seed: u64
+  },
+  /// Every `every`th entry of each group is chosen, starting with the first, until the quota is met.
+  Systematic {
+    every: usize
+  }
+}
+
+/// Takes samples of entries.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+  pub method: SampleMethod,
+  /// The most entries to take from each group, or in total if entries aren't grouped.
+  pub quota: usize,
+  /// Whether each channel is its own group.
+  pub by_channel: bool,
+  /// Whether each day, in UTC, is its own group.
+  pub by_day: bool,
+  /// Entries that don't match this filter are never chosen.
+  pub filter: EntryFilter,
+  /// The time range entries are chosen from.
+  pub range: Range<u32>,
+  /// The most files to read from an archive, chosen the same way as entries. Every file in the
+  /// range is read if this is `None`.
+  pub max_files: Option<usize>
+}
+
+impl Sampler {
+  /// Create a sampler taking `quota` entries at random.
+  pub fn random(quota: usize, seed: u64) -> Self {
+    Sampler::new(SampleMethod::Random { seed }, quota)
+  }
+
+  /// Create a sampler taking every `every`th entry, up to `quota` entries.
+  pub fn systematic(quota: usize, every: usize) -> Self {
+    Sampler::new(SampleMethod::Systematic { every: every.max(1) }, quota)
+  }
+
+  fn new(method: SampleMethod, quota: usize) -> Self {
+    Sampler {
+      method,
+      quota,
+      by_channel: false,
+      by_day: false,
+      filter: EntryFilter::All,
+      range: 0..::std::u32::MAX,
+      max_files: None
+    }
+  }
+
+  /// Takes a sample of entries, returned in timestamp order.
+  ///
+  /// Only the entries chosen so far are held in memory, so this works on any number of entries.
+  pub fn sample<I>(&self, entries: I) -> Vec<Entry>
+    where I: IntoIterator<Item = Entry>
+  {
+    let mut rng = Rng::new(self.method);
+    let mut groups: HashMap<(Option<MessageType>, Option<u32>), Group> = HashMap::new();
+    for entry in entries {
+      if entry.timestamp < self.range.start || entry.timestamp >= self.range.end || !self.filter.matches(&entry) {
+        continue;
+      }
+      let key = (
+        if self.by_channel { Some(entry.message_type) } else { None },
+        if self.by_day { Some(entry.timestamp / 86400) } else { None }
+      );
+      let group = groups.entry(key).or_insert_with(Group::default);
+      group.seen += 1;
+      match self.method {
+        SampleMethod::Random { .. } => {
+          // Reservoir sampling: the nth entry replaces a chosen one with chance quota / n.
+          if group.chosen.len() < self.quota {
+            group.chosen.push(entry);
+          } else {
+            let i = rng.below(group.seen);
+            if i < self.quota {
+              group.chosen[i] = entry;
+            }
+          }
+        },
+        SampleMethod::Systematic { every } => {
+          if (group.seen - 1) % every == 0 && group.chosen.len() < self.quota {
+            group.chosen.push(entry);
+          }
+        }
+      }
+    }
+    let mut sample: Vec<Entry> = groups.into_iter().flat_map(|(_, g)| g.chosen).collect();
+    sample.sort_by_key(|x| x.timestamp);
+    sample
+  }
+
+  /// Takes a sample of the entries in an archive, returned in timestamp order.
+  ///
+  /// Only files overlapping the range are read, and only `max_files` of those if it's set. Files
+  /// that can't be read are skipped.
+  pub fn sample_archive(&self, archive: &Archive) -> Vec<Entry> {
+    let files: Vec<_> = archive.files().iter().filter(|x| x.overlaps(&self.range)).collect();
+    let files = match self.max_files {
+      Some(max) if max < files.len() => {
+        let mut indices: Vec<usize> = match self.method {
+          SampleMethod::Random { .. } => {
+            let mut rng = Rng::new(self.method);
+            let mut indices: Vec<usize> = (0..files.len()).collect();
+            // A partial shuffle puts a random choice of files at the front.
+            for i in 0..max {
+              let j = i + rng.below(files.len() - i);
+              indices.swap(i, j);
+            }
+            indices.truncate(max);
+            indices
+          },
+          // Spread the files out evenly over the range.
+          SampleMethod::Systematic { .. } => (0..max).map(|i| i * files.len() / max).collect()
+        };
+        indices.sort();
+        indices.into_iter().map(|i| files[i]).collect()
+      },
+      _ => files
+    };
+    let entries = files.into_iter()
+      .flat_map(|x| x.format.read_entries(archive.root().join(&x.path)).unwrap_or_default());
+    self.sample(entries)
+  }
+}
+
+#[derive(Default)]
+struct Group {
+  seen: usize,
+  chosen: Vec<Entry>
+}
+
+/// A small xorshift generator, which is plenty for choosing samples.
+struct Rng {
+  state: u64
+}
+
+impl Rng {
+  fn new(method: SampleMethod) -> Self {
+    let seed = match method {
+      SampleMethod::Random { seed } => seed,
+      SampleMethod::Systematic { .. } => 0
+    };
+    // The state can't be zero, so mix the seed with an odd constant.
+    Rng {
+      state: (seed ^ 0x9e37_79b9_7f4a_7c15).max(1)
+    }
+  }
+
+  fn next(&mut self) -> u64 {
+    self.state ^= self.state << 13;
+    self.state ^= self.state >> 7;
+    self.state ^= self.state << 17;
+    self.state
+  }
+
+  /// A number from 0 up to but not including `n`.
+  fn below(&mut self, n: usize) -> usize {
+    (self.next() % n as u64) as usize
+  }
+}
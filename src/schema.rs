@@ -0,0 +1,118 @@
+//! Metadata headers for exported files
+//!
+//! Files written by this crate start with a header describing how they were made, so archives built
+//! up over years by different versions can still be read. In JSON lines files the header is the
+//! first line, wrapped in an object with a single `ffxiv_reader` key so it can never be mistaken for
+//! an entry:
+//!
+//! ```text
+//! {"ffxiv_reader":{"schema_version":1,"crate_version":"0.1.0","game_patch":"4.0","source":"memory","created":1498566896}}
+//! ```
+//!
+//! Files written before headers existed are schema version 0. Their entries are read the same way as
+//! version 1 entries.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the format entries are serialized in.
+///
+/// This is increased whenever a change means older versions could not read new files.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The version of this crate.
+pub const CRATE_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// How a file was made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+  /// The version of the format the entries are in.
+  pub schema_version: u32,
+  /// The version of the crate that wrote the file.
+  pub crate_version: String,
+  /// The game patch the entries were captured on, like `4.0`, if known.
+  #[serde(default)]
+  pub game_patch: Option<String>,
+  /// Where the entries came from, like `memory`, `act`, or `import`, if known.
+  #[serde(default)]
+  pub source: Option<String>,
+  /// When the file was written, in seconds since the epoch.
+  #[serde(default)]
+  pub created: u64
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+  ffxiv_reader: Metadata
+}
+
+impl Metadata {
+  /// Metadata for a file written now by this version of the crate.
+  pub fn new(source: Option<&str>) -> Self {
+    Metadata {
+      schema_version: SCHEMA_VERSION,
+      crate_version: CRATE_VERSION.to_owned(),
+      game_patch: None,
+      source: source.map(ToOwned::to_owned),
+      created: SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0)
+    }
+  }
+
+  /// Metadata for rewriting a file with this version of the crate, keeping where its entries came
+  /// from.
+  pub fn upgraded(&self) -> Self {
+    Metadata {
+      game_patch: self.game_patch.clone(),
+      source: self.source.clone(),
+      .. Metadata::new(None)
+    }
+  }
+
+  /// Parses a header line, returning `None` if the line is not a header.
+  pub fn from_line(line: &str) -> Option<Metadata> {
+    if !Metadata::is_header(line) {
+      return None;
+    }
+    ::serde_json::from_str::<Header>(line).ok().map(|x| x.ffxiv_reader)
+  }
+
+  /// Checks if a line looks like a header, without fully parsing it.
+  pub fn is_header(line: &str) -> bool {
+    line.trim_start().trim_start_matches('{').trim_start().starts_with("\"ffxiv_reader\"")
+  }
+
+  /// Serializes the metadata as a header line, without a trailing newline.
+  pub fn to_line(&self) -> String {
+    let header = Header {
+      ffxiv_reader: self.clone()
+    };
+    ::serde_json::to_string(&header).unwrap()
+  }
+
+  /// Checks if this version of the crate can read a file with this metadata.
+  pub fn check(&self) -> Result<(), SchemaError> {
+    if self.schema_version > SCHEMA_VERSION {
+      return Err(SchemaError::TooNew(self.schema_version));
+    }
+    Ok(())
+  }
+}
+
+/// An error from a file this version of the crate can't read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+  /// The file was written with a newer schema version, which this crate doesn't understand.
+  TooNew(u32)
+}
+
+impl Display for SchemaError {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match *self {
+      SchemaError::TooNew(v) => write!(f, "schema version {} is newer than the supported version {}", v, SCHEMA_VERSION)
+    }
+  }
+}
+
+impl Error for SchemaError {}
@@ -0,0 +1,308 @@
+//! Full-text search over archives
+//!
+//! A [`SearchIndex`](struct.SearchIndex.html) is a [tantivy](https://github.com/tantivy-search/tantivy)
+//! index of the entries in an [`Archive`](../archive/struct.Archive.html), searchable by words in
+//! the message, sender, channel, and time. Keyword queries over years of logs take milliseconds
+//! instead of a scan of every file.
+//!
+//! Messages are split into words with the [default tokenizer](../tokenize/fn.default_tokenizer.html)
+//! before they're indexed, and queries are split the same way, so Japanese can be searched too.
+//!
+//! This needs the `search` feature.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let archive = Archive::open("logs").unwrap();
+//! let mut index = SearchIndex::open("logs/.search").unwrap();
+//! index.update(&archive).unwrap();
+//! let mut query = SearchQuery::new("savage clear");
+//! query.channels = vec![MessageType::FreeCompany];
+//! for hit in index.search(&query).unwrap() {
+//!   println!("{} {:?}: {}", hit.timestamp, hit.sender, hit.text);
+//! }
+//! ```
+
+extern crate tantivy;
+
+use self::tantivy::{Document, Index, IndexWriter, Term};
+use self::tantivy::collector::TopCollector;
+use self::tantivy::query::{AllQuery, BooleanQuery, Occur, Query, RangeQuery, TermQuery};
+use self::tantivy::schema::{Field, IndexRecordOption, Schema, SchemaBuilder, Value, INT_INDEXED, INT_STORED, STORED, STRING, TEXT};
+
+use archive::{Archive, FileMetadata};
+use filter::EntryFilter;
+use messages::{MessageType, HasDisplayText};
+use messages::entries::Entry;
+use tokenize::{Tokenizer, default_tokenizer};
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// The name of the file in an index directory listing which archive files have been indexed.
+pub const INDEXED_FILES: &'static str = "ffxiv_reader_files.json";
+
+/// How much memory the index writer may use, in bytes.
+const WRITER_HEAP: usize = 50_000_000;
+
+/// What to search for.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+  /// Words that must all be in the message.
+  pub text: String,
+  /// The real name of the sender, ignoring case.
+  pub sender: Option<String>,
+  /// The channels to search. Every channel is searched if this is empty.
+  pub channels: Vec<MessageType>,
+  /// The time range to search.
+  pub range: Option<Range<u32>>,
+  /// The most hits to return.
+  pub limit: usize
+}
+
+impl SearchQuery {
+  /// Create a query for messages with every word in `text`, returning at most 100 hits.
+  pub fn new<S: Into<String>>(text: S) -> Self {
+    SearchQuery {
+      text: text.into(),
+      sender: None,
+      channels: Vec::new(),
+      range: None,
+      limit: 100
+    }
+  }
+}
+
+/// An entry found by a search.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+  /// How well the entry matched. Higher is better.
+  pub score: f32,
+  pub timestamp: u32,
+  pub message_type: MessageType,
+  /// The real name of the sender, if there was one.
+  pub sender: Option<String>,
+  /// The display text of the message.
+  pub text: String,
+  /// The `dedup_key` of the entry.
+  pub key: u64
+}
+
+impl SearchHit {
+  /// Reads the whole entry from the archive it was indexed from.
+  pub fn entry(&self, archive: &Archive) -> Option<Entry> {
+    archive.query(self.timestamp..self.timestamp.saturating_add(1), EntryFilter::All)
+      .find(|x| x.dedup_key() == self.key)
+  }
+}
+
+struct Fields {
+  words: Field,
+  text: Field,
+  sender: Field,
+  sender_name: Field,
+  channel: Field,
+  time: Field,
+  key: Field,
+  file: Field
+}
+
+impl Fields {
+  fn schema() -> Schema {
+    let mut builder = SchemaBuilder::default();
+    builder.add_text_field("words", TEXT);
+    builder.add_text_field("text", STORED);
+    builder.add_text_field("sender", STRING);
+    builder.add_text_field("sender_name", STORED);
+    builder.add_u64_field("channel", INT_INDEXED | INT_STORED);
+    builder.add_u64_field("time", INT_INDEXED | INT_STORED);
+    builder.add_u64_field("key", INT_STORED);
+    builder.add_text_field("file", STRING);
+    builder.build()
+  }
+
+  fn from_schema(schema: &Schema) -> io::Result<Fields> {
+    let field = |name: &str| schema.get_field(name)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("search index has no {} field", name)));
+    Ok(Fields {
+      words: field("words")?,
+      text: field("text")?,
+      sender: field("sender")?,
+      sender_name: field("sender_name")?,
+      channel: field("channel")?,
+      time: field("time")?,
+      key: field("key")?,
+      file: field("file")?
+    })
+  }
+}
+
+/// A full-text index of entries.
+pub struct SearchIndex {
+  path: PathBuf,
+  index: Index,
+  fields: Fields,
+  tokenizer: Box<dyn Tokenizer>,
+  files: HashMap<PathBuf, (u64, u64)>
+}
+
+impl SearchIndex {
+  /// Opens the index in the directory `path`, creating it if it doesn't exist.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<SearchIndex> {
+    let path = path.as_ref().to_path_buf();
+    fs::create_dir_all(&path)?;
+    let index = match Index::open_in_dir(&path) {
+      Ok(i) => i,
+      Err(_) => Index::create_in_dir(&path, Fields::schema()).map_err(to_io)?
+    };
+    let fields = Fields::from_schema(&index.schema())?;
+    let files = load_files(&path).unwrap_or_default();
+    Ok(SearchIndex {
+      path,
+      index,
+      fields,
+      tokenizer: default_tokenizer(),
+      files
+    })
+  }
+
+  /// Indexes the files in an archive that are new or have changed since they were last indexed, and
+  /// removes files that no longer exist. Returns the number of files indexed.
+  pub fn update(&mut self, archive: &Archive) -> io::Result<usize> {
+    let mut writer = self.index.writer(WRITER_HEAP).map_err(to_io)?;
+    let mut indexed = 0;
+    let mut seen = HashMap::new();
+    for file in archive.files() {
+      let version = (file.size, file.modified);
+      seen.insert(file.path.clone(), version);
+      if self.files.get(&file.path) == Some(&version) {
+        continue;
+      }
+      self.index_file(&mut writer, archive, file)?;
+      indexed += 1;
+    }
+    for path in self.files.keys().filter(|x| !seen.contains_key(*x)) {
+      writer.delete_term(Term::from_field_text(self.fields.file, &path.to_string_lossy()));
+    }
+    writer.commit().map_err(to_io)?;
+    self.files = seen;
+    save_files(&self.path, &self.files)?;
+    Ok(indexed)
+  }
+
+  /// Adds entries that aren't from an archive, like ones being captured live.
+  ///
+  /// Entries added this way are never removed by `update`.
+  pub fn add<'a, I>(&mut self, entries: I) -> io::Result<()>
+    where I: IntoIterator<Item = &'a Entry>
+  {
+    let mut writer = self.index.writer(WRITER_HEAP).map_err(to_io)?;
+    for entry in entries {
+      writer.add_document(self.document(entry, ""));
+    }
+    writer.commit().map(|_| ()).map_err(to_io)
+  }
+
+  /// Searches the index, returning the best hits first.
+  pub fn search(&self, query: &SearchQuery) -> io::Result<Vec<SearchHit>> {
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    for word in self.tokenizer.tokens(&query.text) {
+      let term = Term::from_field_text(self.fields.words, &word);
+      clauses.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs))));
+    }
+    if let Some(ref sender) = query.sender {
+      let term = Term::from_field_text(self.fields.sender, &sender.to_lowercase());
+      clauses.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+    }
+    if !query.channels.is_empty() {
+      let channels: Vec<(Occur, Box<dyn Query>)> = query.channels.iter()
+        .map(|x| {
+          let term = Term::from_field_u64(self.fields.channel, x.id() as u64);
+          (Occur::Should, Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>)
+        })
+        .collect();
+      clauses.push((Occur::Must, Box::new(BooleanQuery::from(channels))));
+    }
+    if let Some(ref range) = query.range {
+      clauses.push((Occur::Must, Box::new(RangeQuery::new_u64(self.fields.time, range.start as u64..range.end as u64))));
+    }
+    if clauses.is_empty() {
+      clauses.push((Occur::Must, Box::new(AllQuery)));
+    }
+    let query_all = BooleanQuery::from(clauses);
+
+    self.index.load_searchers().map_err(to_io)?;
+    let searcher = self.index.searcher();
+    let mut collector = TopCollector::with_limit(query.limit.max(1));
+    searcher.search(&query_all, &mut collector).map_err(to_io)?;
+    let mut hits = Vec::new();
+    for (score, address) in collector.score_docs() {
+      let doc = searcher.doc(address).map_err(to_io)?;
+      let sender = doc.get_first(self.fields.sender_name).and_then(|x| match *x {
+        Value::Str(ref s) if !s.is_empty() => Some(s.clone()),
+        _ => None
+      });
+      hits.push(SearchHit {
+        score,
+        timestamp: get_u64(&doc, self.fields.time) as u32,
+        message_type: MessageType::from(get_u64(&doc, self.fields.channel) as u8),
+        sender,
+        text: doc.get_first(self.fields.text).and_then(|x| x.text()).unwrap_or("").to_owned(),
+        key: get_u64(&doc, self.fields.key)
+      });
+    }
+    Ok(hits)
+  }
+
+  fn index_file(&self, writer: &mut IndexWriter, archive: &Archive, file: &FileMetadata) -> io::Result<()> {
+    let name = file.path.to_string_lossy().into_owned();
+    writer.delete_term(Term::from_field_text(self.fields.file, &name));
+    for entry in file.format.read_entries(archive.root().join(&file.path))? {
+      writer.add_document(self.document(&entry, &name));
+    }
+    Ok(())
+  }
+
+  fn document(&self, entry: &Entry, file: &str) -> Document {
+    let text = entry.message.display_text();
+    let mut doc = Document::default();
+    doc.add_text(self.fields.words, &self.tokenizer.tokens(&entry.message.plain_text()).join(" "));
+    doc.add_text(self.fields.text, &text);
+    // Senders are indexed lowercased, so they can be matched ignoring case.
+    let sender = entry.sender_name().unwrap_or_default();
+    doc.add_text(self.fields.sender, &sender.to_lowercase());
+    doc.add_text(self.fields.sender_name, &sender);
+    doc.add_u64(self.fields.channel, entry.message_type.id() as u64);
+    doc.add_u64(self.fields.time, entry.timestamp as u64);
+    doc.add_u64(self.fields.key, entry.dedup_key());
+    doc.add_text(self.fields.file, file);
+    doc
+  }
+}
+
+fn get_u64(doc: &Document, field: Field) -> u64 {
+  match doc.get_first(field) {
+    Some(&Value::U64(x)) => x,
+    _ => 0
+  }
+}
+
+fn to_io<E: ::std::fmt::Display>(e: E) -> io::Error {
+  io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+fn load_files(path: &Path) -> Option<HashMap<PathBuf, (u64, u64)>> {
+  let mut data = String::new();
+  try_or!(File::open(path.join(INDEXED_FILES)).and_then(|mut f| f.read_to_string(&mut data)), return None);
+  let files: Vec<(PathBuf, u64, u64)> = ::serde_json::from_str(&data).ok()?;
+  Some(files.into_iter().map(|(p, size, modified)| (p, (size, modified))).collect())
+}
+
+fn save_files(path: &Path, files: &HashMap<PathBuf, (u64, u64)>) -> io::Result<()> {
+  let list: Vec<(&PathBuf, u64, u64)> = files.iter().map(|(p, &(size, modified))| (p, size, modified)).collect();
+  let data = ::serde_json::to_string(&list).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+  File::create(path.join(INDEXED_FILES))?.write_all(data.as_bytes())
+}
@@ -0,0 +1,367 @@
+//! Finding nearly identical messages
+//!
+//! Advertisements and spam get repeated with small changes, like a different time or an extra
+//! emoji, so they never have the same [`dedup_key`](../messages/entries/struct.Entry.html#method.dedup_key).
+//! A [`SimilarityIndex`](struct.SimilarityIndex.html) groups messages whose text is nearly the same
+//! into [`Clusters`](struct.Cluster.html) instead.
+//!
+//! Text is split into overlapping character shingles and summarized with MinHash. Signatures are
+//! then banded for locality-sensitive hashing, so each message is only compared against clusters it
+//! is likely to belong to. This keeps adding a message fast no matter how many clusters there are.
+//!
+//! # Examples
+//! This counts how many times each advertisement was shouted.
+//!
+//! ```rust,no_run
+//! let mut index = SimilarityIndex::new();
+//! for entry in archive.query(range, EntryFilter::MessageTypes(vec![MessageType::Shout])) {
+//!   index.add(&entry);
+//! }
+//! for cluster in index.clusters().iter().filter(|x| x.count() > 10) {
+//!   println!("{} times: {}", cluster.count(), cluster.text);
+//! }
+//! ```
+
+use messages::entries::Entry;
+
+use std::collections::{BTreeMap, HashMap};
+
+/// The length of the character shingles text is split into.
+pub const SHINGLE_LENGTH: usize = 4;
+
+/// A MinHash signature of some text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature(pub Vec<u64>);
+
+impl Signature {
+  /// Estimates the Jaccard similarity of the shingles behind two signatures, from 0 to 1.
+  pub fn similarity(&self, other: &Signature) -> f64 {
+    if self.0.is_empty() || self.0.len() != other.0.len() {
+      return 0.0;
+    }
+    let same = self.0.iter().zip(&other.0).filter(|&(a, b)| a == b).count();
+    same as f64 / self.0.len() as f64
+  }
+}
+
+/// Computes MinHash signatures.
+#[derive(Debug, Clone)]
+pub struct MinHasher {
+  seeds: Vec<u64>
+}
+
+impl MinHasher {
+  /// Create a hasher making signatures of `hashes` values.
+  ///
+  /// The seeds are fixed, so signatures from different hashers of the same size can be compared.
+  pub fn new(hashes: usize) -> Self {
+    let mut state = 0x9e3779b97f4a7c15;
+    let seeds = (0..hashes).map(|_| {
+      state = splitmix(state);
+      state
+    }).collect();
+    MinHasher {
+      seeds
+    }
+  }
+
+  /// The signature of some text.
+  pub fn signature(&self, text: &str) -> Signature {
+    let shingles = shingles(text);
+    let values = self.seeds.iter()
+      .map(|seed| shingles.iter().map(|x| splitmix(x ^ seed)).min().unwrap_or(::std::u64::MAX))
+      .collect();
+    Signature(values)
+  }
+}
+
+/// Hashes of the overlapping character shingles in text, after normalizing it.
+///
+/// Text is lowercased and only letters and digits are kept, with runs of anything else becoming
+/// single spaces.
+pub fn shingles(text: &str) -> Vec<u64> {
+  let chars: Vec<char> = normalize(text).chars().collect();
+  if chars.len() <= SHINGLE_LENGTH {
+    return vec![hash_chars(&chars)];
+  }
+  let mut hashes: Vec<u64> = chars.windows(SHINGLE_LENGTH).map(hash_chars).collect();
+  hashes.sort();
+  hashes.dedup();
+  hashes
+}
+
+fn normalize(text: &str) -> String {
+  let mut normal = String::with_capacity(text.len());
+  let mut space = false;
+  for c in text.chars() {
+    if c.is_alphanumeric() {
+      if space && !normal.is_empty() {
+        normal.push(' ');
+      }
+      space = false;
+      normal.extend(c.to_lowercase());
+    } else {
+      space = true;
+    }
+  }
+  normal
+}
+
+fn hash_chars(chars: &[char]) -> u64 {
+  // FNV-1a, like Entry::dedup_key.
+  let mut hash = 0xcbf29ce484222325;
+  for c in chars {
+    hash ^= *c as u64;
+    hash = hash.wrapping_mul(0x100000001b3);
+  }
+  hash
+}
+
+fn splitmix(x: u64) -> u64 {
+  let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+  z ^ (z >> 31)
+}
+
+/// A group of nearly identical messages.
+#[derive(Debug, Clone)]
+pub struct Cluster {
+  /// The text of the first message in the cluster.
+  pub text: String,
+  /// The signature of the first message in the cluster.
+  pub signature: Signature,
+  /// When each message in the cluster was sent.
+  pub timestamps: Vec<u32>,
+  /// How many messages in the cluster each sender sent.
+  pub senders: BTreeMap<String, usize>
+}
+
+impl Cluster {
+  /// The number of messages in the cluster.
+  pub fn count(&self) -> usize {
+    self.timestamps.len()
+  }
+
+  /// The earliest timestamp in the cluster.
+  pub fn first(&self) -> Option<u32> {
+    self.timestamps.iter().cloned().min()
+  }
+
+  /// The latest timestamp in the cluster.
+  pub fn last(&self) -> Option<u32> {
+    self.timestamps.iter().cloned().max()
+  }
+}
+
+/// An index grouping nearly identical messages into clusters.
+///
+/// Clusters are kept forever unless a `window` is set, which is right for a one-off look at an
+/// archive. Anything watching a live log should set one so old clusters are evicted.
+#[derive(Debug, Clone)]
+pub struct SimilarityIndex {
+  /// How similar a message must be to a cluster's first message to join it, from 0 to 1.
+  pub threshold: f64,
+  /// Messages with fewer letters and digits than this are ignored, since short messages are often
+  /// the same by chance.
+  pub min_length: usize,
+  /// How long, in seconds, a cluster is kept after its latest message. Clusters are checked at most
+  /// once per window, so they may be kept for up to twice as long.
+  pub window: Option<u32>,
+  hasher: MinHasher,
+  bands: usize,
+  rows: usize,
+  last_sweep: u32,
+  buckets: HashMap<(usize, u64), Vec<usize>>,
+  clusters: Vec<Cluster>
+}
+
+impl SimilarityIndex {
+  /// Create an index with 16 bands of 4 rows, which finds most messages above 0.7 similarity.
+  pub fn new() -> Self {
+    SimilarityIndex::with_bands(16, 4)
+  }
+
+  /// Create an index using signatures of `bands * rows` hashes.
+  ///
+  /// More bands find more similar pairs, at the cost of more comparisons. More rows make each band
+  /// stricter. Zero bands or rows are treated as one.
+  pub fn with_bands(bands: usize, rows: usize) -> Self {
+    let bands = bands.max(1);
+    let rows = rows.max(1);
+    SimilarityIndex {
+      threshold: 0.7,
+      min_length: 20,
+      window: None,
+      hasher: MinHasher::new(bands * rows),
+      bands,
+      rows,
+      last_sweep: 0,
+      buckets: HashMap::new(),
+      clusters: Vec::new()
+    }
+  }
+
+  /// Adds an entry's message, returning the index of the cluster it joined.
+  ///
+  /// Returns `None` if the message is too short. If a `window` is set, clusters that have fallen out
+  /// of it are evicted first, which changes the indices of the clusters after them.
+  pub fn add(&mut self, entry: &Entry) -> Option<usize> {
+    let text = entry.message.plain_text();
+    let signature = opt!(self.signature(&text));
+    if let Some(window) = self.window {
+      if entry.timestamp >= self.last_sweep.saturating_add(window) {
+        self.evict_before(entry.timestamp - window);
+        self.last_sweep = entry.timestamp;
+      }
+    }
+    let cluster = match self.find_signature(&signature) {
+      Some(i) => i,
+      None => {
+        let i = self.clusters.len();
+        for key in self.band_keys(&signature) {
+          self.buckets.entry(key).or_insert_with(Vec::new).push(i);
+        }
+        self.clusters.push(Cluster {
+          text,
+          signature,
+          timestamps: Vec::new(),
+          senders: BTreeMap::new()
+        });
+        i
+      }
+    };
+    let c = &mut self.clusters[cluster];
+    c.timestamps.push(entry.timestamp);
+    if let Some(sender) = entry.sender_name() {
+      *c.senders.entry(sender).or_insert(0) += 1;
+    }
+    Some(cluster)
+  }
+
+  /// Finds the cluster some text would join, without adding it.
+  pub fn find(&self, text: &str) -> Option<&Cluster> {
+    let signature = opt!(self.signature(text));
+    self.find_signature(&signature).map(|i| &self.clusters[i])
+  }
+
+  /// Every cluster, in the order they were created.
+  pub fn clusters(&self) -> &[Cluster] {
+    &self.clusters
+  }
+
+  /// Gets a cluster by the index returned from `add`.
+  pub fn cluster(&self, index: usize) -> Option<&Cluster> {
+    self.clusters.get(index)
+  }
+
+  /// Removes every cluster whose latest message is before `cutoff`, returning how many were removed.
+  ///
+  /// The remaining clusters keep their order, but their indices change.
+  pub fn evict_before(&mut self, cutoff: u32) -> usize {
+    let before = self.clusters.len();
+    self.clusters.retain(|x| x.last().map(|t| t >= cutoff).unwrap_or(false));
+    let removed = before - self.clusters.len();
+    if removed > 0 {
+      self.buckets.clear();
+      for i in 0..self.clusters.len() {
+        for key in self.band_keys(&self.clusters[i].signature) {
+          self.buckets.entry(key).or_insert_with(Vec::new).push(i);
+        }
+      }
+    }
+    removed
+  }
+
+  fn signature(&self, text: &str) -> Option<Signature> {
+    if text.chars().filter(|c| c.is_alphanumeric()).count() < self.min_length {
+      return None;
+    }
+    Some(self.hasher.signature(text))
+  }
+
+  fn find_signature(&self, signature: &Signature) -> Option<usize> {
+    let mut best = None;
+    for key in self.band_keys(signature) {
+      for &i in self.buckets.get(&key).map(|x| x.as_slice()).unwrap_or(&[]) {
+        let similarity = self.clusters[i].signature.similarity(signature);
+        if similarity >= self.threshold && best.map(|(_, s)| similarity > s).unwrap_or(true) {
+          best = Some((i, similarity));
+        }
+      }
+    }
+    best.map(|(i, _)| i)
+  }
+
+  fn band_keys(&self, signature: &Signature) -> Vec<(usize, u64)> {
+    signature.0.chunks(self.rows)
+      .take(self.bands)
+      .enumerate()
+      .map(|(band, rows)| (band, rows.iter().fold(0, |hash, x| splitmix(hash ^ x))))
+      .collect()
+  }
+}
+
+impl Default for SimilarityIndex {
+  fn default() -> Self {
+    SimilarityIndex::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use messages::{Message, MessageType};
+  use messages::parts::Part;
+
+  use std::collections::BTreeMap;
+
+  fn entry(timestamp: u32, text: &str) -> Entry {
+    Entry {
+      message_type: MessageType::Shout,
+      timestamp,
+      sender: None,
+      message: Message::new(vec![Part::PlainText(text.to_owned())]),
+      tags: BTreeMap::new(),
+      provenance: None
+    }
+  }
+
+  const AD: &'static str = "Come visit the best house in Goblet ward 5 plot 12 tonight";
+
+  #[test]
+  fn groups_similar_messages() {
+    let mut index = SimilarityIndex::new();
+    let first = index.add(&entry(0, AD));
+    let second = index.add(&entry(1, &format!("{}!!", AD)));
+    assert!(first.is_some());
+    assert_eq!(first, second);
+    assert_eq!(index.add(&entry(2, "short")), None);
+    assert_eq!(index.clusters().len(), 1);
+    assert_eq!(index.clusters()[0].count(), 2);
+  }
+
+  #[test]
+  fn zero_bands_or_rows_do_not_panic() {
+    for &(bands, rows) in &[(0, 4), (16, 0), (0, 0)] {
+      let mut index = SimilarityIndex::with_bands(bands, rows);
+      assert_eq!(index.add(&entry(0, AD)), Some(0));
+    }
+  }
+
+  #[test]
+  fn evicts_clusters_outside_the_window() {
+    let mut index = SimilarityIndex::new();
+    index.window = Some(60);
+    index.add(&entry(100, AD));
+    index.add(&entry(110, "A completely different message about selling crafted gear cheap"));
+    index.add(&entry(150, "A completely different message about selling crafted gear cheap"));
+    assert_eq!(index.clusters().len(), 2);
+    // The advertisement was last seen more than a window ago.
+    index.add(&entry(200, "A completely different message about selling crafted gear cheap"));
+    assert_eq!(index.clusters().len(), 1);
+    assert_eq!(index.clusters()[0].count(), 3);
+    assert!(index.find(AD).is_none());
+  }
+}
@@ -0,0 +1,161 @@
+//! Sound hooks for entries
+//!
+//! Messages can contain `<se.1>` through `<se.16>` to make the game play a sound effect. The tags
+//! are left in the text of the log, so a [`SoundHook`](struct.SoundHook.html) can find them and do
+//! something in their place, along with anything matching a highlight rule. A
+//! [`CommandPlayer`](struct.CommandPlayer.html) plays sound files with an external program.
+
+use config::{Config, HighlightRule};
+use messages::entries::Entry;
+use pipeline::Sink;
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The highest sound effect number the game has.
+pub const MAX_SOUND_EFFECT: u8 = 16;
+
+/// A sound an entry asked for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sound {
+  /// A sound effect, from `<se.N>`.
+  Effect(u8),
+  /// A highlight rule matched. Contains the name of the rule.
+  Highlight(String)
+}
+
+/// Finds the sound effects in some text, in the order they appear.
+///
+/// Numbers outside of `1` to `16` aren't sound effects, so they are ignored like the game does.
+pub fn sound_effects(text: &str) -> Vec<u8> {
+  let mut effects = Vec::new();
+  let mut rest = text;
+  while let Some(start) = rest.find("<se.") {
+    rest = &rest[start + 4..];
+    let end = opt_or!(rest.find('>'), break);
+    if let Ok(n) = rest[..end].parse::<u8>() {
+      if n >= 1 && n <= MAX_SOUND_EFFECT {
+        effects.push(n);
+      }
+    }
+  }
+  effects
+}
+
+/// A sink that calls a function for every sound an entry asks for.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut hook = SoundHook::new(|sound, entry| println!("{:?} from {:?}", sound, entry.sender_name()));
+/// hook.add_highlight(my_rule);
+/// pipeline.add_sink(hook);
+/// ```
+pub struct SoundHook {
+  highlights: Vec<HighlightRule>,
+  callback: Box<dyn FnMut(&Sound, &Entry) + Send>
+}
+
+impl SoundHook {
+  /// Create a hook calling `callback` for each sound.
+  pub fn new<F>(callback: F) -> Self
+    where F: FnMut(&Sound, &Entry) + Send + 'static
+  {
+    SoundHook {
+      highlights: Vec::new(),
+      callback: Box::new(callback)
+    }
+  }
+
+  /// Adds a highlight rule. Entries matching it give a `Sound::Highlight`.
+  pub fn add_highlight(&mut self, rule: HighlightRule) {
+    self.highlights.push(rule);
+  }
+
+  /// The sounds an entry asks for. Each sound effect is only given once, lowest first.
+  pub fn sounds(&self, entry: &Entry) -> Vec<Sound> {
+    let mut effects = sound_effects(&entry.message.plain_text());
+    effects.sort();
+    effects.dedup();
+    let mut sounds: Vec<Sound> = effects.into_iter().map(Sound::Effect).collect();
+    sounds.extend(self.highlights.iter()
+      .filter(|x| x.matches(entry))
+      .map(|x| Sound::Highlight(x.name.clone())));
+    sounds
+  }
+}
+
+impl Sink for SoundHook {
+  fn handle(&mut self, entry: &Entry) {
+    for sound in self.sounds(entry) {
+      (self.callback)(&sound, entry);
+    }
+  }
+}
+
+/// Plays sound files by running an external program, such as `aplay` or `afplay`.
+#[derive(Debug, Clone)]
+pub struct CommandPlayer {
+  /// The program to run.
+  pub command: String,
+  /// Arguments given to the program before the file.
+  pub args: Vec<String>,
+  /// Sound files for each sound effect.
+  pub effects: BTreeMap<u8, PathBuf>,
+  /// Sound files for each highlight rule, by name.
+  pub highlights: BTreeMap<String, PathBuf>
+}
+
+impl CommandPlayer {
+  /// Create a player running `command` with no sound files.
+  pub fn new<S: Into<String>>(command: S) -> Self {
+    CommandPlayer {
+      command: command.into(),
+      args: Vec::new(),
+      effects: BTreeMap::new(),
+      highlights: BTreeMap::new()
+    }
+  }
+
+  /// Create a player from the `sounds` section and highlight rules of a configuration.
+  ///
+  /// Returns `None` if no command is configured.
+  pub fn from_config(config: &Config) -> Option<CommandPlayer> {
+    let mut player = CommandPlayer::new(opt!(config.sounds.command.clone()));
+    player.args = config.sounds.args.clone();
+    player.effects = config.sounds.effects.iter()
+      .filter_map(|(k, v)| k.parse().ok().map(|n| (n, v.clone())))
+      .collect();
+    player.highlights = config.highlights.iter()
+      .filter_map(|x| x.sound.clone().map(|s| (x.name.clone(), s)))
+      .collect();
+    Some(player)
+  }
+
+  /// The file to play for a sound, if there is one.
+  pub fn file(&self, sound: &Sound) -> Option<&Path> {
+    match *sound {
+      Sound::Effect(n) => self.effects.get(&n),
+      Sound::Highlight(ref name) => self.highlights.get(name)
+    }.map(|x| x.as_path())
+  }
+
+  /// Starts playing the file for a sound, without waiting for it to finish.
+  ///
+  /// Returns whether the program was started.
+  pub fn play(&self, sound: &Sound) -> bool {
+    let file = opt_or!(self.file(sound), return false);
+    Command::new(&self.command).args(&self.args).arg(file).spawn().is_ok()
+  }
+
+  /// Turns this player into a hook that plays sounds for the given highlight rules and every
+  /// sound effect.
+  pub fn into_hook(self, highlights: Vec<HighlightRule>) -> SoundHook {
+    let mut hook = SoundHook::new(move |sound, _| { self.play(sound); });
+    for rule in highlights {
+      hook.add_highlight(rule);
+    }
+    hook
+  }
+}
@@ -0,0 +1,111 @@
+//! Storage in JSON lines files
+//!
+//! Entries are appended to one file per month in a directory, named like `2017-06.jsonl`, which is
+//! the same layout [compaction](../../archive/compact/index.html) writes. The directory can be opened
+//! as an [`Archive`](../../archive/struct.Archive.html) too.
+
+use archive::{Archive, jsonl};
+use daemon::Checkpoint;
+use filter::EntryFilter;
+use messages::entries::Entry;
+use schema::Metadata;
+use storage::Storage;
+
+use chrono::{TimeZone, Utc};
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// The name of the checkpoint file kept in the root of the directory.
+pub const CHECKPOINT_FILE: &'static str = "checkpoint.json";
+
+/// Storage in a directory of JSON lines files.
+pub struct JsonLinesStorage {
+  root: PathBuf,
+  metadata: Metadata,
+  current: Option<(String, BufWriter<File>)>
+}
+
+impl JsonLinesStorage {
+  /// Opens a directory for storage, creating it if it doesn't exist.
+  pub fn open<P: AsRef<Path>>(root: P) -> io::Result<JsonLinesStorage> {
+    JsonLinesStorage::with_metadata(root, Metadata::new(None))
+  }
+
+  /// Opens a directory for storage, starting new files with `metadata`.
+  pub fn with_metadata<P: AsRef<Path>>(root: P, metadata: Metadata) -> io::Result<JsonLinesStorage> {
+    let root = root.as_ref().to_path_buf();
+    fs::create_dir_all(&root)?;
+    Ok(JsonLinesStorage {
+      root,
+      metadata,
+      current: None
+    })
+  }
+
+  /// The directory entries are stored in.
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  fn writer(&mut self, month: &str) -> io::Result<&mut BufWriter<File>> {
+    let open = match self.current {
+      Some((ref m, _)) => m != month,
+      None => true
+    };
+    if open {
+      if let Some((_, mut w)) = self.current.take() {
+        w.flush()?;
+      }
+      let path = self.root.join(format!("{}.jsonl", month));
+      let file = OpenOptions::new().create(true).append(true).open(&path)?;
+      let mut writer = BufWriter::new(file);
+      if writer.get_ref().metadata()?.len() == 0 {
+        jsonl::write_header(&mut writer, &self.metadata)?;
+      } else if let Some(existing) = jsonl::read_metadata(&path)? {
+        existing.check().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+      }
+      self.current = Some((month.to_owned(), writer));
+    }
+    Ok(&mut self.current.as_mut().unwrap().1)
+  }
+}
+
+impl Storage for JsonLinesStorage {
+  fn append(&mut self, entries: &[Entry]) -> io::Result<()> {
+    for entry in entries {
+      let month = Utc.timestamp(entry.timestamp as i64, 0).format("%Y-%m").to_string();
+      jsonl::write_entries(self.writer(&month)?, Some(entry))?;
+    }
+    // Flush after every batch, so queries see what was appended.
+    self.flush()
+  }
+
+  fn query<'a>(&'a self, range: Range<u32>, filter: &EntryFilter) -> io::Result<Box<dyn Iterator<Item = Entry> + 'a>> {
+    let archive = Archive::open(&self.root)?;
+    let mut entries: Vec<Entry> = archive.query(range, filter.clone()).collect();
+    entries.sort_by_key(|x| x.timestamp);
+    Ok(Box::new(entries.into_iter()))
+  }
+
+  fn checkpoint(&self) -> io::Result<Option<Checkpoint>> {
+    let path = self.root.join(CHECKPOINT_FILE);
+    if !path.exists() {
+      return Ok(None);
+    }
+    Checkpoint::load(path).map(Some)
+  }
+
+  fn save_checkpoint(&mut self, checkpoint: &Checkpoint) -> io::Result<()> {
+    checkpoint.save(self.root.join(CHECKPOINT_FILE))
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    match self.current {
+      Some((_, ref mut w)) => w.flush(),
+      None => Ok(())
+    }
+  }
+}
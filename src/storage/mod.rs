@@ -0,0 +1,129 @@
+//! Storage backends for entries
+//!
+//! A [`Storage`](trait.Storage.html) is anywhere entries can be appended to and queried from later,
+//! along with a [`Checkpoint`](../daemon/struct.Checkpoint.html) of how far capture has gotten.
+//! Applications with their own database can implement it and still use the capture pipeline, through
+//! a [`StorageSink`](struct.StorageSink.html), and the same queries as everything else.
+//!
+//! JSON lines files are always available. SQLite is available with the `sqlite` feature.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! let storage = JsonLinesStorage::open("logs").unwrap();
+//! pipeline.add_sink(StorageSink::new(storage));
+//! ```
+
+pub mod jsonl;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use self::jsonl::JsonLinesStorage;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteStorage;
+
+use daemon::Checkpoint;
+use filter::EntryFilter;
+use messages::entries::Entry;
+use pipeline::Sink;
+
+use std::io;
+use std::ops::Range;
+
+/// Somewhere entries can be kept.
+pub trait Storage: Send {
+  /// Appends entries. Entries are expected to be roughly in timestamp order.
+  fn append(&mut self, entries: &[Entry]) -> io::Result<()>;
+
+  /// Queries for entries in the time range that match the filter, in timestamp order.
+  fn query<'a>(&'a self, range: Range<u32>, filter: &EntryFilter) -> io::Result<Box<dyn Iterator<Item = Entry> + 'a>>;
+
+  /// The last saved checkpoint, if any.
+  fn checkpoint(&self) -> io::Result<Option<Checkpoint>>;
+
+  /// Saves a checkpoint, replacing any saved before.
+  fn save_checkpoint(&mut self, checkpoint: &Checkpoint) -> io::Result<()>;
+
+  /// Makes sure everything appended has been written.
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+impl<S: Storage + ?Sized> Storage for Box<S> {
+  fn append(&mut self, entries: &[Entry]) -> io::Result<()> {
+    (**self).append(entries)
+  }
+
+  fn query<'a>(&'a self, range: Range<u32>, filter: &EntryFilter) -> io::Result<Box<dyn Iterator<Item = Entry> + 'a>> {
+    (**self).query(range, filter)
+  }
+
+  fn checkpoint(&self) -> io::Result<Option<Checkpoint>> {
+    (**self).checkpoint()
+  }
+
+  fn save_checkpoint(&mut self, checkpoint: &Checkpoint) -> io::Result<()> {
+    (**self).save_checkpoint(checkpoint)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    (**self).flush()
+  }
+}
+
+/// A sink appending entries to storage in batches.
+///
+/// Entries that fail to be stored are counted and dropped, since sinks can't return errors.
+pub struct StorageSink<S> {
+  storage: S,
+  batch: Vec<Entry>,
+  /// The number of entries kept in memory before appending them.
+  pub batch_size: usize,
+  /// The number of entries that couldn't be stored.
+  pub failed: usize
+}
+
+impl<S: Storage> StorageSink<S> {
+  pub fn new(storage: S) -> Self {
+    StorageSink {
+      storage,
+      batch: Vec::new(),
+      batch_size: 64,
+      failed: 0
+    }
+  }
+
+  /// The storage entries are appended to.
+  pub fn storage(&self) -> &S {
+    &self.storage
+  }
+
+  fn append_batch(&mut self) {
+    if self.batch.is_empty() {
+      return;
+    }
+    if self.storage.append(&self.batch).is_err() {
+      self.failed += self.batch.len();
+    }
+    self.batch.clear();
+  }
+}
+
+impl<S: Storage> Sink for StorageSink<S> {
+  fn handle(&mut self, entry: &Entry) {
+    self.batch.push(entry.clone());
+    if self.batch.len() >= self.batch_size {
+      self.append_batch();
+    }
+  }
+
+  fn flush(&mut self) {
+    self.append_batch();
+    let _ = self.storage.flush();
+  }
+
+  fn sync(&mut self) {
+    self.flush();
+  }
+}
@@ -0,0 +1,139 @@
+//! Storage in a SQLite database
+//!
+//! Entries are kept in an `entries` table, with their timestamp, message type ID, sender, and
+//! dedup key in columns so other programs can query them. The whole entry is kept as JSON in the
+//! `entry` column. A `metadata` table holds the [schema header](../../schema/index.html) and the
+//! checkpoint.
+//!
+//! Only available with the `sqlite` feature.
+
+extern crate rusqlite;
+
+use self::rusqlite::{Connection, Error as SqliteError, ToSql};
+
+use daemon::Checkpoint;
+use filter::EntryFilter;
+use messages::entries::Entry;
+use schema::Metadata;
+use storage::Storage;
+
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+const SCHEMA: &'static str = "
+  CREATE TABLE IF NOT EXISTS entries (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    message_type INTEGER NOT NULL,
+    sender TEXT,
+    dedup_key INTEGER NOT NULL,
+    entry TEXT NOT NULL
+  );
+  CREATE INDEX IF NOT EXISTS entries_timestamp ON entries (timestamp);
+  CREATE TABLE IF NOT EXISTS metadata (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+  );
+";
+
+/// Storage in a SQLite database.
+pub struct SqliteStorage {
+  connection: Connection
+}
+
+impl SqliteStorage {
+  /// Opens a database, creating it if it doesn't exist.
+  ///
+  /// New databases are given a header for this version of the crate. Databases from a newer schema
+  /// version are refused.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<SqliteStorage> {
+    SqliteStorage::from_connection(Connection::open(path).map_err(to_io)?, &Metadata::new(None))
+  }
+
+  /// Uses an open connection for storage, giving its database `metadata` if it has no header.
+  pub fn from_connection(connection: Connection, metadata: &Metadata) -> io::Result<SqliteStorage> {
+    connection.execute_batch(SCHEMA).map_err(to_io)?;
+    let storage = SqliteStorage {
+      connection
+    };
+    match storage.get_metadata("header")? {
+      Some(line) => {
+        let existing = opt_or!(Metadata::from_line(&line), return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid header")));
+        existing.check().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+      },
+      None => storage.set_metadata("header", &metadata.to_line())?
+    }
+    Ok(storage)
+  }
+
+  /// The connection to the database.
+  pub fn connection(&self) -> &Connection {
+    &self.connection
+  }
+
+  fn get_metadata(&self, key: &str) -> io::Result<Option<String>> {
+    let result = self.connection.query_row("SELECT value FROM metadata WHERE key = ?", &[&key as &dyn ToSql], |row| row.get(0));
+    match result {
+      Ok(v) => Ok(Some(v)),
+      Err(SqliteError::QueryReturnedNoRows) => Ok(None),
+      Err(e) => Err(to_io(e))
+    }
+  }
+
+  fn set_metadata(&self, key: &str, value: &str) -> io::Result<()> {
+    self.connection.execute("INSERT OR REPLACE INTO metadata (key, value) VALUES (?, ?)", &[&key as &dyn ToSql, &value])
+      .map(|_| ())
+      .map_err(to_io)
+  }
+}
+
+impl Storage for SqliteStorage {
+  fn append(&mut self, entries: &[Entry]) -> io::Result<()> {
+    let transaction = self.connection.transaction().map_err(to_io)?;
+    {
+      let mut statement = transaction.prepare("INSERT INTO entries (timestamp, message_type, sender, dedup_key, entry) VALUES (?, ?, ?, ?, ?)").map_err(to_io)?;
+      for entry in entries {
+        let json = ::serde_json::to_string(entry).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let timestamp = entry.timestamp as i64;
+        let message_type = entry.message_type.id() as i64;
+        let sender = entry.sender_name();
+        // SQLite only has signed integers, so the key is stored with the same bits as an i64.
+        let dedup_key = entry.dedup_key() as i64;
+        statement.execute(&[&timestamp as &dyn ToSql, &message_type, &sender, &dedup_key, &json]).map_err(to_io)?;
+      }
+    }
+    transaction.commit().map_err(to_io)
+  }
+
+  fn query<'a>(&'a self, range: Range<u32>, filter: &EntryFilter) -> io::Result<Box<dyn Iterator<Item = Entry> + 'a>> {
+    let mut statement = self.connection.prepare("SELECT entry FROM entries WHERE timestamp >= ? AND timestamp < ? ORDER BY timestamp, id").map_err(to_io)?;
+    let start = range.start as i64;
+    let end = range.end as i64;
+    let rows = statement.query_map(&[&start as &dyn ToSql, &end], |row| row.get::<_, String>(0)).map_err(to_io)?;
+    let mut entries = Vec::new();
+    for json in rows {
+      let json = json.map_err(to_io)?;
+      // Rows that aren't entries written by a compatible version are skipped, like in JSON lines.
+      let entry: Entry = try_or!(::serde_json::from_str(&json), continue);
+      if filter.matches(&entry) {
+        entries.push(entry);
+      }
+    }
+    Ok(Box::new(entries.into_iter()))
+  }
+
+  fn checkpoint(&self) -> io::Result<Option<Checkpoint>> {
+    let data = opt_or!(self.get_metadata("checkpoint")?, return Ok(None));
+    ::serde_json::from_str(&data).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+  }
+
+  fn save_checkpoint(&mut self, checkpoint: &Checkpoint) -> io::Result<()> {
+    let data = ::serde_json::to_string(checkpoint).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    self.set_metadata("checkpoint", &data)
+  }
+}
+
+fn to_io(e: SqliteError) -> io::Error {
+  io::Error::new(io::ErrorKind::Other, e)
+}
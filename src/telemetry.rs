@@ -0,0 +1,240 @@
+//! Reports of unknown payloads
+//!
+//! Each expansion adds payloads, the structures starting with `0x02` in messages, that the parser
+//! doesn't know yet. They end up as stray bytes in the text until someone works out what they are.
+//! A [`PayloadCollector`](struct.PayloadCollector.html) keeps a local report of the unknown payloads
+//! it sees, which users can choose to send along so support for them comes sooner.
+//!
+//! Nothing is collected unless a report file is configured, and nothing is ever sent anywhere. The
+//! report holds only the marker byte, the length, and the [shape](struct.PayloadShape.html) of each
+//! payload, along with which channels it was seen in and how often. Shapes describe bytes by what
+//! kind they are, never by their values, so no text can be recovered from a report.
+//!
+//! ```toml
+//! [telemetry]
+//! payload_report = "unknown_payloads.json"
+//! ```
+
+use messages::MessageType;
+use messages::entries::Entry;
+use messages::parts::Part;
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The most distinct shapes kept in a report, so a badly broken format can't fill the disk.
+pub const MAX_SHAPES: usize = 1000;
+
+/// The most bytes of a payload described in its shape.
+pub const MAX_SHAPE_BYTES: usize = 64;
+
+/// The structure of a payload, without its contents.
+///
+/// The shape has one character per byte of the payload, or per run of bytes for ordinary ones:
+///
+/// - `<` and `>`: the `0x02` and `0x03` that start and end payloads
+/// - `i`: `0xf0` to `0xfe`, which the game uses to mark the width of integers
+/// - `c`: any other control byte
+/// - `b`: a run of other bytes, however long
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PayloadShape {
+  /// The byte after `0x02` that says what kind of payload it is.
+  pub marker: u8,
+  /// The length the payload gives for itself.
+  pub length: u8,
+  pub shape: String
+}
+
+impl PayloadShape {
+  /// Describes the payload at the start of `bytes`, if there is one.
+  pub fn of(bytes: &[u8]) -> Option<PayloadShape> {
+    if bytes.len() < 3 || bytes[0] != 0x02 {
+      return None;
+    }
+    let length = bytes[2];
+    // The payload is the marker, the length, the data, and the 0x03 at the end.
+    let end = (3 + length as usize + 1).min(bytes.len()).min(MAX_SHAPE_BYTES);
+    let mut shape = String::with_capacity(end);
+    for &byte in &bytes[3..end] {
+      let class = match byte {
+        0x02 => '<',
+        0x03 => '>',
+        0xf0..=0xfe => 'i',
+        0x00..=0x1f => 'c',
+        _ => 'b'
+      };
+      if class == 'b' && shape.ends_with('b') {
+        continue;
+      }
+      shape.push(class);
+    }
+    Some(PayloadShape {
+      marker: bytes[1],
+      length,
+      shape
+    })
+  }
+}
+
+/// Finds the payloads in an entry the parser didn't recognize.
+///
+/// Recognized payloads become their own parts, so any `0x02` left in text or raw bytes is the start
+/// of one it didn't.
+pub fn unknown_payloads(entry: &Entry) -> Vec<PayloadShape> {
+  let mut shapes = Vec::new();
+  for part in entry.sender.iter().chain(entry.message.parts.iter()) {
+    find_payloads(part, &mut shapes);
+  }
+  shapes
+}
+
+fn find_payloads(part: &Part, shapes: &mut Vec<PayloadShape>) {
+  let bytes = match *part {
+    Part::PlainText(ref t) => t.as_bytes(),
+    Part::Bytes(ref b) => b.as_slice(),
+    Part::Name { ref real_name, ref display_name } => {
+      find_payloads(real_name, shapes);
+      find_payloads(display_name, shapes);
+      return;
+    },
+    Part::Colored { ref display, .. }
+      | Part::Formatted { ref display, .. }
+      | Part::Link { ref display, .. } => return find_payloads(display, shapes),
+    Part::Multi(ref parts) => {
+      for part in parts {
+        find_payloads(part, shapes);
+      }
+      return;
+    },
+    _ => return
+  };
+  for (i, _) in bytes.iter().enumerate().filter(|&(_, b)| *b == 0x02) {
+    if let Some(shape) = PayloadShape::of(&bytes[i..]) {
+      shapes.push(shape);
+    }
+  }
+}
+
+/// How often a shape has been seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayloadRecord {
+  pub shape: PayloadShape,
+  /// The number of times the shape was seen.
+  pub count: u64,
+  /// The channels the shape was seen in.
+  pub channels: Vec<MessageType>,
+  /// When the shape was first seen, in seconds since the epoch.
+  pub first_seen: u64,
+  /// When the shape was last seen, in seconds since the epoch.
+  pub last_seen: u64
+}
+
+/// A report of unknown payloads, as saved to disk.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PayloadReport {
+  /// The version of the crate that last wrote the report.
+  pub crate_version: String,
+  /// The number of entries looked at.
+  pub entries: u64,
+  /// The shapes seen, most common first.
+  pub payloads: Vec<PayloadRecord>
+}
+
+/// Collects unknown payloads into a report file.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut collector = PayloadCollector::open("unknown_payloads.json").unwrap();
+/// for entry in entries {
+///   collector.observe(&entry);
+/// }
+/// collector.save().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct PayloadCollector {
+  path: PathBuf,
+  report: PayloadReport,
+  changed: bool
+}
+
+impl PayloadCollector {
+  /// Opens a report file, adding to it if it already exists.
+  pub fn open<P: AsRef<Path>>(path: P) -> io::Result<PayloadCollector> {
+    let path = path.as_ref().to_path_buf();
+    let mut data = String::new();
+    let report = match File::open(&path) {
+      Ok(mut f) => {
+        f.read_to_string(&mut data)?;
+        ::serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+      },
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => PayloadReport::default(),
+      Err(e) => return Err(e)
+    };
+    Ok(PayloadCollector {
+      path,
+      report,
+      changed: false
+    })
+  }
+
+  /// The path of the report file.
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// The report so far.
+  pub fn report(&self) -> &PayloadReport {
+    &self.report
+  }
+
+  /// Records the unknown payloads in an entry, returning how many there were.
+  pub fn observe(&mut self, entry: &Entry) -> usize {
+    self.report.entries += 1;
+    self.changed = true;
+    let shapes = unknown_payloads(entry);
+    if shapes.is_empty() {
+      return 0;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+    for shape in &shapes {
+      if let Some(record) = self.report.payloads.iter_mut().find(|x| x.shape == *shape) {
+        record.count += 1;
+        record.last_seen = now;
+        if !record.channels.contains(&entry.message_type) {
+          record.channels.push(entry.message_type);
+        }
+        continue;
+      }
+      if self.report.payloads.len() < MAX_SHAPES {
+        self.report.payloads.push(PayloadRecord {
+          shape: shape.clone(),
+          count: 1,
+          channels: vec![entry.message_type],
+          first_seen: now,
+          last_seen: now
+        });
+      }
+    }
+    shapes.len()
+  }
+
+  /// Writes the report file, if anything has changed since it was last written.
+  ///
+  /// The report is written to a temporary file first, so a crash never leaves half a report.
+  pub fn save(&mut self) -> io::Result<()> {
+    if !self.changed {
+      return Ok(());
+    }
+    self.report.crate_version = env!("CARGO_PKG_VERSION").to_owned();
+    self.report.payloads.sort_by(|a, b| b.count.cmp(&a.count));
+    let data = ::serde_json::to_string_pretty(&self.report).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let temp = self.path.with_extension("tmp");
+    File::create(&temp)?.write_all(data.as_bytes())?;
+    fs::rename(&temp, &self.path)?;
+    self.changed = false;
+    Ok(())
+  }
+}
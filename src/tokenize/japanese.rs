@@ -0,0 +1,69 @@
+//! Japanese words from a morphological analyzer
+//!
+//! This needs the `japanese` feature, which uses [lindera](https://github.com/lindera-morphology/lindera)
+//! and its built-in IPADIC dictionary.
+
+extern crate lindera;
+
+use self::lindera::tokenizer::Tokenizer as Analyzer;
+
+use tokenize::{Script, ScriptTokenizer, Tokenizer, script_runs};
+
+use std::cell::RefCell;
+
+/// Splits Japanese text into the words found by a morphological analyzer.
+///
+/// Only runs of Japanese are given to the analyzer. Everything else is split like the
+/// [`ScriptTokenizer`](../struct.ScriptTokenizer.html) does, so English in the same message isn't
+/// mangled.
+pub struct JapaneseTokenizer {
+  analyzer: RefCell<Analyzer>,
+  fallback: ScriptTokenizer
+}
+
+impl JapaneseTokenizer {
+  /// Create a tokenizer using the built-in dictionary.
+  pub fn new() -> Self {
+    JapaneseTokenizer {
+      analyzer: RefCell::new(Analyzer::new("normal", "")),
+      fallback: ScriptTokenizer::new()
+    }
+  }
+
+  fn analyze(&self, japanese: &mut String, tokens: &mut Vec<String>) {
+    if japanese.is_empty() {
+      return;
+    }
+    let mut analyzer = self.analyzer.borrow_mut();
+    tokens.extend(analyzer.tokenize(japanese).into_iter()
+      .map(|x| x.text.to_lowercase())
+      .filter(|x| x.chars().any(|c| Script::of(c) != Script::Other)));
+    japanese.clear();
+  }
+}
+
+impl Default for JapaneseTokenizer {
+  fn default() -> Self {
+    JapaneseTokenizer::new()
+  }
+}
+
+impl Tokenizer for JapaneseTokenizer {
+  fn tokens(&self, text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    // Adjacent runs of Japanese scripts are analyzed together, since words mix kanji and kana.
+    let mut japanese = String::new();
+    for (script, run) in script_runs(text) {
+      if script.is_unspaced() {
+        japanese.push_str(run);
+        continue;
+      }
+      self.analyze(&mut japanese, &mut tokens);
+      if script != Script::Other {
+        tokens.extend(self.fallback.tokens(run));
+      }
+    }
+    self.analyze(&mut japanese, &mut tokens);
+    tokens
+  }
+}
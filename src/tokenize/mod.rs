@@ -0,0 +1,169 @@
+//! Splitting message text into words
+//!
+//! Japanese and Chinese are written without spaces, so splitting on whitespace would make a whole
+//! Japanese message one word. A [`Tokenizer`](trait.Tokenizer.html) splits text in a way that works
+//! for every language the game is played in, for word counts and [search](../search/index.html).
+//!
+//! The [`ScriptTokenizer`](struct.ScriptTokenizer.html) is always available. It splits text where
+//! the script changes, such as from kanji to hiragana, and can split runs of Han and kana into
+//! overlapping pairs of characters, which is crude but needs no dictionary. With the `japanese`
+//! feature, the [`JapaneseTokenizer`](struct.JapaneseTokenizer.html) uses a morphological analyzer
+//! to find real Japanese words, and [`default_tokenizer`](fn.default_tokenizer.html) uses it.
+
+#[cfg(feature = "japanese")]
+pub mod japanese;
+
+#[cfg(feature = "japanese")]
+pub use self::japanese::JapaneseTokenizer;
+
+use messages::entries::Entry;
+
+use std::collections::HashMap;
+
+/// Something that can split text into words.
+pub trait Tokenizer {
+  /// Splits text into lowercased words, in the order they appear.
+  ///
+  /// Punctuation, symbols, and the game's private use glyphs are never part of words.
+  fn tokens(&self, text: &str) -> Vec<String>;
+}
+
+impl<'a, T: Tokenizer + ?Sized> Tokenizer for &'a T {
+  fn tokens(&self, text: &str) -> Vec<String> {
+    (**self).tokens(text)
+  }
+}
+
+impl<T: Tokenizer + ?Sized> Tokenizer for Box<T> {
+  fn tokens(&self, text: &str) -> Vec<String> {
+    (**self).tokens(text)
+  }
+}
+
+/// The best tokenizer available, which is the
+/// [`JapaneseTokenizer`](struct.JapaneseTokenizer.html) with the `japanese` feature and the
+/// [`ScriptTokenizer`](struct.ScriptTokenizer.html) with bigrams otherwise.
+#[cfg(feature = "japanese")]
+pub fn default_tokenizer() -> Box<dyn Tokenizer> {
+  Box::new(JapaneseTokenizer::new())
+}
+
+/// The best tokenizer available, which is the
+/// [`JapaneseTokenizer`](struct.JapaneseTokenizer.html) with the `japanese` feature and the
+/// [`ScriptTokenizer`](struct.ScriptTokenizer.html) with bigrams otherwise.
+#[cfg(not(feature = "japanese"))]
+pub fn default_tokenizer() -> Box<dyn Tokenizer> {
+  let mut tokenizer = ScriptTokenizer::new();
+  tokenizer.bigrams = true;
+  Box::new(tokenizer)
+}
+
+/// The writing system a character belongs to, as far as splitting words is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+  /// Latin, Cyrillic, Greek, and digits, which separate words with spaces.
+  Alphabetic,
+  Hiragana,
+  Katakana,
+  /// Kanji and hanzi.
+  Han,
+  Hangul,
+  /// Anything that isn't part of a word.
+  Other
+}
+
+impl Script {
+  /// The script of a character.
+  pub fn of(c: char) -> Script {
+    match c as u32 {
+      0x3041..=0x309f => Script::Hiragana,
+      // The prolonged sound mark is used with both kana, but mostly with katakana.
+      0x30a0..=0x30ff | 0x31f0..=0x31ff | 0xff66..=0xff9f => Script::Katakana,
+      0x3400..=0x4dbf | 0x4e00..=0x9fff | 0xf900..=0xfaff | 0x3005 => Script::Han,
+      0xac00..=0xd7af | 0x1100..=0x11ff | 0x3130..=0x318f => Script::Hangul,
+      _ if c.is_alphanumeric() => Script::Alphabetic,
+      _ => Script::Other
+    }
+  }
+
+  /// Checks if the script is written without spaces between words.
+  pub fn is_unspaced(&self) -> bool {
+    match *self {
+      Script::Hiragana | Script::Katakana | Script::Han => true,
+      _ => false
+    }
+  }
+}
+
+/// Splits text where the script changes and at anything that isn't a letter or digit.
+///
+/// Korean is written with spaces, so Hangul is split like alphabetic text.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptTokenizer {
+  /// Whether runs of Han and kana become overlapping pairs of characters instead of one word.
+  ///
+  /// Pairs let a search for any two or more characters find them inside a longer run, at the cost
+  /// of many meaningless words in counts.
+  pub bigrams: bool
+}
+
+impl ScriptTokenizer {
+  pub fn new() -> Self {
+    ScriptTokenizer::default()
+  }
+}
+
+impl Tokenizer for ScriptTokenizer {
+  fn tokens(&self, text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for (script, run) in script_runs(text) {
+      if script == Script::Other {
+        continue;
+      }
+      let chars: Vec<char> = run.chars().collect();
+      if self.bigrams && script.is_unspaced() && chars.len() > 2 {
+        tokens.extend(chars.windows(2).map(|x| x.iter().collect::<String>()));
+      } else {
+        tokens.push(run.to_lowercase());
+      }
+    }
+    tokens
+  }
+}
+
+/// Splits text into runs of characters of the same script.
+pub fn script_runs(text: &str) -> Vec<(Script, &str)> {
+  let mut runs = Vec::new();
+  let mut start = 0;
+  let mut current = None;
+  for (i, c) in text.char_indices() {
+    let script = Script::of(c);
+    if let Some(s) = current {
+      if s != script {
+        runs.push((s, &text[start..i]));
+        start = i;
+      }
+    }
+    current = Some(script);
+  }
+  if let Some(s) = current {
+    runs.push((s, &text[start..]));
+  }
+  runs
+}
+
+/// Counts how many times each word appears in the messages of entries, most common first.
+pub fn word_frequencies<'a, I, T>(entries: I, tokenizer: &T) -> Vec<(String, usize)>
+  where I: IntoIterator<Item = &'a Entry>,
+        T: Tokenizer + ?Sized
+{
+  let mut counts: HashMap<String, usize> = HashMap::new();
+  for entry in entries {
+    for token in tokenizer.tokens(&entry.message.plain_text()) {
+      *counts.entry(token).or_insert(0) += 1;
+    }
+  }
+  let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+  counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  counts
+}
@@ -0,0 +1,216 @@
+//! Noticing when capture silently stops
+//!
+//! Readers never fail loudly. When the game's chat log stops updating, or the ACT log being tailed is
+//! rotated or deleted, they just stop producing entries, and a daemon left running looks healthy
+//! while capturing nothing. A [`Watchdog`](struct.Watchdog.html) watches for these conditions and
+//! reports each one once as a [`WatchdogEvent`](enum.WatchdogEvent.html), so they can be shown to the
+//! user.
+
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the watched file is checked, at most, in seconds.
+const FILE_CHECK_INTERVAL: u64 = 1;
+
+/// Something wrong, or no longer wrong, with capture.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchdogEvent {
+  /// The source is still connected, but no entries have arrived for `idle` seconds.
+  Stalled {
+    idle: u64
+  },
+  /// Entries arrived again after a stall of `idle` seconds.
+  Resumed {
+    idle: u64
+  },
+  /// The log file being read is no longer the one at its path, so nothing new will be read from it.
+  FileInvalidated {
+    path: PathBuf,
+    reason: Invalidation
+  },
+  /// The source stopped sending entries, which happens when the game closes.
+  Disconnected
+}
+
+impl WatchdogEvent {
+  /// Checks if the event means capture isn't working.
+  pub fn is_problem(&self) -> bool {
+    match *self {
+      WatchdogEvent::Resumed { .. } => false,
+      _ => true
+    }
+  }
+}
+
+impl Display for WatchdogEvent {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match *self {
+      WatchdogEvent::Stalled { idle } => write!(f, "no new entries for {} minutes", idle / 60),
+      WatchdogEvent::Resumed { idle } => write!(f, "entries resumed after {} minutes", idle / 60),
+      WatchdogEvent::FileInvalidated { ref path, reason } => write!(f, "{} was {}", path.display(), reason),
+      WatchdogEvent::Disconnected => write!(f, "the source stopped sending entries")
+    }
+  }
+}
+
+/// Why a watched file is no longer being read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Invalidation {
+  /// Nothing is at the path anymore.
+  Removed,
+  /// A different file is at the path, such as after a log was rotated.
+  Replaced,
+  /// The file got shorter, so it was cleared or rewritten.
+  Truncated
+}
+
+impl Display for Invalidation {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    let name = match *self {
+      Invalidation::Removed => "removed",
+      Invalidation::Replaced => "replaced",
+      Invalidation::Truncated => "truncated"
+    };
+    write!(f, "{}", name)
+  }
+}
+
+#[derive(Debug)]
+struct WatchedFile {
+  path: PathBuf,
+  size: u64,
+  id: Option<u64>,
+  created: Option<SystemTime>
+}
+
+/// Watches a reader for stalls and its log file, if it has one, for being invalidated.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let mut watchdog = Watchdog::new(Duration::from_secs(15 * 60));
+/// watchdog.watch_file("Network.log").unwrap();
+/// loop {
+///   match rx.recv_timeout(Duration::from_millis(100)) {
+///     Ok(entry) => if let Some(event) = watchdog.entry() {
+///       println!("{}", event);
+///     },
+///     Err(RecvTimeoutError::Timeout) => {},
+///     Err(RecvTimeoutError::Disconnected) => break
+///   }
+///   for event in watchdog.check() {
+///     println!("{}", event);
+///   }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Watchdog {
+  /// How long without entries counts as a stall.
+  pub stall_after: Duration,
+  file: Option<WatchedFile>,
+  last_entry: Instant,
+  last_file_check: Instant,
+  stalled: bool,
+  invalidated: bool
+}
+
+impl Watchdog {
+  pub fn new(stall_after: Duration) -> Self {
+    Watchdog {
+      stall_after,
+      file: None,
+      last_entry: Instant::now(),
+      last_file_check: Instant::now(),
+      stalled: false,
+      invalidated: false
+    }
+  }
+
+  /// Watches the log file at `path`, which should be the one the reader has open.
+  pub fn watch_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let metadata = fs::metadata(&path)?;
+    self.file = Some(WatchedFile {
+      path,
+      size: metadata.len(),
+      id: file_id(&metadata),
+      created: metadata.created().ok()
+    });
+    self.invalidated = false;
+    Ok(())
+  }
+
+  /// Records that an entry arrived, returning an event if it ended a stall.
+  pub fn entry(&mut self) -> Option<WatchdogEvent> {
+    let idle = self.last_entry.elapsed().as_secs();
+    self.last_entry = Instant::now();
+    if self.stalled {
+      self.stalled = false;
+      return Some(WatchdogEvent::Resumed {
+        idle
+      });
+    }
+    None
+  }
+
+  /// Checks for new problems. Each problem is only reported once, when it starts.
+  pub fn check(&mut self) -> Vec<WatchdogEvent> {
+    let mut events = Vec::new();
+    let idle = self.last_entry.elapsed();
+    if !self.stalled && self.stall_after > Duration::from_secs(0) && idle >= self.stall_after {
+      self.stalled = true;
+      events.push(WatchdogEvent::Stalled {
+        idle: idle.as_secs()
+      });
+    }
+    if !self.invalidated && self.last_file_check.elapsed().as_secs() >= FILE_CHECK_INTERVAL {
+      self.last_file_check = Instant::now();
+      if let Some(event) = self.check_file() {
+        self.invalidated = true;
+        events.push(event);
+      }
+    }
+    events
+  }
+
+  /// Checks if no problem has been found since the last entry or file change.
+  pub fn is_healthy(&self) -> bool {
+    !self.stalled && !self.invalidated
+  }
+
+  fn check_file(&mut self) -> Option<WatchdogEvent> {
+    let file = opt!(self.file.as_mut());
+    let reason = match fs::metadata(&file.path) {
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => Invalidation::Removed,
+      // The file may just be locked for a moment, so only missing files count.
+      Err(_) => return None,
+      Ok(ref m) if file_id(m) != file.id || m.created().ok() != file.created => Invalidation::Replaced,
+      Ok(ref m) if m.len() < file.size => Invalidation::Truncated,
+      Ok(m) => {
+        file.size = m.len();
+        return None;
+      }
+    };
+    Some(WatchdogEvent::FileInvalidated {
+      path: file.path.clone(),
+      reason
+    })
+  }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<u64> {
+  use std::os::unix::fs::MetadataExt;
+  Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_: &Metadata) -> Option<u64> {
+  None
+}